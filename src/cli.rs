@@ -0,0 +1,43 @@
+use std::{fs::read_to_string, path::Path};
+
+use anyhow::{bail, Result};
+use serde_json::{from_str, to_string_pretty, Value};
+
+use crate::utils::battle_replay::{decode_replay_timeline, decrypt_battle_replay};
+
+const USAGE: &str = "Usage: terra-ps replay-timeline <exported replay file | encoded replay>";
+
+/// Runs a command-line subcommand instead of the server, if the arguments name one.
+pub fn run(args: &[String]) -> Option<Result<()>> {
+    match args.first().map(String::as_str) {
+        Some("replay-timeline") => Some(replay_timeline(args.get(1))),
+        _ => None,
+    }
+}
+
+/// Accepts an exported replay file, a decrypted replay JSON file, or the encoded replay itself.
+fn load_replay(input: &str) -> Result<Value> {
+    let contents = if Path::new(input).is_file() {
+        read_to_string(input)?
+    } else {
+        input.to_string()
+    };
+    let contents = contents.trim();
+    match from_str::<Value>(contents) {
+        Ok(replay) if replay["journal"].is_object() => Ok(replay),
+        Ok(exported) => match exported["battleReplay"].as_str() {
+            Some(encoded_battle_replay) => decrypt_battle_replay(encoded_battle_replay.to_string()),
+            None => bail!("No battleReplay found in {input}."),
+        },
+        Err(_) => decrypt_battle_replay(contents.to_string()),
+    }
+}
+
+fn replay_timeline(input: Option<&String>) -> Result<()> {
+    let Some(input) = input else {
+        bail!(USAGE);
+    };
+    let replay = load_replay(input)?;
+    println!("{}", to_string_pretty(&decode_replay_timeline(&replay))?);
+    Ok(())
+}
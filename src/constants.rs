@@ -7,6 +7,8 @@ pub mod config {
     pub const RLV2_CONFIG_PATH: &str = "./config/rlv2Config.json";
     pub const ASSIST_JSON_PATH: &str = "./config/assist.json";
     pub const SQUADS_PATH: &str = "./config/squads.json";
+    pub const DIY_PRESETS_PATH: &str = "./config/diyPresets.json";
+    pub const PROFILE_PATH: &str = "./config/profile.json";
     pub const SYNC_DATA_TEMPLATE_PATH: &str = "./config/syncData.json";
 }
 
@@ -15,6 +17,12 @@ pub mod config {
 pub mod user {
     pub const USER_JSON_PATH: &str = "./data/user/user.json";
     pub const BATTLE_REPLAY_JSON_PATH: &str = "./data/user/battleReplays.json";
+    pub const BATTLE_REPLAY_EXPORT_PATH: &str = "./data/user/replays/";
+    pub const BATTLE_STATS_JSON_PATH: &str = "./data/user/battleStats.json";
+    pub const BATTLE_JSON_PATH: &str = "./data/user/battle.json";
+    pub const MEDAL_COUNTERS_JSON_PATH: &str = "./data/user/medalCounters.json";
+    pub const SQUAD_EDITS_JSON_PATH: &str = "./data/user/squads.json";
+    pub const MISSION_JSON_PATH: &str = "./data/user/mission.json";
     pub const RLV2_JSON_PATH: &str = "./data/user/rlv2.json";
     pub const RLV2_TEMPBUFF_JSON_PATH: &str = "./data/user/rlv2TempBuffs.json";
     pub const RLV2_USER_SETTINGS_PATH: &str = "data/user/rlv2UserSettings.json";
@@ -22,7 +30,9 @@ pub mod user {
     pub const CRISIS_V2_JSON_BASE_PATH: &str = "./data/crisisV2/";
     pub const RUNE_JSON_PATH: &str = "./data/user/rune.json";
     pub const BUILDING_JSON_PATH: &str = "./data/user/building.json";
+    pub const PLAYERS_PATH: &str = "./data/players/";
     pub const GACHA_TEMPLATE_JSON_PATH: &str = "./data/gacha/gacha.json";
+    pub const GACHA_JSON_PATH: &str = "./data/user/gacha.json";
 }
 
 // RLV2 Options
@@ -58,9 +68,11 @@ pub mod url {
 
     // TABLE Urls
     pub const ACTIVITY_TABLE_URL: &str = concat!(BASE_URL, "/excel/activity_table.json");
+    pub const CHECKIN_TABLE_URL: &str = concat!(BASE_URL, "/excel/checkin_table.json");
     pub const CHARM_TABLE_URL: &str = concat!(BASE_URL, "/excel/charm_table.json");
     pub const SKIN_TABLE_URL: &str = concat!(BASE_URL, "/excel/skin_table.json");
     pub const CHARACTER_TABLE_URL: &str = concat!(BASE_URL, "/excel/character_table.json");
+    pub const CHAR_PATCH_TABLE_URL: &str = concat!(BASE_URL, "/excel/char_patch_table.json");
     pub const BATTLEEQUIP_TABLE_URL: &str = concat!(BASE_URL, "/excel/battle_equip_table.json");
     pub const EQUIP_TABLE_URL: &str = concat!(BASE_URL, "/excel/uniequip_table.json");
     pub const STORY_TABLE_URL: &str = concat!(BASE_URL, "/excel/story_table.json");
@@ -71,14 +83,19 @@ pub mod url {
     pub const HANDBOOK_INFO_TABLE_URL: &str = concat!(BASE_URL, "/excel/handbook_info_table.json");
     pub const TOWER_TABLE_URL: &str = concat!(BASE_URL, "/excel/climb_tower_table.json");
     pub const BUILDING_TABLE_URL: &str = concat!(BASE_URL, "/excel/building_data.json");
+    pub const CLUE_TABLE_URL: &str = concat!(BASE_URL, "/excel/clue_data.json");
     pub const SANDBOX_TABLE_URL: &str = concat!(BASE_URL, "/excel/sandbox_perm_table.json");
     pub const STORY_REVIEW_TABLE_URL: &str = concat!(BASE_URL, "/excel/story_review_table.json");
     pub const STORY_REVIEW_META_TABLE_URL: &str = concat!(BASE_URL, "/excel/story_review_meta_table.json");
     pub const ENEMY_HANDBOOK_TABLE_URL: &str = concat!(BASE_URL, "/excel/enemy_handbook_table.json");
     pub const MEDAL_TABLE_URL: &str = concat!(BASE_URL, "/excel/medal_table.json");
+    pub const MISSION_TABLE_URL: &str = concat!(BASE_URL, "/excel/mission_table.json");
     pub const CHARWORD_TABLE_URL: &str = concat!(BASE_URL, "/excel/charword_table.json");
     pub const GACHA_TABLE_URL: &str = concat!(BASE_URL, "/excel/gacha_table.json");
     pub const GAMEDATA_CONST_URL: &str = concat!(BASE_URL, "/excel/gamedata_const.json");
+    pub const FAVOR_TABLE_URL: &str = concat!(BASE_URL, "/excel/favor_table.json");
+    pub const ITEM_TABLE_URL: &str = concat!(BASE_URL, "/excel/item_table.json");
+    pub const CAMPAIGN_TABLE_URL: &str = concat!(BASE_URL, "/excel/campaign_table.json");
 }
 
 pub mod ascii {
@@ -2,8 +2,20 @@ use axum::Json;
 use serde_json::{json, Value};
 
 use crate::{
-    constants,
-    utils::json::{read_json, write_json, JSON},
+    constants::{
+        self,
+        url::{ACTIVITY_TABLE_URL, CHECKIN_TABLE_URL, GAMEDATA_CONST_URL, ITEM_TABLE_URL},
+    },
+    game::{
+        check_in::{event_check_in_delta, refresh_check_in, sign_in},
+        players::publish_player,
+    },
+    utils::{
+        ap::{add_ap, refresh_ap},
+        game::{error_result, update_data},
+        item::consume_items,
+        json::{read_json, write_json, JSON},
+    },
 };
 
 pub mod app {
@@ -119,11 +131,26 @@ pub async fn info_v1_basic() -> JSON {
     }))
 }
 
+/// Signs in for today, also catching up any check-in events.
 pub async fn user_check_in() -> JSON {
+    let checkin_table = update_data(CHECKIN_TABLE_URL).await;
+    let activity_table = update_data(ACTIVITY_TABLE_URL).await;
+
+    let mut user_data = read_json(constants::user::USER_JSON_PATH);
+    refresh_check_in(&mut user_data["user"], &checkin_table, &activity_table);
+    let mut modified = json!({});
+    let Some(rewards) = sign_in(&mut user_data["user"], &checkin_table, &mut modified).await else {
+        return error_result();
+    };
+    modified["checkIn"] = user_data["user"]["checkIn"].clone();
+    event_check_in_delta(&user_data["user"], &mut modified);
+    write_json(constants::user::USER_JSON_PATH, user_data);
     Json(json!({
-        "JSONult": 0,
+        "result": 0,
+        "signInRewards": rewards,
+        "subscriptionRewards": [],
         "playerDataDelta": {
-            "modified": {},
+            "modified": modified,
             "deleted": {}
         }
     }))
@@ -231,7 +258,15 @@ pub async fn user_auth() -> JSON {
 
 pub async fn user_change_resume(Json(payload): JSON) -> JSON {
     let resume = payload["resume"].clone();
+    if !resume.is_string() {
+        return error_result();
+    }
+    let mut user_data = read_json(constants::user::USER_JSON_PATH);
+    user_data["user"]["status"]["resume"] = resume.clone();
+    write_json(constants::user::USER_JSON_PATH, &user_data);
+    publish_player(&user_data["user"]);
     Json(json!({
+        "result": 0,
         "playerDataDelta": {
             "modified": {
                 "status": {
@@ -287,37 +322,160 @@ pub async fn user_change_avatar(Json(payload): JSON) -> JSON {
     }))
 }
 
+pub async fn user_buy_ap() -> JSON {
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let ap_buy_cost = const_table["apBuyCost"].as_u64().unwrap_or(1);
+    let no_limit = const_table["buyApTimeNoLimitFlag"].as_bool().unwrap_or(false);
+
+    let mut user_data = read_json(constants::user::USER_JSON_PATH);
+    refresh_ap(&mut user_data["user"]["status"], &const_table);
+
+    let remain_times = user_data["user"]["status"]["buyApRemainTimes"].as_u64().unwrap_or(0);
+    if !no_limit && remain_times == 0 {
+        return error_result();
+    }
+
+    let mut modified = json!({});
+    let cost = [json!({ "id": "4002", "count": ap_buy_cost, "type": "DIAMOND" })];
+    if consume_items(&mut user_data["user"], &cost, &mut modified).await.is_err() {
+        return error_result();
+    }
+
+    let status = &mut user_data["user"]["status"];
+    if !no_limit {
+        status["buyApRemainTimes"] = json!(remain_times - 1);
+    }
+    let max_ap = status["maxAp"].as_u64().unwrap_or(0);
+    add_ap(status, max_ap);
+    modified["status"]["ap"] = status["ap"].clone();
+    modified["status"]["lastApAddTime"] = status["lastApAddTime"].clone();
+    modified["status"]["buyApRemainTimes"] = status["buyApRemainTimes"].clone();
+
+    write_json(constants::user::USER_JSON_PATH, user_data);
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": modified,
+            "deleted": {}
+        }
+    }))
+}
+
+pub async fn user_use_item(Json(payload): JSON) -> JSON {
+    user_use_items(Json(json!({ "items": [payload] }))).await
+}
+
+pub async fn user_use_items(Json(payload): JSON) -> JSON {
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let item_table = update_data(ITEM_TABLE_URL).await;
+
+    let mut user_data = read_json(constants::user::USER_JSON_PATH);
+    refresh_ap(&mut user_data["user"]["status"], &const_table);
+
+    let mut modified = json!({});
+    for item in payload["items"].as_array().unwrap() {
+        let item_id = item["itemId"].as_str().unwrap();
+        let count = item["cnt"].as_u64().unwrap_or(1);
+        let ap = match item_table["apSupplies"][item_id]["ap"].as_u64() {
+            Some(ap) => ap,
+            None => continue,
+        };
+        let item_type = item_table["items"][item_id]["itemType"].clone();
+        let cost = [json!({ "id": item_id, "count": count, "type": item_type })];
+        if consume_items(&mut user_data["user"], &cost, &mut modified).await.is_err() {
+            return error_result();
+        }
+        add_ap(&mut user_data["user"]["status"], ap * count);
+    }
+
+    let status = &user_data["user"]["status"];
+    modified["status"]["ap"] = status["ap"].clone();
+    modified["status"]["lastApAddTime"] = status["lastApAddTime"].clone();
+
+    write_json(constants::user::USER_JSON_PATH, user_data);
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": modified,
+            "deleted": {}
+        }
+    }))
+}
+
 pub mod business_card {
     use axum::Json;
     use serde_json::json;
 
-    use crate::utils::json::JSON;
+    use crate::{
+        constants::user::USER_JSON_PATH,
+        game::{
+            mission::{track_missions, MissionEvent},
+            players::publish_player,
+        },
+        utils::{
+            game::error_result,
+            json::{read_json, write_json, JSON},
+        },
+    };
+
+    /// Name card sections the client lays out, in any order.
+    const NAME_CARD_COMPONENTS: [&str; 3] = ["module_sign", "module_assist", "module_medal"];
 
     pub async fn change_name_component(Json(payload): JSON) -> JSON {
-        let component = payload["component"].clone();
+        let component = payload["component"].as_array().cloned().unwrap_or_default();
+        let is_order = component.len() == NAME_CARD_COMPONENTS.len()
+            && NAME_CARD_COMPONENTS
+                .iter()
+                .all(|module| component.iter().any(|listed| listed == module));
+        if !is_order {
+            return error_result();
+        }
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        user_data["user"]["nameCardStyle"]["componentOrder"] = json!(component);
+        let mut modified = json!({
+            "nameCardStyle": {
+                "componentOrder": component
+            }
+        });
+        track_missions(&mut user_data["user"], &[MissionEvent::Count("EditBusinessCard", 1)], &mut modified).await;
+        write_json(USER_JSON_PATH, &user_data);
+        publish_player(&user_data["user"]);
         Json(json!({
+            "result": 0,
             "playerDataDelta": {
-                "modified": {
-                    "nameCardStyle": {
-                        "componentOrder": component
-                    }
-                },
+                "modified": modified,
                 "deleted": {}
             }
         }))
     }
 
+    /// Switches to an unlocked name card skin.
     pub async fn change_card_skin(Json(payload): JSON) -> JSON {
-        let skin_id = payload["skinId"].clone();
+        let mut user_data = read_json(USER_JSON_PATH);
+        let skin_id = payload["skinId"].as_str().unwrap_or("");
+        let unlocked = user_data["user"]["nameCardStyle"]["skin"]["state"][skin_id]["unlock"]
+            .as_bool()
+            .unwrap_or(false);
+        if !unlocked {
+            return error_result();
+        }
+
+        user_data["user"]["nameCardStyle"]["skin"]["selected"] = json!(skin_id);
+        let mut modified = json!({
+            "nameCardStyle": {
+                "skin": {
+                    "selected": skin_id
+                }
+            }
+        });
+        track_missions(&mut user_data["user"], &[MissionEvent::Count("EditBusinessCard", 1)], &mut modified).await;
+        write_json(USER_JSON_PATH, &user_data);
+        publish_player(&user_data["user"]);
         Json(json!({
+            "result": 0,
             "playerDataDelta": {
-                "modified": {
-                    "nameCardStyle": {
-                        "skin": {
-                            "selected": skin_id
-                        }
-                    }
-                },
+                "modified": modified,
                 "deleted": {}
             }
         }))
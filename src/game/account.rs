@@ -1,17 +1,34 @@
 use crate::{
     constants::{
-        config::{CONFIG_JSON_PATH, MAILLIST_PATH, SQUADS_PATH, SYNC_DATA_TEMPLATE_PATH},
+        config::{CONFIG_JSON_PATH, MAILLIST_PATH, SYNC_DATA_TEMPLATE_PATH},
         url::*,
-        user::{BATTLE_REPLAY_JSON_PATH, USER_JSON_PATH},
+        user::{BATTLE_REPLAY_JSON_PATH, BUILDING_JSON_PATH, USER_JSON_PATH},
     },
     core::time,
-    utils::{comp::max, game::*, json::*, zipper},
+    utils::{
+        ap::{infinite_ap, max_ap, refresh_ap},
+        comp::max,
+        game::*,
+        item::carry_over_items,
+        json::*,
+        zipper,
+    },
 };
 use axum::{http::HeaderMap, Json};
 use serde_json::{json, Value};
 use uuid::Uuid;
 
-use super::building::building_sync;
+use super::{
+    building::building_sync,
+    campaignv2::campaignv2_sync,
+    check_in::{event_check_in_delta, refresh_check_in},
+    equipment::{completed_equip_missions, unlocked_equips},
+    medal::{award_medals, MedalContext},
+    mission::track_missions,
+    players::{publish_player, saved_assist_selection},
+    profile::{apply_profile, apply_unit, Profile},
+    squad::{export_squads, resolve_squads, squad_presets},
+};
 
 pub async fn account_login(header: HeaderMap) -> JSON {
     let fallback_uid = Uuid::new_v4().to_string();
@@ -43,13 +60,14 @@ pub async fn account_sync_data() -> JSON {
     let retro_table = update_data(RETRO_TABLE_URL).await;
     let charm_table = update_data(CHARM_TABLE_URL).await;
     let acitivity_table = update_data(ACTIVITY_TABLE_URL).await;
+    let checkin_table = update_data(CHECKIN_TABLE_URL).await;
     let charword_table = update_data(CHARWORD_TABLE_URL).await;
     let story_review_table = update_data(STORY_REVIEW_TABLE_URL).await;
     let story_review_meta_table = update_data(STORY_REVIEW_META_TABLE_URL).await;
     let enemy_handbook_table = update_data(ENEMY_HANDBOOK_TABLE_URL).await;
-    let medal_table = update_data(MEDAL_TABLE_URL).await;
     let rlv2_table = update_data(RL_TABLE_URL).await;
     let stage_table = update_data(STAGE_TABLE_URL).await;
+    let patch_table = update_data(CHAR_PATCH_TABLE_URL).await;
     let story_table = update_data(STORY_TABLE_URL).await;
 
     let mut count = 0;
@@ -92,10 +110,10 @@ pub async fn account_sync_data() -> JSON {
 
     // Operators
     let operator_template = config["charConfig"].clone();
+    let skills_specialize_level = operator_template["skillsSpecializeLevel"].as_u64().unwrap();
 
     let mut count = 0;
     let operator_keys = get_keys(&char_table);
-    let equip_keys = get_keys(&equip_table["charEquip"]);
 
     for operator in &operator_keys {
         if !operator.contains("char") {
@@ -141,7 +159,7 @@ pub async fn account_sync_data() -> JSON {
             "favorPoint": operator_template["favorPoint"],
             "potentialRank": operator_template["potentialRank"],
             "mainSkillLvl": operator_template["mainSkillLvl"],
-            "skin": operator_keys[count].clone() + "#1",
+            "skin": default_skin(&skin_table, &operator, evolve_phase),
             "level": level,
             "exp": 0,
             "evolvePhase": evolve_phase,
@@ -154,191 +172,64 @@ pub async fn account_sync_data() -> JSON {
             "starMark": 0
         });
 
-        // Set E2 skin
-
-        if [
-            "char_508_aguard".to_string(),
-            "char_509_acast".to_string(),
-            "char_510_amedic".to_string(),
-            "char_511_asnipe".to_string(),
-        ]
-        .contains(&operator_keys[count])
-            && temp_char_list[count_inst_id.to_string()]["evolvePhase"].as_i64().unwrap() == 2
-        {
-            temp_char_list[count_inst_id.to_string()]["skin"] = json!(operator_keys[count].clone() + "#2");
-        }
-
         // Set skins
         if get_keys(&temp_skin_table).contains(&operator_keys[count]) {
             temp_char_list[count_inst_id.to_string()]["skin"] = json!(temp_skin_table[&operator_keys[count]]);
         }
 
-        let mut skill_vec = Vec::new();
         // Set skills
-        for skill in char_table[&operator]["skills"].as_array().unwrap() {
-            let specialization_level = if !skill["levelUpCostCond"].as_array().unwrap().is_empty() {
-                operator_template["skillsSpecializeLevel"].as_u64().unwrap()
-            } else {
-                0
-            };
-            skill_vec.push(json!({
-                "skillId": skill["skillId"],
-                "unlock": 1,
-                "state": 0,
-                "specializeLevel": specialization_level,
-                "completeUpgradeTime": -1
-            }));
-        }
-        temp_char_list[count_inst_id.to_string()]["skills"] = json!(skill_vec);
+        temp_char_list[count_inst_id.to_string()]["skills"] = json!(build_skills(&char_table[&operator], skills_specialize_level));
 
         // Set modules
-        if equip_keys
-            .clone()
-            .contains(&temp_char_list[count_inst_id.to_string()]["charId"].to_string())
-        {
-            for equip in get_keys(&equip_table["charEquip"][temp_char_list[count_inst_id.to_string()]["charId"].to_string()]) {
-                let mut lvl = 1;
-                if get_keys(&battleequip_table).contains(&equip) {
-                    lvl = battleequip_table[&equip]["phases"].as_array().unwrap().len();
-                }
-                temp_char_list[count_inst_id.to_string()]["equip"] = json!({
-                    equip: {
-                        "hide": 0,
-                        "locked": 0,
-                        "level": lvl
-                    }
-                });
-            }
-            temp_char_list[count_inst_id.to_string()]["currentEquip"] = equip_table["charEquip"]
-                [temp_char_list[count_inst_id.to_string()]["charId"].to_string()]
-            .as_array()
-            .unwrap()
-            .last()
-            .unwrap()
-            .clone();
-        }
+        let (equip, current_equip) = unlocked_equips(
+            &equip_table,
+            &battleequip_table,
+            &operator,
+            &temp_char_list[count_inst_id.to_string()],
+        );
+        temp_char_list[count_inst_id.to_string()]["equip"] = equip;
+        temp_char_list[count_inst_id.to_string()]["currentEquip"] = current_equip;
+
         player_data["user"]["dexNav"]["character"][&operator_keys[count]] = json!({
             "charInstId": count_inst_id,
             "count": 6
         });
 
-        // FIXME: This is a temporary fix for Amiya
-        if operator_keys[count] == "char_002_amiya" {
-            temp_char_list[count_inst_id.to_string()] = json!({
-                "instId": 2,
-                "charId": "char_002_amiya",
-                "favorPoint": 25570,
-                "potentialRank": 5,
-                "mainSkillLvl": 7,
-                "skin": "char_1001_amiya2@casc#1",
-                "level": 80,
-                "exp": 0,
-                "evolvePhase": 2,
-                "defaultSkillIndex": -1,
-                "gainTime": 1712627499,
-                "skills": [],
-                "voiceLan": "JP",
-                "currentEquip": "uniequip_002_amiya",
-                "equip": {
-                    "uniequip_001_amiya": {
-                        "hide": 0,
-                        "locked": 0,
-                        "level": 1
-                    },
-                    "uniequip_002_amiya": {
-                        "hide": 0,
-                        "locked": 0,
-                        "level": 3
-                    }
-                },
-                "starMark": 0,
-                "currentTmpl": "char_002_amiya",
-                "tmpl": {
-                    "char_002_amiya": {
-                        "skinId": "char_002_amiya@test#1",
-                        "defaultSkillIndex": 2,
-                        "skills": [
-                            {
-                                "skillId": "skcom_magic_rage[3]",
-                                "unlock": 1,
-                                "state": 0,
-                                "specializeLevel": 3,
-                                "completeUpgradeTime": -1
-                            },
-                            {
-                                "skillId": "skchr_amiya_2",
-                                "unlock": 1,
-                                "state": 0,
-                                "specializeLevel": 3,
-                                "completeUpgradeTime": -1
-                            },
-                            {
-                                "skillId": "skchr_amiya_3",
-                                "unlock": 1,
-                                "state": 0,
-                                "specializeLevel": 3,
-                                "completeUpgradeTime": -1
-                            }
-                        ],
-                        "currentEquip": "uniequip_002_amiya",
-                        "equip": {
-                            "uniequip_001_amiya": {
-                                "hide": 0,
-                                "locked": 0,
-                                "level": 1
-                            },
-                            "uniequip_002_amiya": {
-                                "hide": 0,
-                                "locked": 0,
-                                "level": 3
-                            }
-                        }
-                    },
-                    "char_1001_amiya2": {
-                        "skinId": "char_1001_amiya2@casc#1",
-                        "defaultSkillIndex": 1,
-                        "skills": [
-                            {
-                                "skillId": "skchr_amiya2_1",
-                                "unlock": 1,
-                                "state": 0,
-                                "specializeLevel": 3,
-                                "completeUpgradeTime": -1
-                            },
-                            {
-                                "skillId": "skchr_amiya2_2",
-                                "unlock": 1,
-                                "state": 0,
-                                "specializeLevel": 3,
-                                "completeUpgradeTime": -1
-                            }
-                        ],
-                        "currentEquip": null,
-                        "equip": {}
-                    }
-                }
-            });
-            for equip in get_keys(&equip_table["charEquip"]["char_002_amiya"]) {
-                let mut lvl = 1;
-                if get_keys(&battleequip_table).contains(&equip) {
-                    lvl = battleequip_table[&equip]["phases"].as_array().unwrap().len();
-                }
-                temp_char_list[count_inst_id.to_string()]["tmpl"]["char_002_amiya"]["equip"] = json!({
-                    equip: {
-                        "hide": 0,
-                        "locked": 0,
-                        "level": lvl
-                    }
+        // Set templates
+        let patch_info = &patch_table["infos"][&operator];
+        if patch_info.is_object() {
+            let mut tmpls = json!({});
+            for tmpl_id in patch_info["tmplIds"].as_array().unwrap() {
+                let tmpl_id = tmpl_id.as_str().unwrap();
+                let tmpl_data = if char_table[tmpl_id].is_null() {
+                    &patch_table["patchChars"][tmpl_id]
+                } else {
+                    &char_table[tmpl_id]
+                };
+                let skills = build_skills(tmpl_data, skills_specialize_level);
+                let (equip, current_equip) = unlocked_equips(
+                    &equip_table,
+                    &battleequip_table,
+                    tmpl_id,
+                    &temp_char_list[count_inst_id.to_string()],
+                );
+                tmpls[tmpl_id] = json!({
+                    "skinId": default_skin(&skin_table, tmpl_id, evolve_phase),
+                    "defaultSkillIndex": skills.len() as i64 - 1,
+                    "skills": skills,
+                    "currentEquip": current_equip,
+                    "equip": equip
                 });
             }
-            temp_char_list[count_inst_id.to_string()]["tmpl"]["char_002_amiya"]["currentEquip"] = equip_table["charEquip"]["char_002_amiya"]
-                .as_array()
-                .unwrap()
-                .last()
-                .unwrap()
-                .clone();
-        } else if operator_keys[count] == "char_512_aprot" {
-            temp_char_list[count_inst_id.to_string()]["skin"] = json!("char_512_aprot#1");
+            let default_tmpl = patch_info["default"].as_str().unwrap();
+            let char = &mut temp_char_list[count_inst_id.to_string()];
+            char["currentTmpl"] = json!(default_tmpl);
+            char["skin"] = tmpls[default_tmpl]["skinId"].clone();
+            char["defaultSkillIndex"] = tmpls[default_tmpl]["defaultSkillIndex"].clone();
+            char["currentEquip"] = tmpls[default_tmpl]["currentEquip"].clone();
+            char["equip"] = tmpls[default_tmpl]["equip"].clone();
+            char["skills"] = json!([]);
+            char["tmpl"] = tmpls;
         }
 
         building_chars[count_inst_id.to_string()] = json!({
@@ -364,9 +255,40 @@ pub async fn account_sync_data() -> JSON {
         count += 1;
     }
 
-    let count_inst_id = 10000;
+    // Custom units
+    let custom_unit_info = &operator_template["customUnitInfo"];
+    let unit_tables = [&char_table, &patch_table, &skin_table, &equip_table, &battleequip_table];
+    for inst_id in get_keys(&temp_char_list) {
+        let char_id = temp_char_list[&inst_id]["charId"].as_str().unwrap().to_string();
+        if custom_unit_info[&char_id].is_object() {
+            apply_unit(&mut temp_char_list[&inst_id], &custom_unit_info[&char_id], unit_tables, &[]);
+            char_group[&char_id]["favorPoint"] = temp_char_list[&inst_id]["favorPoint"].clone();
+        }
+    }
+
+    let mut count_inst_id = 10000;
+    let mut duplicates = Vec::new();
+    for unit in operator_template["duplicateUnits"].as_array().unwrap_or(&Vec::new()) {
+        let char_id = unit.as_str().or(unit["charId"].as_str()).unwrap_or("");
+        let Some(mut duplicate) = get_values(&temp_char_list).into_iter().find(|char| char["charId"] == char_id) else {
+            continue;
+        };
+        let source_inst_id = duplicate["instId"].to_string();
+        duplicate["instId"] = json!(count_inst_id);
+        building_chars[count_inst_id.to_string()] = building_chars[&source_inst_id].clone();
+        if unit.is_object() {
+            apply_unit(&mut duplicate, unit, unit_tables, &[]);
+        }
+        duplicates.push(duplicate);
+        count_inst_id += 1;
+    }
+    for duplicate in duplicates {
+        let inst_id = duplicate["instId"].to_string();
+        temp_char_list[inst_id] = duplicate;
+    }
 
     player_data["user"]["troop"]["chars"] = temp_char_list;
+    player_data["user"]["equipment"]["missions"] = completed_equip_missions(&equip_table);
     player_data["user"]["troop"]["charGroup"] = char_group;
     player_data["user"]["troop"]["curCharInstId"] = json!(count_inst_id);
 
@@ -450,26 +372,8 @@ pub async fn account_sync_data() -> JSON {
     }
     player_data["user"]["retro"]["trail"] = trails;
 
-    for stage in get_keys(&stage_table["stages"]) {
-        if stage.starts_with("camp") {
-            player_data["user"]["campaignsV2"]["instances"][&stage] = json!({
-                            "maxKills": 400,
-                            "rewardStatus": [1, 1, 1, 1, 1, 1, 1, 1]
-                        }
-            );
-
-            let new_perma_vec = player_data["user"]["campaignsV2"]["open"]["permanent"].clone();
-            let mut new_perma_vec = new_perma_vec.as_array().unwrap().clone();
-            let new_training_vec = player_data["user"]["campaignsV2"]["open"]["training"].clone();
-            let mut new_training_vec = new_training_vec.as_array().unwrap().clone();
-            new_perma_vec.push(json!(stage));
-            new_training_vec.push(json!(stage));
-
-            player_data["user"]["campaignsV2"]["sweepMaxKills"][&stage] = json!(400);
-            player_data["user"]["campaignsV2"]["open"]["permanent"] = json!(new_perma_vec);
-            player_data["user"]["campaignsV2"]["open"]["training"] = json!(new_training_vec);
-        }
-    }
+    // Annihilation
+    player_data["user"]["campaignsV2"] = campaignv2_sync(&user_data["user"]["campaignsV2"]).await;
 
     let mut avatar_list = json!({});
 
@@ -598,12 +502,72 @@ pub async fn account_sync_data() -> JSON {
     player_data["user"]["crisis"]["nst"] = json!(time() + 3600);
     player_data["ts"] = json!(time());
 
+    // Profile
+    let profile = Profile::from_config(&config);
+    apply_profile(&profile, &mut player_data["user"]).await;
+    // Level, items and progress only carry over from a save built with the same profile. Saves older than profiles were maxed.
+    let same_profile = user_data["profile"].as_str().unwrap_or(Profile::Maxed.name()) == profile.name();
+    player_data["profile"] = json!(profile.name());
+
+    // Player level
+    let previous_status = &user_data["user"]["status"];
+    if same_profile && previous_status["level"].is_u64() {
+        player_data["user"]["status"]["level"] = previous_status["level"].clone();
+        player_data["user"]["status"]["exp"] = previous_status["exp"].clone();
+    }
+
+    // Name card
+    if previous_status["resume"].is_string() {
+        player_data["user"]["status"]["resume"] = previous_status["resume"].clone();
+    }
+    for field in ["nameCardStyle", "medal"] {
+        if user_data["user"][field].is_object() {
+            player_data["user"][field] = user_data["user"][field].clone();
+        }
+    }
+    if user_data["user"]["social"]["medalBoard"].is_object() {
+        player_data["user"]["social"]["medalBoard"] = user_data["user"]["social"]["medalBoard"].clone();
+    }
+
+    // Missions
+    let previous_mission = &user_data["user"]["mission"];
+    for mission_type in ["DAILY", "WEEKLY", "GUIDE", "MAIN"] {
+        if previous_mission["missions"][mission_type].is_object() {
+            player_data["user"]["mission"]["missions"][mission_type] = previous_mission["missions"][mission_type].clone();
+        }
+    }
+    for field in ["missionRewards", "missionGroups"] {
+        if previous_mission[field].is_object() {
+            player_data["user"]["mission"][field] = previous_mission[field].clone();
+        }
+    }
+
+    // Items, operator progress and stage clears
+    if same_profile {
+        carry_over_items(&user_data["user"], &mut player_data["user"]);
+        carry_over_progress(&user_data["user"], &mut player_data["user"]);
+    }
+
+    // Sanity
+    if !infinite_ap() {
+        let const_table = update_data(GAMEDATA_CONST_URL).await;
+        let level = player_data["user"]["status"]["level"].as_u64().unwrap();
+        let status = &mut player_data["user"]["status"];
+        status["maxAp"] = json!(max_ap(&const_table, level));
+        match previous_status["lastApAddTime"].as_u64() {
+            Some(last_ap_add_time) if same_profile && last_ap_add_time > 0 => {
+                status["ap"] = previous_status["ap"].clone();
+                status["lastApAddTime"] = json!(last_ap_add_time);
+            }
+            _ => status["ap"] = status["maxAp"].clone(),
+        }
+        refresh_ap(status, &const_table);
+    }
+
     // REPLAY CODES
     let replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
 
-    // TODO: MAKE THIS DYNAMIC
-    let cur_char_conf = "95cf97c228e6c235cd6503e5eeb1b737";
-    // md5(b64encode(json.dumps(edit_json).encode())).hexdigest()
+    let cur_char_conf = replay_data["currentCharConfig"].as_str().unwrap_or("");
 
     if get_keys(&replay_data["saved"]).contains(&cur_char_conf.to_string()) {
         for replay in get_keys(&replay_data["saved"][&cur_char_conf]) {
@@ -613,59 +577,20 @@ pub async fn account_sync_data() -> JSON {
         }
     }
 
-    let squads = read_json(SQUADS_PATH);
-
-    let mut char_id_map = json!({});
-
-    for char in get_values(&player_data["user"]["troop"]["chars"]) {
-        let char_id = char["charId"].as_str().unwrap();
-        char_id_map[char_id] = char["instId"].clone();
+    // Squads
+    let squads = resolve_squads(&squad_presets(), &player_data["user"]["troop"]["chars"]);
+    for squad_id in get_keys(&squads) {
+        player_data["user"]["troop"]["squads"][&squad_id] = squads[&squad_id].clone();
     }
 
-    let mut squads_data = json!({});
-    for id in get_keys(&squads) {
-        let mut ct = 0;
-        let mut slots = Vec::new();
-        for (cntr, slot) in squads[id.to_string()]["slots"].as_array().unwrap().iter().enumerate() {
-            let mut slot_data = json!({});
-            if cntr >= 12 {
-                break;
-            }
-            let char_id = slot["charId"].as_str().unwrap();
-            if char_id_map.get(char_id).is_some() {
-                let inst_id = char_id_map[char_id].as_i64().unwrap();
-                slot_data["charInstId"] = json!(inst_id);
-                let current_equip = slot["currentEquip"].as_str();
-                match current_equip {
-                    Some(equip) => {
-                        if get_keys(&player_data["user"]["troop"]["chars"][inst_id.to_string()]["equip"]).contains(&equip.to_string()) {
-                            slot_data["currentEquip"] = json!(equip);
-                        } else {
-                            slot_data["currentEquip"] = Value::Null;
-                        }
-                    }
-                    None => {
-                        slot_data["currentEquip"] = Value::Null;
-                    }
-                }
-                slot_data["skillIndex"] = slot["skillIndex"].clone();
-                slots.push(slot_data);
-                ct += 1;
-            }
-        }
-        for _ in ct..12 {
-            slots.push(Value::Null)
-        }
-        if slots.len() > 12 {
-            slots = slots[..12].to_vec();
-        }
-        squads_data[id.to_string()]["squadId"] = json!(id.to_string());
-        squads_data[id.to_string()]["name"] = squads[id.to_string()]["name"].clone();
-        squads_data[id.to_string()]["slots"] = json!(slots);
+    let mut secretary = &config["userConfig"]["secretary"];
+    let mut secretary_skin_id = &config["userConfig"]["secretarySkinId"];
+    let chars = &player_data["user"]["troop"]["chars"];
+    let fallback_secretary = get_values(chars).into_iter().next().unwrap_or(Value::Null);
+    if !get_values(chars).iter().any(|char| &char["charId"] == secretary) && !fallback_secretary.is_null() {
+        secretary = &fallback_secretary["charId"];
+        secretary_skin_id = &fallback_secretary["skin"];
     }
-
-    let secretary = &config["userConfig"]["secretary"];
-    let secretary_skin_id = &config["userConfig"]["secretarySkinId"];
     let bg = &config["userConfig"]["background"];
     let theme = &config["userConfig"]["theme"];
 
@@ -721,19 +646,191 @@ pub async fn account_sync_data() -> JSON {
         }
     }
 
+    // Check-ins
+    if user_data["user"]["checkIn"].is_object() {
+        player_data["user"]["checkIn"] = user_data["user"]["checkIn"].clone();
+    }
+    let mut check_in_events = json!({});
+    event_check_in_delta(&user_data["user"], &mut check_in_events);
+    for (activity_type, state) in check_in_events["activity"].as_object().into_iter().flatten() {
+        player_data["user"]["activity"][activity_type] = state.clone();
+    }
+
+    restore_previous_states(
+        &config["userConfig"]["restorePreviousStates"],
+        &user_data["user"],
+        &mut player_data["user"],
+    );
+
+    // Medals earned outside of tracked events, such as with a maxed profile
+    let favor_table = update_data(FAVOR_TABLE_URL).await;
+    let building_table = update_data(BUILDING_TABLE_URL).await;
+    let building = read_json(BUILDING_JSON_PATH);
+    let medal_context = MedalContext {
+        favor_table: Some(&favor_table),
+        base: Some((&building_table, &building)),
+    };
+    award_medals(&mut player_data["user"], &medal_context, &mut json!({})).await;
+    // Missions reset since the last login, and progress read off the synced save
+    track_missions(&mut player_data["user"], &[], &mut json!({})).await;
+    refresh_check_in(&mut player_data["user"], &checkin_table, &acitivity_table);
+
+    if let Some(selection) = saved_assist_selection(&player_data["user"]) {
+        player_data["user"]["social"]["assistCharList"] = selection;
+    }
+
     write_json(USER_JSON_PATH, player_data.clone());
+    publish_player(&player_data["user"]);
 
+    // The base settlement may have used up factory materials.
     let Json(building) = building_sync().await;
+    let mut player_data = read_json(USER_JSON_PATH);
     player_data["user"]["building"] = json!(building["playerDataDelta"]["modified"]["building"]);
     Json(player_data)
 }
 
+/// Operator progress kept across syncs of saves built with the same profile.
+const PROGRESS_CHAR_FIELDS: [&str; 9] = [
+    "level",
+    "exp",
+    "evolvePhase",
+    "potentialRank",
+    "mainSkillLvl",
+    "favorPoint",
+    "skills",
+    "equip",
+    "currentEquip",
+];
+const PROGRESS_TMPL_FIELDS: [&str; 3] = ["skills", "equip", "currentEquip"];
+
+/// Carries what the player has upgraded and cleared since the save was built: operator progress, matched by instId,
+/// module missions and stage states.
+fn carry_over_progress(previous_user: &Value, user: &mut Value) {
+    let previous_chars = &previous_user["troop"]["chars"];
+    for inst_id in get_keys(previous_chars) {
+        let previous_char = &previous_chars[&inst_id];
+        let Some(char) = user["troop"]["chars"]
+            .get_mut(&inst_id)
+            .filter(|char| char["charId"] == previous_char["charId"])
+        else {
+            continue;
+        };
+        for field in PROGRESS_CHAR_FIELDS {
+            if !previous_char[field].is_null() {
+                char[field] = previous_char[field].clone();
+            }
+        }
+        for tmpl_id in get_keys(&char["tmpl"]) {
+            for field in PROGRESS_TMPL_FIELDS {
+                if !previous_char["tmpl"][&tmpl_id][field].is_null() {
+                    char["tmpl"][&tmpl_id][field] = previous_char["tmpl"][&tmpl_id][field].clone();
+                }
+            }
+        }
+        let char_id = previous_char["charId"].as_str().unwrap_or("").to_string();
+        let favor_point = char["favorPoint"].clone();
+        if user["troop"]["charGroup"][&char_id].is_object() {
+            user["troop"]["charGroup"][&char_id]["favorPoint"] = favor_point;
+        }
+    }
+
+    for mission_id in get_keys(&previous_user["equipment"]["missions"]) {
+        user["equipment"]["missions"][&mission_id] = previous_user["equipment"]["missions"][&mission_id].clone();
+    }
+    for stage_id in get_keys(&previous_user["dungeon"]["stages"]) {
+        if user["dungeon"]["stages"][&stage_id].is_object() {
+            user["dungeon"]["stages"][&stage_id] = previous_user["dungeon"]["stages"][&stage_id].clone();
+        }
+    }
+}
+
+/// Per-operator choices carried over by `squadsAndFavs`, matched by `instId` where the `charId` still agrees.
+const RESTORED_CHAR_FIELDS: [&str; 6] = ["starMark", "skin", "defaultSkillIndex", "currentEquip", "voiceLan", "currentTmpl"];
+
+/// Carries the sections enabled in `userConfig.restorePreviousStates` from the previous save into a regenerated one.
+fn restore_previous_states(flags: &Value, previous_user: &Value, user: &mut Value) {
+    if previous_user.is_null() {
+        return;
+    }
+
+    if flags["is2"].as_bool().unwrap_or(false) && previous_user["rlv2"].is_object() {
+        user["rlv2"] = previous_user["rlv2"].clone();
+    }
+
+    if flags["squadsAndFavs"].as_bool().unwrap_or(false) {
+        let previous_chars = &previous_user["troop"]["chars"];
+        let presets = export_squads(&previous_user["troop"]["squads"], previous_chars);
+        let squads = resolve_squads(&presets, &user["troop"]["chars"]);
+        for squad_id in get_keys(&squads) {
+            user["troop"]["squads"][&squad_id] = squads[&squad_id].clone();
+        }
+
+        // Matched by instId, so each duplicate of an operator gets back its own settings.
+        for inst_id in get_keys(previous_chars) {
+            let previous_char = &previous_chars[&inst_id];
+            if previous_char["charId"].is_null() || user["troop"]["chars"][&inst_id]["charId"] != previous_char["charId"] {
+                continue;
+            }
+            for field in RESTORED_CHAR_FIELDS {
+                if !previous_char[field].is_null() {
+                    user["troop"]["chars"][&inst_id][field] = previous_char[field].clone();
+                }
+            }
+        }
+    }
+
+    if flags["ui"].as_bool().unwrap_or(false) {
+        for field in ["secretary", "secretarySkinId", "avatarId"] {
+            if !previous_user["status"][field].is_null() {
+                user["status"][field] = previous_user["status"][field].clone();
+            }
+        }
+        for section in ["background", "homeTheme"] {
+            if !previous_user[section]["selected"].is_null() {
+                user[section]["selected"] = previous_user[section]["selected"].clone();
+            }
+        }
+    }
+}
+
+/// Every skill of an operator or template, mastered up to `specialize_level` where it can be.
+fn build_skills(char_data: &Value, specialize_level: u64) -> Vec<Value> {
+    let mut skills = Vec::new();
+    for skill in char_data["skills"].as_array().unwrap() {
+        let specialization_level = if !skill["levelUpCostCond"].as_array().unwrap().is_empty() {
+            specialize_level
+        } else {
+            0
+        };
+        skills.push(json!({
+            "skillId": skill["skillId"],
+            "unlock": 1,
+            "state": 0,
+            "specializeLevel": specialization_level,
+            "completeUpgradeTime": -1
+        }));
+    }
+    skills
+}
+
 pub async fn account_sync_status() -> JSON {
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let mut user_data = read_json(USER_JSON_PATH);
+    let status = &mut user_data["user"]["status"];
+    refresh_ap(status, &const_table);
+    let modified_status = json!({
+        "ap": status["ap"],
+        "lastApAddTime": status["lastApAddTime"]
+    });
+    write_json(USER_JSON_PATH, user_data);
+
     Json(json!({
         "ts": time(),
         "result": {},
         "playerDataDelta": {
-            "modified": {},
+            "modified": {
+                "status": modified_status
+            },
             "deleted": {}
         }
     }))
@@ -751,3 +848,62 @@ pub async fn account_yostar_auth_submit() -> JSON {
         "yostar_uid": "1"
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_each_duplicate_by_inst_id() {
+        let chars = |skins: [&str; 3]| {
+            json!({
+                "1": { "charId": "char_002_amiya", "skin": skins[0] },
+                "2": { "charId": "char_010_chen", "skin": skins[1] },
+                "3": { "charId": "char_002_amiya", "skin": skins[2] }
+            })
+        };
+        let previous_user = json!({ "troop": { "chars": chars(["amiya#2", "chen#2", "amiya@test#1"]), "squads": {} } });
+        let mut user = json!({ "troop": { "chars": chars(["amiya#1", "chen#1", "amiya#1"]), "squads": {} } });
+        user["troop"]["chars"]["2"]["charId"] = json!("char_017_huang");
+
+        restore_previous_states(&json!({ "squadsAndFavs": true }), &previous_user, &mut user);
+        assert_eq!(user["troop"]["chars"]["1"]["skin"], "amiya#2");
+        assert_eq!(user["troop"]["chars"]["2"]["skin"], "chen#1");
+        assert_eq!(user["troop"]["chars"]["3"]["skin"], "amiya@test#1");
+    }
+
+    #[test]
+    fn carries_progress_and_clears_over() {
+        let previous_user = json!({
+            "troop": {
+                "chars": {
+                    "1": { "charId": "char_002_amiya", "level": 40, "evolvePhase": 1, "skills": [], "tmpl": { "char_002_amiya": { "skills": [{ "specializeLevel": 1 }] } } },
+                    "2": { "charId": "char_010_chen", "level": 30, "favorPoint": 100 },
+                    "9": { "charId": "char_017_huang", "level": 50 }
+                }
+            },
+            "dungeon": { "stages": { "main_00-01": { "state": 3 }, "main_99-99": { "state": 3 } } }
+        });
+        let mut user = json!({
+            "troop": {
+                "chars": {
+                    "1": { "charId": "char_002_amiya", "level": 1, "evolvePhase": 0, "skills": [], "tmpl": { "char_002_amiya": { "skills": [{ "specializeLevel": 0 }] } } },
+                    "2": { "charId": "char_010_chen", "level": 1, "favorPoint": 0 }
+                },
+                "charGroup": { "char_010_chen": { "favorPoint": 0 } }
+            },
+            "dungeon": { "stages": { "main_00-01": { "state": 0 } } }
+        });
+
+        carry_over_progress(&previous_user, &mut user);
+        assert_eq!(user["troop"]["chars"]["1"]["level"], 40);
+        assert_eq!(
+            user["troop"]["chars"]["1"]["tmpl"]["char_002_amiya"]["skills"][0]["specializeLevel"],
+            1
+        );
+        assert_eq!(user["troop"]["charGroup"]["char_010_chen"]["favorPoint"], 100);
+        assert!(user["troop"]["chars"].get("9").is_none());
+        assert_eq!(user["dungeon"]["stages"]["main_00-01"]["state"], 3);
+        assert!(user["dungeon"]["stages"].get("main_99-99").is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use axum::Json;
+use serde_json::{json, Value};
+
+use crate::{
+    constants::user::BATTLE_STATS_JSON_PATH,
+    core::time,
+    utils::{
+        battle_data::BattleData,
+        json::{get_keys, read_json, write_json, JSON},
+    },
+};
+
+/// Attempts kept per stage; older ones are dropped first.
+const MAX_ATTEMPTS_PER_STAGE: usize = 200;
+const DEFAULT_RECENT_ATTEMPTS: usize = 10;
+const DEFAULT_TOP_OPERATORS: usize = 10;
+const COMPLETE_STATE_PASS: i64 = 2;
+
+/// Records a finished battle from its session (`battle.json`) and decrypted battle data.
+pub fn record_battle(battle: &Value, battle_data: &BattleData) {
+    let stage_id = match battle["stageId"].as_str() {
+        Some(stage_id) if !stage_id.is_empty() => stage_id,
+        _ => return,
+    };
+
+    let now = time();
+    let duration = if battle_data.battle_data.complete_time > 0.0 {
+        battle_data.battle_data.complete_time
+    } else {
+        now.saturating_sub(battle["startTs"].as_u64().unwrap_or(now)) as f64
+    };
+
+    let attempt = json!({
+        "ts": now,
+        "battleId": battle["battleId"],
+        "completeState": battle_data.complete_state,
+        "killCnt": battle_data.kill_cnt,
+        "missedCnt": battle_data.missed_cnt,
+        "duration": duration,
+        "chars": battle_data.deployed_chars(),
+        "extraBattleInfo": battle_data.extra_battle_info()
+    });
+
+    let mut stats_data = read_json(BATTLE_STATS_JSON_PATH);
+    if !stats_data["stages"][stage_id].is_array() {
+        stats_data["stages"][stage_id] = json!([]);
+    }
+    let attempts = stats_data["stages"][stage_id].as_array_mut().unwrap();
+    attempts.push(attempt);
+    if attempts.len() > MAX_ATTEMPTS_PER_STAGE {
+        let excess = attempts.len() - MAX_ATTEMPTS_PER_STAGE;
+        attempts.drain(..excess);
+    }
+    write_json(BATTLE_STATS_JSON_PATH, stats_data);
+}
+
+/// Attempts for one stage, or for every stage when `stageId` is omitted.
+fn attempts_for(stats_data: &Value, payload: &Value) -> Vec<Value> {
+    let stage_ids = match payload["stageId"].as_str() {
+        Some(stage_id) => vec![stage_id.to_string()],
+        None => get_keys(&stats_data["stages"]),
+    };
+    let mut attempts: Vec<Value> = stage_ids
+        .iter()
+        .flat_map(|stage_id| {
+            let attempts = stats_data["stages"][stage_id].as_array().cloned().unwrap_or_default();
+            attempts.into_iter().map(move |mut attempt| {
+                attempt["stageId"] = json!(stage_id);
+                attempt
+            })
+        })
+        .collect();
+    attempts.sort_by_key(|attempt| attempt["ts"].as_u64().unwrap_or(0));
+    attempts
+}
+
+pub async fn battle_stats_summary(Json(payload): JSON) -> JSON {
+    let attempts = attempts_for(&read_json(BATTLE_STATS_JSON_PATH), &payload);
+
+    let total = attempts.len();
+    let clears = attempts
+        .iter()
+        .filter(|attempt| attempt["completeState"].as_i64().unwrap_or(0) >= COMPLETE_STATE_PASS)
+        .count();
+    let average = |field: &str| {
+        if total == 0 {
+            0.0
+        } else {
+            attempts.iter().map(|attempt| attempt[field].as_f64().unwrap_or(0.0)).sum::<f64>() / total as f64
+        }
+    };
+
+    Json(json!({
+        "result": 0,
+        "stageId": payload["stageId"],
+        "attempts": total,
+        "clears": clears,
+        "clearRate": if total == 0 { 0.0 } else { clears as f64 / total as f64 },
+        "averageTime": average("duration"),
+        "averageKills": average("killCnt"),
+        "averageMissed": average("missedCnt")
+    }))
+}
+
+pub async fn battle_stats_operators(Json(payload): JSON) -> JSON {
+    let attempts = attempts_for(&read_json(BATTLE_STATS_JSON_PATH), &payload);
+    let limit = payload["limit"]
+        .as_u64()
+        .map(|limit| limit as usize)
+        .unwrap_or(DEFAULT_TOP_OPERATORS);
+
+    let mut usage: Vec<(String, u64, u64)> = Vec::new();
+    for attempt in &attempts {
+        let cleared = attempt["completeState"].as_i64().unwrap_or(0) >= COMPLETE_STATE_PASS;
+        for char_id in attempt["chars"].as_array().unwrap_or(&Vec::new()) {
+            let char_id = char_id.as_str().unwrap_or("");
+            match usage.iter_mut().find(|(id, _, _)| id == char_id) {
+                Some((_, count, clears)) => {
+                    *count += 1;
+                    *clears += cleared as u64;
+                }
+                None => usage.push((char_id.to_string(), 1, cleared as u64)),
+            }
+        }
+    }
+    usage.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let operators: Vec<Value> = usage
+        .into_iter()
+        .take(limit)
+        .map(|(char_id, count, clears)| {
+            json!({
+                "charId": char_id,
+                "count": count,
+                "clearRate": clears as f64 / count as f64
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "result": 0,
+        "stageId": payload["stageId"],
+        "operators": operators
+    }))
+}
+
+pub async fn battle_stats_recent(Json(payload): JSON) -> JSON {
+    let attempts = attempts_for(&read_json(BATTLE_STATS_JSON_PATH), &payload);
+    let count = payload["count"]
+        .as_u64()
+        .map(|count| count as usize)
+        .unwrap_or(DEFAULT_RECENT_ATTEMPTS);
+    let recent: Vec<Value> = attempts.into_iter().rev().take(count).collect();
+
+    Json(json!({
+        "result": 0,
+        "stageId": payload["stageId"],
+        "attempts": recent
+    }))
+}
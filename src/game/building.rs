@@ -6,11 +6,26 @@ use crate::{
         url::BUILDING_TABLE_URL,
         user::{BUILDING_JSON_PATH, USER_JSON_PATH},
     },
+    core::time,
     utils::{
-        game::update_data,
-        json::{read_json, write_json, JSON},
+        game::{error_result, update_data},
+        item::{consume_items, grant_items},
+        json::{get_keys, get_values, read_json, write_json, JSON},
     },
 };
+use rand::Rng;
+
+use super::{
+    building_sim::{advance_manufacture, advance_trading, order_rarity, settle_building, Ledger, LMD_ID},
+    furniture::{apply_diy_solution, sync_furniture, take_furniture_grants},
+    medal::{award_medals, count_medal_event, MedalContext, MANUFACTURE_PRODUCT_TIMES},
+    mission::{update_missions, MissionEvent},
+};
+
+/// `roomSlots` states: rubble, cleared for building, and built.
+const SLOT_STATE_UNCLEARED: u64 = 0;
+const SLOT_STATE_CLEARED: u64 = 1;
+const SLOT_STATE_BUILT: u64 = 2;
 
 fn update_building_char_inst_id_list(building_data: Value) -> Value {
     let mut building_data = building_data.clone();
@@ -45,14 +60,30 @@ fn update_building_char_inst_id_list(building_data: Value) -> Value {
     building_data
 }
 
+/// Settles the base up to now, saving the player's items if factories used any, and returns the delta with the new building.
+async fn settle_and_save(building_data: &mut Value, user_data: &mut Value, building_table: &Value) -> Value {
+    let mut modified = settle_building(building_data, &mut user_data["user"], building_table).await;
+    if !get_keys(&modified).is_empty() {
+        write_json(USER_JSON_PATH, user_data.clone());
+    }
+    modified["building"] = building_data.clone();
+    modified
+}
+
 pub async fn building_sync() -> JSON {
     let mut building_data = read_json(BUILDING_JSON_PATH);
-    let user_data = read_json(USER_JSON_PATH);
+    let mut user_data = read_json(USER_JSON_PATH);
+    let building_table = update_data(BUILDING_TABLE_URL).await;
     let mut chars = json!({});
-    for (char_inst_id, _) in user_data["user"]["troop"]["chars"].as_object().unwrap() {
-        chars[char_inst_id] = json!({ "charId": user_data["user"]["troop"]["chars"][char_inst_id]["charId"],
-            "lastApAddTime": 1695000000,
-            "ap": 8640000,
+    for (char_inst_id, char) in user_data["user"]["troop"]["chars"].as_object().unwrap() {
+        if building_data["chars"][char_inst_id].is_object() {
+            chars[char_inst_id] = building_data["chars"][char_inst_id].clone();
+            chars[char_inst_id]["charId"] = char["charId"].clone();
+            continue;
+        }
+        chars[char_inst_id] = json!({ "charId": char["charId"],
+            "lastApAddTime": time(),
+            "ap": building_table["chars"][char["charId"].as_str().unwrap()]["maxManpower"].as_u64().unwrap_or(8640000),
             "roomSlotId": "",
             "index": -1,
             "changeScale": 0,
@@ -71,46 +102,44 @@ pub async fn building_sync() -> JSON {
     }
     building_data["chars"] = chars;
     let mut building_data = update_building_char_inst_id_list(building_data);
-    let building_table = update_data(BUILDING_TABLE_URL).await;
-    let mut furniture = json!({});
-    for (furn_index, _) in building_table["customData"]["furnitures"].as_object().unwrap() {
-        furniture[furn_index] = json!({
-            "count": 9999,
-            "inUse": 0
-        })
+    sync_furniture(&mut building_data, &building_table);
+    let granted = take_furniture_grants(&mut building_data, &mut user_data["user"]);
+    let modified = settle_and_save(&mut building_data, &mut user_data, &building_table).await;
+    if granted {
+        write_json(USER_JSON_PATH, &user_data);
     }
-    building_data["furniture"] = furniture;
-    write_json(BUILDING_JSON_PATH, building_data.clone());
+    write_json(BUILDING_JSON_PATH, building_data);
     Json(json!({
         "playerDataDelta": {
-            "modified": {
-                "building": building_data
-            },
+            "modified": modified,
             "deleted": {}
         }
     }))
 }
 
-pub async fn building_get_recent_visitors() -> JSON {
-    Json(json!({"visitors": []}))
-}
-
-pub async fn building_get_info_share_visitor_num() -> JSON {
-    Json(json!({"num": 0}))
+/// Feeds `events` to the player's missions for base changes that leave the player data alone otherwise, returning the delta.
+async fn track_building_missions(building: &Value, events: &[MissionEvent<'_>]) -> Value {
+    let mut user_data = read_json(USER_JSON_PATH);
+    let mut modified = json!({});
+    update_missions(&mut user_data["user"], building, events, &mut modified).await;
+    write_json(USER_JSON_PATH, user_data);
+    modified
 }
 
 pub async fn building_change_diy_solution(Json(payload): JSON) -> JSON {
     let room_slot_id = payload["roomSlotId"].as_str().unwrap();
-    let diy_solution = payload["solution"].clone();
+    let building_table = update_data(BUILDING_TABLE_URL).await;
 
     let mut building_data = read_json(BUILDING_JSON_PATH);
-    building_data["rooms"]["DORMITORY"][room_slot_id]["diySolution"] = diy_solution;
+    if !apply_diy_solution(&mut building_data, &building_table, room_slot_id, &payload["solution"]) {
+        return error_result();
+    }
     write_json(BUILDING_JSON_PATH, building_data.clone());
+    let mut modified = track_building_missions(&building_data, &[]).await;
+    modified["building"] = building_data;
     Json(json!({
         "playerDataDelta": {
-            "modified": {
-                "building": building_data
-            },
+            "modified": modified,
             "deleted": {}
         }
     }))
@@ -121,6 +150,10 @@ pub async fn building_assign_char(Json(payload): JSON) -> JSON {
     let char_inst_id_list = payload["charInstId"].as_array().unwrap();
 
     let mut building_data = read_json(BUILDING_JSON_PATH);
+    let mut user_data = read_json(USER_JSON_PATH);
+    let building_table = update_data(BUILDING_TABLE_URL).await;
+    // Production so far counts with the old assignments.
+    settle_and_save(&mut building_data, &mut user_data, &building_table).await;
     for char_inst_id in char_inst_id_list {
         let char_inst_id = char_inst_id.as_i64().unwrap();
         if char_inst_id == -1 {
@@ -153,11 +186,11 @@ pub async fn building_set_building_assist(Json(payload): JSON) -> JSON {
     let mut building_data = read_json(BUILDING_JSON_PATH);
     building_data["assist"][&assist_type]["charInstId"] = char_inst_id;
     write_json(BUILDING_JSON_PATH, building_data.clone());
+    let mut modified = track_building_missions(&building_data, &[MissionEvent::Count("SetBuildingAssist", 1)]).await;
+    modified["building"] = building_data;
     Json(json!({
         "playerDataDelta": {
-            "modified": {
-                "building": building_data
-            },
+            "modified": modified,
             "deleted": {}
         }
     }))
@@ -173,3 +206,506 @@ pub async fn building_get_assist_report() -> JSON {
         }
     }))
 }
+
+/// The base and save with the base settled up to now, as every base action starts from.
+pub struct Base {
+    pub building: Value,
+    pub user_data: Value,
+    pub table: Value,
+    pub modified: Value,
+}
+
+pub async fn open_base() -> Base {
+    let mut building = read_json(BUILDING_JSON_PATH);
+    let mut user_data = read_json(USER_JSON_PATH);
+    let table = update_data(BUILDING_TABLE_URL).await;
+    take_furniture_grants(&mut building, &mut user_data["user"]);
+    let modified = settle_building(&mut building, &mut user_data["user"], &table).await;
+    Base {
+        building,
+        user_data,
+        table,
+        modified,
+    }
+}
+
+impl Base {
+    fn slot_phase(&self, room_slot_id: &str) -> usize {
+        self.building["roomSlots"][room_slot_id]["level"].as_u64().unwrap_or(1).max(1) as usize - 1
+    }
+
+    /// Whether a manufacturing or workshop formula's `requireRooms` are met by its room at `level` and its `requireStages` are cleared.
+    fn formula_unlocked(&self, formula: &Value, room_id: &str, level: u64) -> bool {
+        let rooms_met = formula["requireRooms"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|require| require["roomId"] != room_id || level >= require["roomLevel"].as_u64().unwrap_or(0));
+        let stages_met = formula["requireStages"].as_array().unwrap().iter().all(|require| {
+            let stage = &self.user_data["user"]["dungeon"]["stages"][require["stageId"].as_str().unwrap()];
+            stage["state"].as_i64().unwrap_or(0) >= require["rank"].as_i64().unwrap_or(1)
+        });
+        rooms_met && stages_met
+    }
+
+    /// Spends drones, failing if there aren't enough charged.
+    fn use_labor(&mut self, amount: u64) -> bool {
+        let labor = self.building["status"]["labor"]["value"].as_u64().unwrap_or(0);
+        if labor < amount {
+            return false;
+        }
+        self.building["status"]["labor"]["value"] = json!(labor - amount);
+        true
+    }
+
+    pub async fn grant(&mut self, items: &[Value]) -> Vec<Value> {
+        grant_items(&mut self.user_data["user"], items, &mut self.modified).await
+    }
+
+    pub async fn consume(&mut self, items: &[Value]) -> bool {
+        consume_items(&mut self.user_data["user"], items, &mut self.modified).await.is_ok()
+    }
+
+    /// Feeds `events` to the player's missions, checked against the base as it is now.
+    pub async fn track(&mut self, events: &[MissionEvent<'_>]) {
+        update_missions(&mut self.user_data["user"], &self.building, events, &mut self.modified).await;
+    }
+
+    /// Saves the base and player data, and adds their delta to `response`.
+    pub fn save(mut self, mut response: Value) -> JSON {
+        write_json(BUILDING_JSON_PATH, &self.building);
+        write_json(USER_JSON_PATH, &self.user_data);
+        self.modified["building"] = self.building;
+        response["playerDataDelta"] = json!({
+            "modified": self.modified,
+            "deleted": {}
+        });
+        Json(response)
+    }
+}
+
+/// How many built rooms of a type (or `FUNCTIONAL` for any function room) are at `level` or above.
+pub fn room_count(building: &Value, building_table: &Value, room_type: &str, level: u64) -> u64 {
+    get_values(&building["roomSlots"])
+        .iter()
+        .filter(|slot| {
+            let room_id = slot["roomId"].as_str().unwrap_or("");
+            let matches = match room_type {
+                "FUNCTIONAL" => building_table["rooms"][room_id]["category"] == "FUNCTION",
+                room_type => room_id == room_type,
+            };
+            matches && slot["level"].as_u64().unwrap_or(0) >= level
+        })
+        .count() as u64
+}
+
+/// Whether the `nth` room of a kind can be built or upgraded under an unlock condition from `roomUnlockConds`.
+fn unlock_cond_met(building: &Value, building_table: &Value, cond_id: &str, nth: u64) -> bool {
+    let cond = &building_table["roomUnlockConds"][cond_id]["number"][nth.to_string()];
+    match cond["type"].as_str() {
+        Some("NONE") => true,
+        Some(room_type) => {
+            room_count(building, building_table, room_type, cond["level"].as_u64().unwrap_or(0)) >= cond["count"].as_u64().unwrap_or(0)
+        }
+        None => false,
+    }
+}
+
+/// Resizes a room's stations to its current level, unassigning anyone left without one.
+fn resize_stations(building: &mut Value, building_table: &Value, room_slot_id: &str) -> Value {
+    let slot = &building["roomSlots"][room_slot_id];
+    let room_id = slot["roomId"].as_str().unwrap().to_string();
+    let phase = slot["level"].as_u64().unwrap_or(1).max(1) as usize - 1;
+    let stations = building_table["rooms"][&room_id]["phases"][phase]["maxStationedNum"]
+        .as_u64()
+        .unwrap_or(0) as usize;
+    let mut char_inst_ids = slot["charInstIds"].as_array().cloned().unwrap_or_default();
+    char_inst_ids.resize(stations, json!(-1));
+    building["roomSlots"][room_slot_id]["charInstIds"] = json!(char_inst_ids);
+    update_building_char_inst_id_list(building.clone())
+}
+
+/// Takes a factory's finished products out of its warehouse.
+async fn collect_manufacture(base: &mut Base, room_slot_id: &str) -> Vec<Value> {
+    let state = &base.building["rooms"]["MANUFACTURE"][room_slot_id];
+    let formula = &base.table["manufactFormulas"][state["formulaId"].as_str().unwrap_or("")];
+    let count = state["outputSolutionCnt"].as_u64().unwrap_or(0) * formula["count"].as_u64().unwrap_or(1);
+    if formula.is_null() || count == 0 {
+        return Vec::new();
+    }
+    let item = json!({
+        "id": formula["itemId"],
+        "count": count
+    });
+    base.building["rooms"]["MANUFACTURE"][room_slot_id]["outputSolutionCnt"] = json!(0);
+    count_medal_event(MANUFACTURE_PRODUCT_TIMES, count);
+    base.track(&[MissionEvent::Items("ManufactureItem", item["id"].as_str().unwrap_or(""), count)])
+        .await;
+    base.grant(&[item]).await
+}
+
+pub async fn building_settle_manufacture(Json(payload): JSON) -> JSON {
+    let mut base = open_base().await;
+    let mut items = Vec::new();
+    for room_slot_id in payload["roomSlotIdList"].as_array().unwrap_or(&Vec::new()) {
+        items.extend(collect_manufacture(&mut base, room_slot_id.as_str().unwrap()).await);
+    }
+    let context = MedalContext {
+        favor_table: None,
+        base: Some((&base.table, &base.building)),
+    };
+    award_medals(&mut base.user_data["user"], &context, &mut base.modified).await;
+    base.save(json!({ "items": items }))
+}
+
+pub async fn building_change_manufacture_solution(Json(payload): JSON) -> JSON {
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap();
+    let formula_id = payload["targetFormulaId"].as_str().unwrap_or("");
+
+    let mut base = open_base().await;
+    let formula = base.table["manufactFormulas"][formula_id].clone();
+    if formula.is_null() || base.building["rooms"]["MANUFACTURE"][room_slot_id].is_null() {
+        return error_result();
+    }
+    let level = base.building["roomSlots"][room_slot_id]["level"].as_u64().unwrap_or(0);
+    if !base.formula_unlocked(&formula, "MANUFACTURE", level) {
+        return error_result();
+    }
+
+    let items = collect_manufacture(&mut base, room_slot_id).await;
+    let state = &mut base.building["rooms"]["MANUFACTURE"][room_slot_id];
+    state["formulaId"] = json!(formula_id);
+    state["processPoint"] = json!(0);
+    state["outputSolutionCnt"] = json!(0);
+    let context = MedalContext {
+        favor_table: None,
+        base: Some((&base.table, &base.building)),
+    };
+    award_medals(&mut base.user_data["user"], &context, &mut base.modified).await;
+    base.save(json!({ "items": items }))
+}
+
+/// Hands over a trading post order's goods and takes its reward, or returns `None` if the player can't fill it.
+async fn deliver_order(base: &mut Base, slot_id: &str, order_id: &Value) -> Option<Vec<Value>> {
+    let stock = base.building["rooms"]["TRADING"][slot_id]["stock"].as_array()?;
+    let index = stock.iter().position(|order| &order["instId"] == order_id)?;
+    let order = stock[index].clone();
+    if !base.consume(order["delivery"].as_array().unwrap()).await {
+        return None;
+    }
+    base.building["rooms"]["TRADING"][slot_id]["stock"]
+        .as_array_mut()
+        .unwrap()
+        .remove(index);
+    let delivery = &order["delivery"][0];
+    base.track(&[MissionEvent::Items("DeliveryOrder", delivery["id"].as_str().unwrap_or(""), 1)])
+        .await;
+    Some(base.grant(&[order["gain"].clone()]).await)
+}
+
+pub async fn building_delivery_order(Json(payload): JSON) -> JSON {
+    let mut base = open_base().await;
+    let Some(delivered) = deliver_order(&mut base, payload["slotId"].as_str().unwrap(), &payload["orderId"]).await else {
+        return error_result();
+    };
+    base.save(json!({ "delivered": delivered }))
+}
+
+pub async fn building_delivery_batch_order(Json(payload): JSON) -> JSON {
+    let mut base = open_base().await;
+    let mut delivered = Vec::new();
+    for slot_id in payload["slotList"].as_array().unwrap_or(&Vec::new()) {
+        let slot_id = slot_id.as_str().unwrap();
+        let order_ids: Vec<Value> = base.building["rooms"]["TRADING"][slot_id]["stock"]
+            .as_array()
+            .unwrap_or(&Vec::new())
+            .iter()
+            .map(|order| order["instId"].clone())
+            .collect();
+        for order_id in order_ids {
+            // Orders stay in stock once pure gold runs out.
+            match deliver_order(&mut base, slot_id, &order_id).await {
+                Some(items) => delivered.extend(items),
+                None => break,
+            }
+        }
+    }
+    base.save(json!({ "delivered": delivered }))
+}
+
+/// Rolls the workshop's by-products, once per synthesis.
+fn roll_extra_outcomes(formula: &Value, times: u64) -> Vec<Value> {
+    let mut rng = rand::thread_rng();
+    let mut extra = Vec::new();
+    let extra_group = formula["extraOutcomeGroup"].as_array().cloned().unwrap_or_default();
+    let total_weight: u64 = extra_group.iter().map(|outcome| outcome["weight"].as_u64().unwrap_or(0)).sum();
+    for _ in 0..times {
+        if total_weight == 0 || !rng.gen_bool(formula["extraOutcomeRate"].as_f64().unwrap_or(0.0).clamp(0.0, 1.0)) {
+            continue;
+        }
+        let mut roll = rng.gen_range(0..total_weight);
+        for outcome in &extra_group {
+            let weight = outcome["weight"].as_u64().unwrap_or(0);
+            if roll < weight {
+                extra.push(json!({
+                    "id": outcome["itemId"],
+                    "count": outcome["itemCount"]
+                }));
+                break;
+            }
+            roll -= weight;
+        }
+    }
+    extra
+}
+
+pub async fn building_workshop_synthesis(Json(payload): JSON) -> JSON {
+    let formula_id = payload["formulaId"].as_str().unwrap();
+    let times = payload["times"].as_u64().unwrap_or(1);
+
+    let mut base = open_base().await;
+    let formula = base.table["workshopFormulas"][formula_id].clone();
+    let Some(room_slot_id) = get_keys(&base.building["roomSlots"])
+        .into_iter()
+        .find(|room_slot_id| base.building["roomSlots"][room_slot_id]["roomId"] == "WORKSHOP")
+    else {
+        return error_result();
+    };
+    let level = base.building["roomSlots"][&room_slot_id]["level"].as_u64().unwrap_or(0);
+    if formula.is_null() || times == 0 || !base.formula_unlocked(&formula, "WORKSHOP", level) {
+        return error_result();
+    }
+    // Synthesis wears down the morale of the operator stationed in the workshop.
+    let ap_cost = formula["apCost"].as_u64().unwrap_or(0) * times;
+    let worker = base.building["roomSlots"][&room_slot_id]["charInstIds"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find_map(|char_inst_id| char_inst_id.as_i64().filter(|char_inst_id| *char_inst_id >= 0))
+        .map(|char_inst_id| char_inst_id.to_string());
+    let Some((worker, ap)) = worker
+        .map(|worker| {
+            let ap = base.building["chars"][&worker]["ap"].as_u64().unwrap_or(0);
+            (worker, ap)
+        })
+        .filter(|(_, ap)| *ap >= ap_cost)
+    else {
+        return error_result();
+    };
+    let mut costs: Vec<Value> = formula["costs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|cost| {
+            json!({
+                "id": cost["id"],
+                "type": cost["type"],
+                "count": cost["count"].as_u64().unwrap_or(0) * times
+            })
+        })
+        .collect();
+    costs.push(json!({
+        "id": LMD_ID,
+        "type": "GOLD",
+        "count": formula["goldCost"].as_u64().unwrap_or(0) * times
+    }));
+    if !base.consume(&costs).await {
+        return error_result();
+    }
+    base.building["chars"][&worker]["ap"] = json!(ap - ap_cost);
+
+    let extra = roll_extra_outcomes(&formula, times);
+    let results = base
+        .grant(&[json!({
+            "id": formula["itemId"],
+            "count": formula["count"].as_u64().unwrap_or(1) * times
+        })])
+        .await;
+    let extra = base.grant(&extra).await;
+    base.track(&[MissionEvent::Items(
+        "WorkshopSynthesis",
+        formula["itemId"].as_str().unwrap_or(""),
+        times,
+    )])
+    .await;
+    base.save(json!({
+        "results": results,
+        "extraResults": extra
+    }))
+}
+
+/// Clears a slot's rubble so a room can be built there, paying the next of its `cleanCosts`.
+pub async fn building_clean_room_slot(Json(payload): JSON) -> JSON {
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap_or("");
+
+    let mut base = open_base().await;
+    let layout_slots = &base.table["layouts"]["v0"]["slots"];
+    let layout_slot = layout_slots[room_slot_id].clone();
+    if layout_slot.is_null() || base.building["roomSlots"][room_slot_id]["state"].as_u64().unwrap_or(0) != SLOT_STATE_UNCLEARED {
+        return error_result();
+    }
+    let cleared = get_keys(layout_slots)
+        .iter()
+        .filter(|slot_id| {
+            layout_slots[slot_id]["cleanCostId"] == layout_slot["cleanCostId"]
+                && base.building["roomSlots"][slot_id]["state"].as_u64().unwrap_or(0) != SLOT_STATE_UNCLEARED
+        })
+        .count();
+    let clean_cost = &base.table["layouts"]["v0"]["cleanCosts"][layout_slot["cleanCostId"].as_str().unwrap_or("")]["number"];
+    let Some(items) = clean_cost[cleared.to_string()]["items"].as_array().cloned() else {
+        return error_result();
+    };
+    if !base.use_labor(layout_slot["costLabor"].as_u64().unwrap_or(0)) || !base.consume(&items).await {
+        return error_result();
+    }
+
+    base.building["roomSlots"][room_slot_id] = json!({
+        "roomId": "",
+        "level": 0,
+        "state": SLOT_STATE_CLEARED,
+        "completeConstructTime": -1,
+        "charInstIds": []
+    });
+    base.save(json!({}))
+}
+
+pub async fn building_build_room(Json(payload): JSON) -> JSON {
+    let room_id = payload["roomId"].as_str().unwrap();
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap();
+
+    let mut base = open_base().await;
+    let room = base.table["rooms"][room_id].clone();
+    let layout_slot = &base.table["layouts"]["v0"]["slots"][room_slot_id];
+    let slot = &base.building["roomSlots"][room_slot_id];
+    let slot_empty = slot["state"].as_u64().unwrap_or(0) == SLOT_STATE_CLEARED && slot["roomId"].as_str().unwrap_or("").is_empty();
+    let built = room_count(&base.building, &base.table, room_id, 1);
+    let max_count = room["maxCount"].as_i64().unwrap_or(0);
+    if room.is_null()
+        || !slot_empty
+        || layout_slot["category"] != room["category"]
+        || (max_count >= 0 && built >= max_count as u64)
+        || !unlock_cond_met(
+            &base.building,
+            &base.table,
+            room["phases"][0]["unlockCondId"].as_str().unwrap(),
+            built + 1,
+        )
+    {
+        return error_result();
+    }
+    let build_cost = &room["phases"][0]["buildCost"];
+    if !base.use_labor(build_cost["labor"].as_u64().unwrap_or(0)) || !base.consume(build_cost["items"].as_array().unwrap()).await {
+        return error_result();
+    }
+
+    base.building["roomSlots"][room_slot_id] = json!({
+        "roomId": room_id,
+        "level": 1,
+        "state": SLOT_STATE_BUILT,
+        "completeConstructTime": -1,
+        "charInstIds": []
+    });
+    base.building["rooms"][room_id][room_slot_id] = match room_id {
+        "MANUFACTURE" => json!({ "state": 1 }),
+        "TRADING" => json!({
+            "state": 1,
+            "stock": [],
+            "stockLimit": base.table["tradingData"]["phases"][0]["orderLimit"],
+            "strategy": "O_GOLD"
+        }),
+        _ => json!({}),
+    };
+    base.building = resize_stations(&mut base.building, &base.table, room_slot_id);
+    base.track(&[]).await;
+    base.save(json!({}))
+}
+
+pub async fn building_upgrade_room(Json(payload): JSON) -> JSON {
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap();
+
+    let mut base = open_base().await;
+    let room_id = base.building["roomSlots"][room_slot_id]["roomId"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+    let level = base.building["roomSlots"][room_slot_id]["level"].as_u64().unwrap_or(0);
+    let phase = base.table["rooms"][&room_id]["phases"][level as usize].clone();
+    let nth = room_count(&base.building, &base.table, &room_id, level + 1) + 1;
+    if phase.is_null() || !unlock_cond_met(&base.building, &base.table, phase["unlockCondId"].as_str().unwrap(), nth) {
+        return error_result();
+    }
+    let build_cost = &phase["buildCost"];
+    if !base.use_labor(build_cost["labor"].as_u64().unwrap_or(0)) || !base.consume(build_cost["items"].as_array().unwrap()).await {
+        return error_result();
+    }
+
+    base.building["roomSlots"][room_slot_id]["level"] = json!(level + 1);
+    base.building = resize_stations(&mut base.building, &base.table, room_slot_id);
+    base.track(&[]).await;
+    base.save(json!({}))
+}
+
+/// Takes a room down to a lower level; the materials spent on the higher levels aren't refunded.
+pub async fn building_change_room_level(Json(payload): JSON) -> JSON {
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap();
+    let target_level = payload["targetLevel"].as_u64().unwrap_or(0);
+
+    let mut base = open_base().await;
+    let room_id = base.building["roomSlots"][room_slot_id]["roomId"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+    let level = base.building["roomSlots"][room_slot_id]["level"].as_u64().unwrap_or(0);
+    if !base.table["rooms"][&room_id]["canLevelDown"].as_bool().unwrap_or(false) || target_level == 0 || target_level >= level {
+        return error_result();
+    }
+
+    base.building["roomSlots"][room_slot_id]["level"] = json!(target_level);
+    base.building = resize_stations(&mut base.building, &base.table, room_slot_id);
+    base.save(json!({}))
+}
+
+pub async fn building_boost_manufacture(Json(payload): JSON) -> JSON {
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap();
+    let labor_cost = payload["laborCost"].as_u64().unwrap_or(0);
+
+    let mut base = open_base().await;
+    if base.building["rooms"]["MANUFACTURE"][room_slot_id]["formulaId"].is_null() || !base.use_labor(labor_cost) {
+        return error_result();
+    }
+    let phase = base.slot_phase(room_slot_id);
+    let state = &base.building["rooms"]["MANUFACTURE"][room_slot_id];
+    let capacity = state["capacity"]
+        .as_u64()
+        .or(base.table["manufactData"]["phases"][phase]["outputCapacity"].as_u64())
+        .unwrap_or(0);
+    let points = (labor_cost * base.table["manufactReduceTimeUnit"].as_u64().unwrap_or(180)) as f64;
+
+    let mut ledger = Ledger::default();
+    let mut state = state.clone();
+    advance_manufacture(&mut state, &base.table, &base.user_data["user"], &mut ledger, capacity, points);
+    base.building["rooms"]["MANUFACTURE"][room_slot_id] = state;
+    base.consume(&ledger.items()).await;
+    base.save(json!({}))
+}
+
+pub async fn building_boost_trading(Json(payload): JSON) -> JSON {
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap();
+    let labor_cost = payload["laborCost"].as_u64().unwrap_or(0);
+
+    let mut base = open_base().await;
+    if base.building["rooms"]["TRADING"][room_slot_id]["strategy"] != "O_GOLD" || !base.use_labor(labor_cost) {
+        return error_result();
+    }
+    let phase = base.slot_phase(room_slot_id);
+    let rarity = order_rarity(&base.table, phase);
+    let limit = base.building["rooms"]["TRADING"][room_slot_id]["stockLimit"]
+        .as_u64()
+        .or(base.table["tradingData"]["phases"][phase]["orderLimit"].as_u64())
+        .unwrap_or(0);
+    let points = (labor_cost * base.table["tradingReduceTimeUnit"].as_u64().unwrap_or(180)) as f64;
+
+    advance_trading(&mut base.building["rooms"]["TRADING"][room_slot_id], rarity, limit, points);
+    base.track(&[MissionEvent::Count("AccelerateOrder", 1)]).await;
+    base.save(json!({}))
+}
@@ -0,0 +1,622 @@
+use serde_json::{json, Value};
+
+use crate::{
+    core::time,
+    utils::{
+        game::phase_index,
+        item::{consume_items, item_count},
+        json::get_keys,
+    },
+};
+
+use super::mission::{update_missions, MissionEvent};
+
+/// Morale and production are recomputed at this granularity.
+const STEP_SECONDS: u64 = 300;
+/// Offline time beyond this isn't settled; every room has long stalled by then.
+const MAX_SETTLE_SECONDS: u64 = 7 * 86400;
+const DEFAULT_MAX_AP: f64 = 8640000.0;
+const DEFAULT_MAX_LABOR: u64 = 200;
+
+/// Gold orders a trading post rolls through, up to its order rarity: (pure gold delivered, LMD gained, seconds to obtain).
+pub const GOLD_ORDERS: [(u64, u64, u64); 3] = [(2, 1000, 8640), (3, 1500, 12600), (4, 2000, 16560)];
+pub const PURE_GOLD_ID: &str = "3003";
+pub const LMD_ID: &str = "4001";
+
+/// Descriptions with these depend on the rest of the base, which isn't modelled, so they're skipped.
+const CONDITIONAL_MARKERS: [&str; 11] = ["如果", "当与", "若", "每个", "每有", "每名", "每间", "每台", "某个", "一名", "其他"];
+
+/// The formula classes factory skills name, with the `formulaType` of the formulas they cover.
+const FORMULA_CLASSES: [(&str, &str); 4] = [
+    ("作战记录", "F_EXP"),
+    ("贵金属", "F_GOLD"),
+    ("源石", "F_DIAMOND"),
+    ("芯片", "F_ASC"),
+];
+
+/// A base skill effect, read from its description.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Effect {
+    /// Productivity of the operator's own room, in percent.
+    RoomSpeed(f64),
+    /// Productivity of every factory, in percent; only the highest applies.
+    ManufactureSpeed(f64),
+    /// Order efficiency of every trading post, in percent; only the highest applies.
+    TradingSpeed(f64),
+    /// Extra factory capacity or trading post order limit.
+    RoomLimit(f64),
+    /// Morale per hour for the operator, positive for recovery.
+    SelfMorale(f64),
+    /// Morale per hour for everyone in the room.
+    RoomMorale(f64),
+    /// Drone recharge speed, in percent.
+    DroneSpeed(f64),
+}
+
+fn strip_tags(description: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in description.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn number_after(clause: &str, marker: &str) -> Option<f64> {
+    let rest = &clause[clause.find(marker)? + marker.len()..];
+    let number: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || ['.', '+', '-'].contains(c))
+        .collect();
+    number.parse().ok()
+}
+
+/// The `formulaType` a clause restricts itself to, as in "作战记录类配方的生产力".
+fn formula_class(clause: &str) -> Option<&'static str> {
+    FORMULA_CLASSES
+        .iter()
+        .find(|(name, _)| clause.contains(&format!("{name}类配方")))
+        .map(|(_, formula_type)| *formula_type)
+}
+
+/// The effects a skill description grants in a room making `formula_type`. Clauses naming a formula class only apply
+/// while the room makes it, and a "生产…类配方时" clause scopes every clause after it.
+fn parse_effects(description: &str, formula_type: Option<&str>) -> Vec<Effect> {
+    let text = strip_tags(description);
+    if CONDITIONAL_MARKERS.iter().any(|marker| text.contains(marker)) {
+        return Vec::new();
+    }
+
+    let mut effects = Vec::new();
+    let mut in_scope = true;
+    for clause in text.split(['，', '；', '。']) {
+        match formula_class(clause) {
+            Some(class) if clause.ends_with("类配方时") => {
+                in_scope = formula_type == Some(class);
+                continue;
+            }
+            Some(class) if formula_type != Some(class) => continue,
+            _ if !in_scope => continue,
+            _ => (),
+        }
+        let morale = number_after(clause, "心情每小时恢复")
+            .or(number_after(clause, "心情每小时消耗").map(|value| -value))
+            .or(number_after(clause, "每小时心情消耗").map(|value| -value));
+        if let Some(value) = number_after(clause, "所有制造站生产力") {
+            effects.push(Effect::ManufactureSpeed(value));
+        } else if let Some(value) = number_after(clause, "所有贸易站订单效率") {
+            effects.push(Effect::TradingSpeed(value));
+        } else if let Some(value) = number_after(clause, "生产力").or(number_after(clause, "订单获取效率")) {
+            effects.push(Effect::RoomSpeed(value));
+        } else if let Some(value) = number_after(clause, "仓库容量上限").or(number_after(clause, "订单上限")) {
+            effects.push(Effect::RoomLimit(value));
+        } else if let Some(value) = number_after(clause, "无人机充能速度") {
+            effects.push(Effect::DroneSpeed(value));
+        } else if let Some(value) = morale {
+            if clause.contains("所有干员") || clause.contains("全体干员") {
+                effects.push(Effect::RoomMorale(value));
+            } else {
+                effects.push(Effect::SelfMorale(value));
+            }
+        }
+    }
+    effects
+}
+
+/// The effects of an operator's base skills that work in `room_id` while it makes `formula_type`, taking the best level each
+/// skill slot has unlocked.
+fn active_effects(building_table: &Value, troop_char: &Value, room_id: &str, formula_type: Option<&str>) -> Vec<Effect> {
+    let phase = troop_char["evolvePhase"].as_i64().unwrap_or(0);
+    let level = troop_char["level"].as_i64().unwrap_or(1);
+    let char_id = troop_char["charId"].as_str().unwrap_or("");
+
+    let mut effects = Vec::new();
+    for slot in building_table["chars"][char_id]["buffChar"].as_array().unwrap_or(&Vec::new()) {
+        let active = slot["buffData"].as_array().into_iter().flatten().rfind(|buff| {
+            let cond_phase = phase_index(&buff["cond"]["phase"]);
+            phase > cond_phase || (phase == cond_phase && level >= buff["cond"]["level"].as_i64().unwrap_or(1))
+        });
+        let Some(buff) = active.map(|buff| &building_table["buffs"][buff["buffId"].as_str().unwrap()]) else {
+            continue;
+        };
+        if buff["roomType"] == room_id {
+            effects.extend(parse_effects(buff["description"].as_str().unwrap_or(""), formula_type));
+        }
+    }
+    effects
+}
+
+struct Worker {
+    inst_id: String,
+    ap: f64,
+    max_ap: f64,
+    work_time: u64,
+    effects: Vec<Effect>,
+}
+
+impl Worker {
+    fn sum(&self, pick: fn(&Effect) -> Option<f64>) -> f64 {
+        self.effects.iter().filter_map(pick).sum()
+    }
+}
+
+struct Room {
+    slot_id: String,
+    room_id: String,
+    phase: usize,
+    workers: Vec<Worker>,
+}
+
+impl Room {
+    fn sum(&self, pick: fn(&Effect) -> Option<f64>) -> f64 {
+        self.workers.iter().map(|worker| worker.sum(pick)).sum()
+    }
+
+    fn working(&self) -> impl Iterator<Item = &Worker> {
+        self.workers.iter().filter(|worker| worker.ap > 0.0)
+    }
+}
+
+fn room_speed(effect: &Effect) -> Option<f64> {
+    match effect {
+        Effect::RoomSpeed(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn room_limit(effect: &Effect) -> Option<f64> {
+    match effect {
+        Effect::RoomLimit(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn self_morale(effect: &Effect) -> Option<f64> {
+    match effect {
+        Effect::SelfMorale(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn room_morale(effect: &Effect) -> Option<f64> {
+    match effect {
+        Effect::RoomMorale(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn drone_speed(effect: &Effect) -> Option<f64> {
+    match effect {
+        Effect::DroneSpeed(value) => Some(*value),
+        _ => None,
+    }
+}
+
+/// The highest global effect among the control center's working operators.
+fn control_bonus(rooms: &[Room], pick: fn(&Effect) -> Option<f64>) -> f64 {
+    rooms
+        .iter()
+        .filter(|room| room.room_id == "CONTROL")
+        .flat_map(|room| room.working())
+        .flat_map(|worker| worker.effects.iter().filter_map(pick))
+        .fold(0.0, f64::max)
+}
+
+fn load_rooms(building: &Value, user: &Value, building_table: &Value) -> Vec<Room> {
+    let mut rooms = Vec::new();
+    for slot_id in get_keys(&building["roomSlots"]) {
+        let slot = &building["roomSlots"][&slot_id];
+        let room_id = slot["roomId"].as_str().unwrap_or("").to_string();
+        let formula_id = building["rooms"][&room_id][&slot_id]["formulaId"].as_str().unwrap_or("");
+        let formula_type = building_table["manufactFormulas"][formula_id]["formulaType"].as_str();
+        let mut workers = Vec::new();
+        for inst_id in slot["charInstIds"].as_array().unwrap_or(&Vec::new()) {
+            let inst_id = match inst_id {
+                Value::String(inst_id) => inst_id.clone(),
+                inst_id if inst_id.as_i64().unwrap_or(-1) >= 0 => inst_id.to_string(),
+                _ => continue,
+            };
+            let troop_char = &user["troop"]["chars"][&inst_id];
+            let char = &building["chars"][&inst_id];
+            let max_ap = building_table["chars"][troop_char["charId"].as_str().unwrap_or("")]["maxManpower"]
+                .as_f64()
+                .unwrap_or(DEFAULT_MAX_AP);
+            workers.push(Worker {
+                ap: char["ap"].as_f64().unwrap_or(max_ap),
+                max_ap,
+                work_time: char["workTime"].as_u64().unwrap_or(0),
+                effects: active_effects(building_table, troop_char, &room_id, formula_type),
+                inst_id,
+            });
+        }
+        rooms.push(Room {
+            phase: slot["level"].as_u64().unwrap_or(1).max(1) as usize - 1,
+            slot_id,
+            room_id,
+            workers,
+        });
+    }
+    rooms
+}
+
+/// Power plants' output against every other room's draw, as `(supply, demand)`.
+fn electricity(building_table: &Value, rooms: &[Room]) -> (i64, i64) {
+    let (mut supply, mut demand) = (0, 0);
+    for room in rooms {
+        let electricity = building_table["rooms"][&room.room_id]["phases"][room.phase]["electricity"]
+            .as_i64()
+            .unwrap_or(0);
+        if electricity > 0 {
+            supply += electricity;
+        } else {
+            demand -= electricity;
+        }
+    }
+    (supply, demand)
+}
+
+/// Items reserved by factories during a settlement, consumed once it's done.
+#[derive(Default)]
+pub struct Ledger {
+    costs: Vec<(String, String, u64)>,
+}
+
+impl Ledger {
+    fn reserved(&self, item_id: &str) -> u64 {
+        self.costs
+            .iter()
+            .filter(|(id, _, _)| id == item_id)
+            .map(|(_, _, count)| count)
+            .sum()
+    }
+
+    /// Reserves a batch of costs if the player can still afford all of them.
+    fn reserve(&mut self, user: &Value, costs: &[Value]) -> bool {
+        let affordable = costs.iter().all(|cost| {
+            let item_id = cost["id"].as_str().unwrap();
+            let item_type = cost["type"].as_str().unwrap_or("MATERIAL");
+            item_count(user, item_id, item_type) >= self.reserved(item_id) + cost["count"].as_u64().unwrap_or(0)
+        });
+        if affordable {
+            for cost in costs {
+                self.costs.push((
+                    cost["id"].as_str().unwrap().to_string(),
+                    cost["type"].as_str().unwrap_or("MATERIAL").to_string(),
+                    cost["count"].as_u64().unwrap_or(0),
+                ));
+            }
+        }
+        affordable
+    }
+
+    pub fn items(&self) -> Vec<Value> {
+        self.costs
+            .iter()
+            .map(|(id, item_type, count)| json!({ "id": id, "type": item_type, "count": count }))
+            .collect()
+    }
+}
+
+/// A gold order as it sits in a trading post's `stock`.
+pub fn gold_order(inst_id: u64, rarity: usize) -> Value {
+    let (pure_gold, lmd, _) = GOLD_ORDERS[rarity];
+    json!({
+        "instId": inst_id,
+        "type": "O_GOLD",
+        "delivery": [{ "type": "MATERIAL", "id": PURE_GOLD_ID, "count": pure_gold }],
+        "gain": { "type": "GOLD", "id": LMD_ID, "count": lmd },
+        "isViolated": false
+    })
+}
+
+/// How many distinct gold orders a trading post at `phase` can roll.
+pub fn order_rarity(building_table: &Value, phase: usize) -> usize {
+    let rarity = building_table["tradingData"]["phases"][phase]["orderRarity"].as_u64().unwrap_or(1);
+    (rarity as usize).clamp(1, GOLD_ORDERS.len())
+}
+
+/// Puts `points` of work into a factory's formula, finishing products until the warehouse is full or materials run out.
+pub fn advance_manufacture(state: &mut Value, building_table: &Value, user: &Value, ledger: &mut Ledger, capacity: u64, points: f64) {
+    let Some(formula_id) = state["formulaId"].as_str().map(str::to_string) else {
+        return;
+    };
+    let formula = &building_table["manufactFormulas"][&formula_id];
+    let cost_point = formula["costPoint"].as_f64().unwrap_or(0.0);
+    if cost_point <= 0.0 {
+        return;
+    }
+    let weight = formula["weight"].as_u64().unwrap_or(1);
+    let costs = formula["costs"].as_array().cloned().unwrap_or_default();
+
+    let mut process_point = state["processPoint"].as_f64().unwrap_or(0.0) + points;
+    let mut output = state["outputSolutionCnt"].as_u64().unwrap_or(0);
+    while process_point >= cost_point {
+        if (output + 1) * weight > capacity || !ledger.reserve(user, &costs) {
+            process_point = cost_point;
+            break;
+        }
+        output += 1;
+        process_point -= cost_point;
+    }
+    state["processPoint"] = json!(process_point);
+    state["outputSolutionCnt"] = json!(output);
+    state["capacity"] = json!(capacity);
+}
+
+/// Puts `points` of work into a trading post's next order, stocking orders up to `limit`.
+pub fn advance_trading(state: &mut Value, rarity: usize, limit: u64, points: f64) {
+    let mut stock = state["stock"].as_array().cloned().unwrap_or_default();
+    let mut order_inst_id = state["next"]["orderInstId"].as_u64().unwrap_or(0);
+    let mut max_point = GOLD_ORDERS[order_inst_id as usize % rarity].2 as f64;
+    let mut process_point = state["next"]["processPoint"].as_f64().unwrap_or(0.0);
+    if (stock.len() as u64) < limit {
+        process_point += points;
+    }
+    while process_point >= max_point && (stock.len() as u64) < limit {
+        stock.push(gold_order(order_inst_id, order_inst_id as usize % rarity));
+        process_point -= max_point;
+        order_inst_id += 1;
+        max_point = GOLD_ORDERS[order_inst_id as usize % rarity].2 as f64;
+    }
+    state["stock"] = json!(stock);
+    state["stockLimit"] = json!(limit);
+    state["next"]["processPoint"] = json!(process_point);
+    state["next"]["maxPoint"] = json!(max_point);
+    state["next"]["orderInstId"] = json!(order_inst_id);
+}
+
+fn settle_manufacture(room: &Room, state: &mut Value, building_table: &Value, user: &Value, ledger: &mut Ledger, points: f64) {
+    let capacity = building_table["manufactData"]["phases"][room.phase]["outputCapacity"]
+        .as_u64()
+        .unwrap_or(0)
+        + room.sum(room_limit) as u64;
+    advance_manufacture(state, building_table, user, ledger, capacity, points);
+}
+
+fn settle_trading(room: &Room, state: &mut Value, building_table: &Value, speed: f64, dt: f64) {
+    if state["strategy"] != "O_GOLD" {
+        return;
+    }
+    let limit = building_table["tradingData"]["phases"][room.phase]["orderLimit"]
+        .as_u64()
+        .unwrap_or(0)
+        + room.sum(room_limit) as u64;
+    advance_trading(state, order_rarity(building_table, room.phase), limit, dt * speed);
+    state["next"]["speed"] = json!(speed);
+}
+
+/// Runs the base from its last settlement up to the game clock: morale drains in work rooms and recovers in dormitories,
+/// factories and trading posts produce while their rooms are powered and staffed, and power plants recharge drones.
+/// Factory costs are taken from `user`, operators resting back to full morale count for missions, and the returned delta
+/// lists the changed balances.
+pub async fn settle_building(building: &mut Value, user: &mut Value, building_table: &Value) -> Value {
+    let now = time();
+    let last_update = building["status"]["lastUpdateTime"].as_u64().unwrap_or(now).min(now);
+    let elapsed = (now - last_update).min(MAX_SETTLE_SECONDS);
+
+    let mut rooms = load_rooms(building, user, building_table);
+    let (supply, demand) = electricity(building_table, &rooms);
+    let powered = supply >= demand;
+    let ap_per_morale = building_table["manpowerDisplayFactor"].as_f64().unwrap_or(360000.0) / 3600.0;
+    let labor_recover_time = building_table["laborRecoverTime"].as_f64().unwrap_or(360.0);
+    let max_labor = building["status"]["labor"]["maxValue"].as_u64().unwrap_or(DEFAULT_MAX_LABOR);
+    let mut labor = building["status"]["labor"]["value"].as_u64().unwrap_or(max_labor);
+    let mut labor_point = building["status"]["labor"]["processPoint"].as_f64().unwrap_or(0.0);
+    let mut ledger = Ledger::default();
+    let mut recovered = 0;
+
+    let mut settled = 0;
+    while settled < elapsed {
+        let dt = STEP_SECONDS.min(elapsed - settled);
+        settled += dt;
+        let dt = dt as f64;
+
+        let manufacture_bonus = control_bonus(&rooms, |effect| match effect {
+            Effect::ManufactureSpeed(value) => Some(*value),
+            _ => None,
+        });
+        let trading_bonus = control_bonus(&rooms, |effect| match effect {
+            Effect::TradingSpeed(value) => Some(*value),
+            _ => None,
+        });
+        let mut drone_speed_bonus = 1.0;
+
+        for room in rooms.iter_mut() {
+            let room_data = &building_table["rooms"][&room.room_id]["phases"][room.phase];
+            let working = room.working().count();
+            let shared_morale = room.sum(room_morale);
+
+            // Morale
+            let room_drain = if room.room_id == "DORMITORY" {
+                let recover = building_table["dormData"]["phases"][room.phase]["manpowerRecover"]
+                    .as_f64()
+                    .unwrap_or(0.0);
+                let comfort = building["rooms"]["DORMITORY"][&room.slot_id]["comfort"]
+                    .as_f64()
+                    .unwrap_or(0.0)
+                    .min(building_table["comfortLimit"].as_f64().unwrap_or(0.0));
+                -(recover + comfort / building_table["comfortManpowerRecoverFactor"].as_f64().unwrap_or(1.0))
+            } else {
+                let cost_by_num = match room.room_id.as_str() {
+                    "MANUFACTURE" => &building_table["manufactManpowerCostByNum"][working],
+                    "TRADING" => &building_table["tradingManpowerCostByNum"][working],
+                    _ => &Value::Null,
+                };
+                room_data["manpowerCost"].as_f64().unwrap_or(0.0) + cost_by_num.as_f64().unwrap_or(0.0)
+            };
+            for worker in room.workers.iter_mut() {
+                let is_dorm = room.room_id == "DORMITORY";
+                if !is_dorm && worker.ap <= 0.0 {
+                    continue;
+                }
+                let change = -room_drain + (shared_morale + worker.sum(self_morale)) * ap_per_morale;
+                let resting = worker.ap < worker.max_ap;
+                worker.ap = (worker.ap + change * dt).clamp(0.0, worker.max_ap);
+                if !is_dorm {
+                    worker.work_time += dt as u64;
+                } else if resting && worker.ap >= worker.max_ap {
+                    recovered += 1;
+                }
+            }
+
+            // Production
+            if working == 0 || (!powered && room_data["electricity"].as_i64().unwrap_or(0) < 0) {
+                continue;
+            }
+            let basic_speed_buff = match room.room_id.as_str() {
+                "MANUFACTURE" => building_table["manufactData"]["basicSpeedBuff"].as_f64(),
+                "TRADING" => building_table["tradingData"]["basicSpeedBuff"].as_f64(),
+                "POWER" => building_table["powerData"]["basicSpeedBuff"].as_f64(),
+                _ => None,
+            }
+            .unwrap_or(0.0);
+            let worker_speed: f64 = room.working().map(|worker| basic_speed_buff + worker.sum(room_speed) / 100.0).sum();
+            match room.room_id.as_str() {
+                "MANUFACTURE" => {
+                    let base = building_table["manufactData"]["phases"][room.phase]["speed"]
+                        .as_f64()
+                        .unwrap_or(1.0);
+                    let speed = base + worker_speed + manufacture_bonus / 100.0;
+                    let state = &mut building["rooms"]["MANUFACTURE"][&room.slot_id];
+                    settle_manufacture(room, state, building_table, user, &mut ledger, dt * speed);
+                }
+                "TRADING" => {
+                    let base = building_table["tradingData"]["phases"][room.phase]["orderSpeed"]
+                        .as_f64()
+                        .unwrap_or(1.0);
+                    let speed = base + worker_speed + trading_bonus / 100.0;
+                    settle_trading(room, &mut building["rooms"]["TRADING"][&room.slot_id], building_table, speed, dt);
+                }
+                "POWER" => {
+                    let drone_bonus: f64 = room.working().map(|worker| worker.sum(drone_speed) / 100.0).sum();
+                    drone_speed_bonus += worker_speed + drone_bonus;
+                }
+                _ => {}
+            }
+        }
+
+        // Drones
+        if labor < max_labor {
+            labor_point += dt * drone_speed_bonus;
+            while labor_point >= labor_recover_time && labor < max_labor {
+                labor += 1;
+                labor_point -= labor_recover_time;
+            }
+        }
+        if labor >= max_labor {
+            labor_point = 0.0;
+        }
+    }
+
+    for room in &rooms {
+        for worker in &room.workers {
+            let char = &mut building["chars"][&worker.inst_id];
+            char["ap"] = json!(worker.ap.round() as u64);
+            char["lastApAddTime"] = json!(now);
+            char["workTime"] = json!(worker.work_time);
+        }
+        if ["MANUFACTURE", "TRADING"].contains(&room.room_id.as_str()) {
+            building["rooms"][&room.room_id][&room.slot_id]["lastUpdateTime"] = json!(now);
+        }
+    }
+    building["status"]["labor"] = json!({
+        "value": labor,
+        "maxValue": max_labor,
+        "processPoint": labor_point,
+        "lastUpdateTime": now,
+        "buffSpeed": 0
+    });
+    building["status"]["electricity"] = json!({
+        "supply": supply,
+        "demand": demand
+    });
+    building["status"]["lastUpdateTime"] = json!(now);
+
+    let mut modified = json!({});
+    consume_items(user, &ledger.items(), &mut modified).await.unwrap();
+    if recovered > 0 {
+        update_missions(
+            user,
+            building,
+            &[MissionEvent::Count("RecoverCharBaseAp", recovered)],
+            &mut modified,
+        )
+        .await;
+    }
+    modified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_room_and_global_effects() {
+        assert_eq!(
+            parse_effects("进驻贸易站时，订单获取效率<@cc.vup>+30%</>", None),
+            [Effect::RoomSpeed(30.0)]
+        );
+        assert_eq!(
+            parse_effects("进驻控制中枢时，所有制造站生产力<@cc.vup>+2%</>（同种效果取最高）", None),
+            [Effect::ManufactureSpeed(2.0)]
+        );
+        assert_eq!(
+            parse_effects("进驻控制中枢时，控制中枢内所有干员的心情每小时恢复<@cc.vup>+0.05</>", None),
+            [Effect::RoomMorale(0.05)]
+        );
+        assert_eq!(
+            parse_effects("进驻发电站时，无人机充能速度<@cc.vup>+10%</>", None),
+            [Effect::DroneSpeed(10.0)]
+        );
+    }
+
+    #[test]
+    fn formula_effects_only_apply_to_their_formula() {
+        let description = "进驻制造站时，<@cc.kw>作战记录</>类配方的生产力<@cc.vup>+30%</>";
+        assert_eq!(parse_effects(description, Some("F_EXP")), [Effect::RoomSpeed(30.0)]);
+        assert!(parse_effects(description, Some("F_GOLD")).is_empty());
+        assert!(parse_effects(description, None).is_empty());
+
+        let description = "进驻制造站时，<@cc.kw>贵金属</>类配方的生产力<@cc.vup>+25%</>，心情每小时消耗<@cc.vdown>+0.25</>";
+        assert_eq!(
+            parse_effects(description, Some("F_GOLD")),
+            [Effect::RoomSpeed(25.0), Effect::SelfMorale(-0.25)]
+        );
+        assert_eq!(parse_effects(description, Some("F_EXP")), [Effect::SelfMorale(-0.25)]);
+
+        let description = "进驻制造站时，生产<@cc.kw>作战记录</>类配方时，仓库容量上限<@cc.vup>+12</>";
+        assert_eq!(parse_effects(description, Some("F_EXP")), [Effect::RoomLimit(12.0)]);
+        assert!(parse_effects(description, Some("F_DIAMOND")).is_empty());
+    }
+
+    #[test]
+    fn skips_effects_that_depend_on_the_rest_of_the_base() {
+        let description = "进驻制造站时，若<@cc.kw>古米</>在贸易站，则<@cc.kw>作战记录</>类配方的生产力<@cc.vup>+35%</>";
+        assert!(parse_effects(description, Some("F_EXP")).is_empty());
+    }
+}
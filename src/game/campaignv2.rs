@@ -1,58 +1,390 @@
 use axum::Json;
-use serde_json::json;
+use serde_json::{json, Value};
+use uuid::Uuid;
 
 use crate::{
-    constants::user::BATTLE_REPLAY_JSON_PATH,
-    utils::json::{read_json, write_json, JSON},
+    constants::{
+        url::{CAMPAIGN_TABLE_URL, FAVOR_TABLE_URL, GAMEDATA_CONST_URL, STAGE_TABLE_URL},
+        user::{BATTLE_JSON_PATH, USER_JSON_PATH},
+    },
+    core::time,
+    game::{battle_stats::record_battle, quest_manager::quest::take_pending_battle, replay::register_battle},
+    utils::{
+        ap::{add_ap, cost_ap, infinite_ap, refresh_ap},
+        battle_data::BattleDataDecoder,
+        comp::min,
+        game::{error_result, update_data, week_start},
+        item::{consume_items, grant_items},
+        json::{get_keys, get_map, read_json, write_json, JSON},
+        level::add_squad_favor,
+    },
 };
 
+const MISSION_UNCOMPLETED: u64 = 1;
+const MISSION_COMPLETE: u64 = 2;
+
+/// Picks the rotation entry that is open at `now`, falling back to the latest one that has started.
+fn current_rotation(open_times: &Value, now: u64) -> Value {
+    open_times
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry["startTs"].as_u64().unwrap_or(0) <= now)
+        .max_by_key(|entry| entry["startTs"].as_u64().unwrap_or(0))
+        .cloned()
+        .unwrap_or(Value::Null)
+}
+
+/// The weekly orundum cap: the base fee plus every claimed milestone and completed mission that raises it.
+fn total_fee(campaign_table: &Value, const_table: &Value, campaigns: &Value) -> u64 {
+    let mut fee = const_table["initCampaignTotalFee"].as_u64().unwrap_or(0);
+    for (stage_id, instance) in get_map(&campaigns["instances"]) {
+        let ladders = campaign_table["campaigns"][&stage_id]["breakLadders"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let reward_status = instance["rewardStatus"].as_array().cloned().unwrap_or_default();
+        for (ladder, status) in ladders.iter().zip(reward_status) {
+            if status.as_u64().unwrap_or(0) == 1 {
+                fee += ladder["breakFeeAdd"].as_u64().unwrap_or(0);
+            }
+        }
+    }
+    for (mission_id, state) in get_map(&campaigns["missions"]) {
+        if state.as_u64().unwrap_or(0) == MISSION_COMPLETE {
+            fee += campaign_table["campaignMissions"][&mission_id]["breakFeeAdd"].as_u64().unwrap_or(0);
+        }
+    }
+    fee
+}
+
+/// Resets the weekly orundum counter once the clock has passed the weekly reset, and recomputes the cap.
+fn refresh_campaign_fee(campaigns: &mut Value, campaign_table: &Value, const_table: &Value) {
+    let now = time();
+    if campaigns["lastRefreshTs"].as_u64().unwrap_or(0) < week_start(now) {
+        campaigns["campaignCurrentFee"] = json!(0);
+        campaigns["lastRefreshTs"] = json!(now);
+    }
+    campaigns["campaignTotalFee"] = json!(total_fee(campaign_table, const_table, campaigns));
+}
+
+/// Resolves which reward mode a stage is being played in.
+fn campaign_mode(campaigns: &Value, stage_id: &str) -> &'static str {
+    if campaigns["open"]["rotate"].as_str() == Some(stage_id) {
+        "ROTATE"
+    } else if campaigns["open"]["permanent"]
+        .as_array()
+        .is_some_and(|permanent| permanent.contains(&json!(stage_id)))
+    {
+        "PERMANENT"
+    } else {
+        "TRAINING"
+    }
+}
+
+/// The highest gain ladder reached with `kills`.
+fn gain_ladder(campaign: &Value, mode: &str, kills: u64) -> Value {
+    campaign["dropGains"][mode]["gainLadders"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .rev()
+        .find(|ladder| ladder["killCnt"].as_u64().unwrap_or(0) <= kills)
+        .cloned()
+        .unwrap_or(json!({}))
+}
+
+/// Grants the gold, EXP, favor and orundum of a gain ladder, capping orundum at the remaining weekly fee.
+/// Returns the granted rewards, orundum rewards and the fee before and after.
+async fn settle_gain_ladder(
+    user: &mut Value,
+    ladder: &Value,
+    squad: &Value,
+    deployed: &[String],
+    modified: &mut Value,
+) -> (Vec<Value>, Vec<Value>, u64, u64) {
+    let favor_table = update_data(FAVOR_TABLE_URL).await;
+    add_squad_favor(user, &favor_table, squad, deployed, ladder["favor"].as_u64().unwrap_or(0), modified);
+
+    let gains = [
+        json!({ "id": "4001", "count": ladder["goldGain"].as_u64().unwrap_or(0), "type": "GOLD" }),
+        json!({ "id": "5001", "count": ladder["expGain"].as_u64().unwrap_or(0), "type": "EXP_PLAYER" }),
+    ];
+    let rewards = grant_items(user, &gains, modified).await;
+
+    let campaigns = &user["campaignsV2"];
+    let fee_before = campaigns["campaignCurrentFee"].as_u64().unwrap_or(0);
+    let remaining_fee = campaigns["campaignTotalFee"].as_u64().unwrap_or(0).saturating_sub(fee_before);
+    let diamond_shard = min(ladder["displayDiamondShdNum"].as_u64().unwrap_or(0), remaining_fee);
+    let diamond_shard_reward = [json!({ "id": "4003", "count": diamond_shard, "type": "DIAMOND_SHD" })];
+    let diamond_material_rewards = grant_items(user, &diamond_shard_reward, modified).await;
+    user["campaignsV2"]["campaignCurrentFee"] = json!(fee_before + diamond_shard);
+
+    (rewards, diamond_material_rewards, fee_before, fee_before + diamond_shard)
+}
+
+/// Builds `campaignsV2` for `syncData`, keeping previous progress and opening the stages the rotation schedule allows.
+pub async fn campaignv2_sync(previous: &Value) -> Value {
+    let campaign_table = update_data(CAMPAIGN_TABLE_URL).await;
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let now = time();
+
+    let mut campaigns = json!({
+        "campaignCurrentFee": previous["campaignCurrentFee"].as_u64().unwrap_or(0),
+        "campaignTotalFee": 0,
+        "lastRefreshTs": previous["lastRefreshTs"].as_u64().unwrap_or(0),
+        "instances": {},
+        "open": {},
+        "missions": {},
+        "sweepMaxKills": {}
+    });
+
+    let mut permanent = Vec::new();
+    for stage_id in get_keys(&campaign_table["campaigns"]) {
+        let ladder_count = campaign_table["campaigns"][&stage_id]["breakLadders"]
+            .as_array()
+            .map_or(0, Vec::len);
+        campaigns["instances"][&stage_id] = match previous["instances"].get(&stage_id) {
+            Some(instance) => instance.clone(),
+            None => json!({
+                "maxKills": 0,
+                "rewardStatus": vec![0; ladder_count]
+            }),
+        };
+        if let Some(sweep_max_kills) = previous["sweepMaxKills"].get(&stage_id) {
+            campaigns["sweepMaxKills"][&stage_id] = sweep_max_kills.clone();
+        }
+        if !stage_id.starts_with("camp_r_") {
+            permanent.push(stage_id);
+        }
+    }
+
+    // Like kill counts, missions a save hasn't seen yet start over.
+    for mission_id in get_keys(&campaign_table["campaignMissions"]) {
+        campaigns["missions"][&mission_id] = json!(previous["missions"][&mission_id].as_u64().unwrap_or(MISSION_UNCOMPLETED));
+    }
+
+    let rotate = current_rotation(&campaign_table["campaignRotateStageOpenTimes"], now);
+    let training = current_rotation(&campaign_table["campaignTrainingStageOpenTimes"], now);
+    let training_all = current_rotation(&campaign_table["campaignTrainingAllOpenTimes"], now);
+    campaigns["open"] = json!({
+        "permanent": permanent,
+        "rotate": rotate["stageId"],
+        "rGroup": rotate["groupId"],
+        "training": training["stages"].as_array().cloned().unwrap_or_default(),
+        "tGroup": training["groupId"],
+        "tAllOpen": training_all["groupId"]
+    });
+
+    refresh_campaign_fee(&mut campaigns, &campaign_table, &const_table);
+    campaigns
+}
+
 pub async fn campaignv2_battle_start(Json(payload): JSON) -> JSON {
-    let stage_id = payload["stageId"].as_str().unwrap();
+    let Some(stage_id) = payload["stageId"].as_str() else {
+        return error_result();
+    };
+
+    let stage_table = update_data(STAGE_TABLE_URL).await;
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let ap_cost = stage_table["stages"][stage_id]["apCost"].as_u64().unwrap_or(0);
 
-    let mut replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
-    replay_data["current"] = json!(stage_id);
-    write_json(BATTLE_REPLAY_JSON_PATH, replay_data);
+    let mut user_data = read_json(USER_JSON_PATH);
+    let status = &mut user_data["user"]["status"];
+    refresh_ap(status, &const_table);
+    if !cost_ap(status, ap_cost) {
+        return error_result();
+    }
+    let modified_status = json!({
+        "ap": status["ap"],
+        "lastApAddTime": status["lastApAddTime"]
+    });
+    write_json(USER_JSON_PATH, user_data);
+
+    let battle_id = Uuid::new_v4().to_string();
+    register_battle(&battle_id, stage_id);
+    write_json(
+        BATTLE_JSON_PATH,
+        json!({
+            "battleId": battle_id,
+            "stageId": stage_id,
+            "apCost": ap_cost,
+            "isPractice": false,
+            "squad": payload["squad"]["slots"],
+            "startTs": time()
+        }),
+    );
 
     Json(json!({
-        "battleId": "abcdefgh-1234-5678-a1b2c3d4e5f6",
+        "battleId": battle_id,
         "playerDataDelta": {
-            "modified": {},
+            "modified": {
+                "status": modified_status
+            },
             "deleted": {}
         },
         "result": 0
     }))
 }
 
-pub async fn campaignv2_battle_finish() -> JSON {
+pub async fn campaignv2_battle_finish(Json(payload): JSON) -> JSON {
+    let battle_data = payload["data"]
+        .as_str()
+        .and_then(|data| BattleDataDecoder::new().decrypt_battle_data(data.to_string()).ok());
+    let Some(battle_data) = battle_data else {
+        return error_result();
+    };
+    let Some(battle) = take_pending_battle(&payload, &battle_data) else {
+        return error_result();
+    };
+    let stage_id = battle["stageId"].as_str().unwrap_or("");
+    let kills = battle_data.kill_cnt;
+
+    let campaign_table = update_data(CAMPAIGN_TABLE_URL).await;
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let campaign = &campaign_table["campaigns"][stage_id];
+    if !campaign.is_object() {
+        return error_result();
+    }
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let user = &mut user_data["user"];
+    refresh_ap(&mut user["status"], &const_table);
+    refresh_campaign_fee(&mut user["campaignsV2"], &campaign_table, &const_table);
+
+    let mut modified = json!({});
+    let mode = campaign_mode(&user["campaignsV2"], stage_id);
+    let ladder = gain_ladder(campaign, mode, kills);
+
+    let ap_fail_return = ladder["apFailReturn"].as_u64().unwrap_or(0);
+    if !infinite_ap() {
+        add_ap(&mut user["status"], ap_fail_return);
+    }
+
+    let mut first_rewards = Vec::new();
+    if mode != "TRAINING" {
+        let instance = &user["campaignsV2"]["instances"][stage_id];
+        let max_kills = instance["maxKills"].as_u64().unwrap_or(0).max(kills);
+        let mut reward_status = instance["rewardStatus"].as_array().cloned().unwrap_or_default();
+
+        let break_ladders = campaign["breakLadders"].as_array().cloned().unwrap_or_default();
+        reward_status.resize(break_ladders.len(), json!(0));
+        for (index, break_ladder) in break_ladders.iter().enumerate() {
+            if reward_status[index].as_u64().unwrap_or(0) == 0 && break_ladder["killCnt"].as_u64().unwrap_or(0) <= kills {
+                reward_status[index] = json!(1);
+                let rewards = break_ladder["rewards"].as_array().cloned().unwrap_or_default();
+                first_rewards.extend(grant_items(user, &rewards, &mut modified).await);
+            }
+        }
+
+        let missions = user["campaignsV2"]["missions"].clone();
+        for (mission_id, state) in get_map(&missions) {
+            let param = &campaign_table["campaignMissions"][&mission_id]["param"];
+            let required = param[campaign["isSmallScale"].as_u64().unwrap_or(0) as usize]
+                .as_str()
+                .unwrap_or("0");
+            if state.as_u64().unwrap_or(0) != MISSION_COMPLETE && kills >= required.parse::<u64>().unwrap_or(0) {
+                user["campaignsV2"]["missions"][&mission_id] = json!(MISSION_COMPLETE);
+            }
+        }
+
+        let campaigns = &mut user["campaignsV2"];
+        campaigns["instances"][stage_id] = json!({
+            "maxKills": max_kills,
+            "rewardStatus": reward_status
+        });
+        campaigns["sweepMaxKills"][stage_id] = json!(max_kills);
+        refresh_campaign_fee(campaigns, &campaign_table, &const_table);
+    }
+
+    let (rewards, diamond_material_rewards, fee_before, fee_after) =
+        settle_gain_ladder(user, &ladder, &battle["squad"], &battle_data.deployed_chars(), &mut modified).await;
+
+    modified["campaignsV2"] = user["campaignsV2"].clone();
+    for field in ["ap", "maxAp", "lastApAddTime", "level", "exp"] {
+        modified["status"][field] = user["status"][field].clone();
+    }
+    record_battle(&battle, &battle_data);
+    write_json(USER_JSON_PATH, user_data);
+
     Json(json!({
         "result": 0,
+        "apFailReturn": ap_fail_return,
+        "expScale": 1,
+        "goldScale": 1,
+        "rewards": rewards,
+        "firstRewards": first_rewards,
+        "unlockStages": [],
+        "unusualRewards": [],
+        "additionalRewards": [],
+        "furnitureRewards": [],
+        "diamondMaterialRewards": diamond_material_rewards,
+        "currentFeeBefore": fee_before,
+        "currentFeeAfter": fee_after,
+        "alert": [],
+        "suggestFriend": false,
+        "pryResult": [],
         "playerDataDelta": {
-            "modified": {},
+            "modified": modified,
             "deleted": {}
         }
     }))
 }
 
-pub async fn campaignv2_battle_sweep() -> JSON {
+pub async fn campaignv2_battle_sweep(Json(payload): JSON) -> JSON {
+    let Some(stage_id) = payload["stageId"].as_str() else {
+        return error_result();
+    };
+
+    let stage_table = update_data(STAGE_TABLE_URL).await;
+    let campaign_table = update_data(CAMPAIGN_TABLE_URL).await;
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let ap_cost = stage_table["stages"][stage_id]["apCost"].as_u64().unwrap_or(0);
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let user = &mut user_data["user"];
+    let kills = user["campaignsV2"]["sweepMaxKills"][stage_id].as_u64().unwrap_or(0);
+    if kills == 0 {
+        return error_result();
+    }
+
+    let mut modified = json!({});
+    if let Some(item_id) = payload["itemId"].as_str() {
+        let sweep_cost = [json!({ "id": item_id, "count": 1 })];
+        if consume_items(user, &sweep_cost, &mut modified).await.is_err() {
+            return error_result();
+        }
+    }
+    refresh_ap(&mut user["status"], &const_table);
+    if !cost_ap(&mut user["status"], ap_cost) {
+        return error_result();
+    }
+    refresh_campaign_fee(&mut user["campaignsV2"], &campaign_table, &const_table);
+
+    let campaign = &campaign_table["campaigns"][stage_id];
+    let mode = campaign_mode(&user["campaignsV2"], stage_id);
+    let ladder = gain_ladder(campaign, mode, kills);
+    let (rewards, diamond_material_rewards, fee_before, fee_after) = settle_gain_ladder(user, &ladder, &Value::Null, &[], &mut modified).await;
+
+    modified["campaignsV2"] = user["campaignsV2"].clone();
+    for field in ["ap", "maxAp", "lastApAddTime", "level", "exp"] {
+        modified["status"][field] = user["status"][field].clone();
+    }
+    write_json(USER_JSON_PATH, user_data);
+
     Json(json!({
         "result": 0,
-        "apFailReturn": 1,
-        "rewards": [],
+        "apFailReturn": 0,
+        "rewards": rewards,
         "unlockStages": [],
         "unusualRewards": [],
         "additionalRewards": [],
         "furnitureRewards": [],
-        "diamondMaterialRewards": [
-            {
-                "type": "DIAMOND_SHD",
-                "id": "4003",
-                "count": 1
-            }
-        ],
-        "currentFeeBefore": 0,
-        "currentFeeAfter": 1,
+        "diamondMaterialRewards": diamond_material_rewards,
+        "currentFeeBefore": fee_before,
+        "currentFeeAfter": fee_after,
         "playerDataDelta": {
-            "modified": {},
+            "modified": modified,
             "deleted": {}
         }
     }))
@@ -47,12 +47,452 @@ pub mod char {
 
 pub mod char_build {
     use crate::{
-        constants::user::USER_JSON_PATH,
+        constants::{
+            url::{BATTLEEQUIP_TABLE_URL, CHARACTER_TABLE_URL, CHAR_PATCH_TABLE_URL, EQUIP_TABLE_URL, GAMEDATA_CONST_URL, ITEM_TABLE_URL},
+            user::USER_JSON_PATH,
+        },
         core::time,
-        utils::json::{get_keys, read_json, write_json, JSON},
+        game::{
+            equipment::{equip_missions_complete, meets_equip_cond},
+            medal::{award_medals, MedalContext},
+            mission::{track_missions, MissionEvent},
+            profile::tmpl_conds_met,
+        },
+        utils::{
+            game::{error_result, phase_index, rarity_index, update_data},
+            item::consume_items,
+            json::{get_keys, read_json, write_json, JSON},
+        },
     };
     use axum::Json;
-    use serde_json::json;
+    use serde_json::{json, Value};
+
+    fn get_char_inst_id(char_inst_id: &Value) -> String {
+        match char_inst_id.as_str() {
+            Some(char_inst_id) => char_inst_id.to_string(),
+            None => char_inst_id.as_u64().map(|id| id.to_string()).unwrap_or_default(),
+        }
+    }
+
+    fn meets_unlock_cond(char_data: &Value, unlock_cond: &Value) -> bool {
+        let phase = char_data["evolvePhase"].as_i64().unwrap_or(0);
+        let level = char_data["level"].as_i64().unwrap_or(1);
+        let cond_phase = phase_index(&unlock_cond["phase"]);
+        phase > cond_phase || (phase == cond_phase && level >= unlock_cond["level"].as_i64().unwrap_or(1))
+    }
+
+    /// The template whose skills are in use, or `None` for an operator without templates.
+    fn current_tmpl(char_data: &Value) -> Option<String> {
+        let current_tmpl = char_data["currentTmpl"].as_str()?;
+        char_data["tmpl"][current_tmpl].is_object().then(|| current_tmpl.to_string())
+    }
+
+    /// The skills of the operator's current template, or its own skills when it has none.
+    fn skills_mut(char_data: &mut Value) -> Option<&mut Vec<Value>> {
+        match current_tmpl(char_data) {
+            Some(tmpl_id) => char_data["tmpl"][&tmpl_id]["skills"].as_array_mut(),
+            None => char_data["skills"].as_array_mut(),
+        }
+    }
+
+    /// The table entry of an operator or template, which for some templates only `char_patch_table` has.
+    fn form_data<'a>(char_table: &'a Value, patch_table: &'a Value, form_id: &str) -> &'a Value {
+        if char_table[form_id].is_null() {
+            &patch_table["patchChars"][form_id]
+        } else {
+            &char_table[form_id]
+        }
+    }
+
+    /// The table entry the current skills come from: the current template's, or the operator's own.
+    fn skill_data<'a>(char_table: &'a Value, patch_table: &'a Value, char_data: &Value) -> &'a Value {
+        let form_id = current_tmpl(char_data).unwrap_or(char_data["charId"].as_str().unwrap_or("").to_string());
+        form_data(char_table, patch_table, &form_id)
+    }
+
+    /// Unlocks the skills the operator's elite and level now reach, in every template it has.
+    fn unlock_skills(char_data: &mut Value, char_table: &Value, patch_table: &Value) {
+        let tmpl_ids = get_keys(&char_data["tmpl"]);
+        let forms = if tmpl_ids.is_empty() {
+            vec![None]
+        } else {
+            tmpl_ids.into_iter().map(Some).collect()
+        };
+        for tmpl_id in forms {
+            let form_id = tmpl_id.clone().unwrap_or(char_data["charId"].as_str().unwrap_or("").to_string());
+            let unlocked: Vec<usize> = form_data(char_table, patch_table, &form_id)["skills"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .filter(|(_, skill)| meets_unlock_cond(char_data, &skill["unlockCond"]))
+                .map(|(index, _)| index)
+                .collect();
+            let skills = match &tmpl_id {
+                Some(tmpl_id) => char_data["tmpl"][tmpl_id]["skills"].as_array_mut(),
+                None => char_data["skills"].as_array_mut(),
+            };
+            let Some(skills) = skills else {
+                continue;
+            };
+            for index in unlocked {
+                if let Some(skill) = skills.get_mut(index) {
+                    skill["unlock"] = json!(1);
+                }
+            }
+        }
+    }
+
+    fn char_delta(char_inst_id: &str, char_data: &Value, mut modified: Value) -> JSON {
+        modified["troop"]["chars"][char_inst_id] = char_data.clone();
+        Json(json!({
+            "result": 0,
+            "playerDataDelta": {
+                "modified": modified,
+                "deleted": {}
+            }
+        }))
+    }
+
+    pub async fn char_build_upgrade_char(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let char_table = update_data(CHARACTER_TABLE_URL).await;
+        let patch_table = update_data(CHAR_PATCH_TABLE_URL).await;
+        let const_table = update_data(GAMEDATA_CONST_URL).await;
+        let item_table = update_data(ITEM_TABLE_URL).await;
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+        let char_info = &char_table[char_data["charId"].as_str().unwrap()];
+
+        let mut costs = Vec::new();
+        let mut gain_exp = 0;
+        for exp_mat in payload["expMats"].as_array().unwrap_or(&Vec::new()) {
+            let item_id = exp_mat["id"].as_str().unwrap_or("");
+            let count = exp_mat["count"].as_u64().unwrap_or(0);
+            let Some(item_exp) = item_table["expItems"][item_id]["gainExp"].as_u64() else {
+                return error_result();
+            };
+            gain_exp += item_exp * count;
+            costs.push(json!({ "id": item_id, "count": count, "type": "CARD_EXP" }));
+        }
+
+        let phase = char_data["evolvePhase"].as_u64().unwrap() as usize;
+        let max_level = char_info["phases"][phase]["maxLevel"].as_u64().unwrap();
+        let exp_map = &const_table["characterExpMap"][phase];
+        let gold_map = &const_table["characterUpgradeCostMap"][phase];
+        let mut level = char_data["level"].as_u64().unwrap();
+        let mut exp = char_data["exp"].as_u64().unwrap_or(0);
+        // LMD is charged for the exp gained here only, in proportion to each level's cost.
+        let mut gold = 0;
+        while level < max_level && gain_exp > 0 {
+            let required = exp_map[level as usize - 1].as_u64().unwrap();
+            let level_gold = gold_map[level as usize - 1].as_u64().unwrap();
+            let step = gain_exp.min(required.saturating_sub(exp));
+            gold += level_gold * step / required;
+            gain_exp -= step;
+            exp += step;
+            if exp < required {
+                break;
+            }
+            exp = 0;
+            level += 1;
+        }
+        if level >= max_level {
+            exp = 0;
+        }
+        costs.push(json!({ "id": "4001", "count": gold, "type": "GOLD" }));
+
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+        char_data["level"] = json!(level);
+        char_data["exp"] = json!(exp);
+        unlock_skills(&mut char_data, &char_table, &patch_table);
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        track_missions(&mut user_data["user"], &[MissionEvent::Count("UpgradeChar", 1)], &mut modified).await;
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
+
+    pub async fn char_build_evolve_char(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let dest_evolve_phase = payload["destEvolvePhase"].as_u64().unwrap() as usize;
+        let char_table = update_data(CHARACTER_TABLE_URL).await;
+        let patch_table = update_data(CHAR_PATCH_TABLE_URL).await;
+        let const_table = update_data(GAMEDATA_CONST_URL).await;
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+        let char_id = char_data["charId"].as_str().unwrap().to_string();
+        let char_info = &char_table[&char_id];
+
+        let phase = char_data["evolvePhase"].as_u64().unwrap() as usize;
+        let phases = char_info["phases"].as_array().unwrap();
+        if dest_evolve_phase != phase + 1
+            || dest_evolve_phase >= phases.len()
+            || char_data["level"].as_u64().unwrap() < phases[phase]["maxLevel"].as_u64().unwrap()
+        {
+            return error_result();
+        }
+
+        let mut costs = phases[dest_evolve_phase]["evolveCost"].as_array().cloned().unwrap_or_default();
+        let gold = const_table["evolveGoldCost"][rarity_index(&char_info["rarity"])][dest_evolve_phase - 1]
+            .as_i64()
+            .unwrap_or(0);
+        if gold > 0 {
+            costs.push(json!({ "id": "4001", "count": gold, "type": "GOLD" }));
+        }
+
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+        char_data["evolvePhase"] = json!(dest_evolve_phase);
+        char_data["level"] = json!(1);
+        char_data["exp"] = json!(0);
+        if dest_evolve_phase == 2 && char_data["skin"] == json!(format!("{char_id}#1")) {
+            char_data["skin"] = json!(format!("{char_id}#2"));
+        }
+        unlock_skills(&mut char_data, &char_table, &patch_table);
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        award_medals(&mut user_data["user"], &MedalContext::default(), &mut modified).await;
+        track_missions(&mut user_data["user"], &[], &mut modified).await;
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
+
+    pub async fn char_build_boost_potential(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let item_id = payload["itemId"].as_str().unwrap();
+        let target_rank = payload["targetRank"].as_u64().unwrap();
+        let char_table = update_data(CHARACTER_TABLE_URL).await;
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+        let char_info = &char_table[char_data["charId"].as_str().unwrap()];
+
+        let max_rank = char_info["maxPotentialLevel"].as_u64().unwrap_or(5);
+        if item_id != char_info["potentialItemId"].as_str().unwrap_or("")
+            || target_rank != char_data["potentialRank"].as_u64().unwrap() + 1
+            || target_rank > max_rank
+        {
+            return error_result();
+        }
+
+        let costs = [json!({ "id": item_id, "count": 1, "type": "MATERIAL" })];
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+        char_data["potentialRank"] = json!(target_rank);
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        award_medals(&mut user_data["user"], &MedalContext::default(), &mut modified).await;
+        track_missions(&mut user_data["user"], &[], &mut modified).await;
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
+
+    pub async fn char_build_upgrade_skill(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let target_level = payload["targetLevel"].as_u64().unwrap();
+        let char_table = update_data(CHARACTER_TABLE_URL).await;
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+        let char_info = &char_table[char_data["charId"].as_str().unwrap()];
+
+        let skill_lvlup = &char_info["allSkillLvlup"][target_level.saturating_sub(2) as usize];
+        if target_level != char_data["mainSkillLvl"].as_u64().unwrap() + 1
+            || skill_lvlup.is_null()
+            || !meets_unlock_cond(&char_data, &skill_lvlup["unlockCond"])
+        {
+            return error_result();
+        }
+
+        let costs = skill_lvlup["lvlUpCost"].as_array().cloned().unwrap_or_default();
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+        char_data["mainSkillLvl"] = json!(target_level);
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        award_medals(&mut user_data["user"], &MedalContext::default(), &mut modified).await;
+        track_missions(&mut user_data["user"], &[MissionEvent::Count("UpgradeSkill", 1)], &mut modified).await;
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
+
+    pub async fn char_build_upgrade_specialized_skill(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let skill_index = payload["skillIndex"].as_u64().unwrap() as usize;
+        let target_level = payload["targetLevel"].as_u64().unwrap();
+        let char_table = update_data(CHARACTER_TABLE_URL).await;
+        let patch_table = update_data(CHAR_PATCH_TABLE_URL).await;
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+        let skill_info = &skill_data(&char_table, &patch_table, &char_data)["skills"][skill_index];
+
+        let Some(skill) = skills_mut(&mut char_data).and_then(|skills| skills.get(skill_index)).cloned() else {
+            return error_result();
+        };
+        let cost_cond = &skill_info["levelUpCostCond"][target_level.saturating_sub(1) as usize];
+        if char_data["mainSkillLvl"].as_u64().unwrap() < 7
+            || target_level != skill["specializeLevel"].as_u64().unwrap_or(0) + 1
+            || skill["completeUpgradeTime"].as_i64().unwrap_or(-1) != -1
+            || cost_cond.is_null()
+            || !meets_unlock_cond(&char_data, &cost_cond["unlockCond"])
+        {
+            return error_result();
+        }
+
+        let costs = cost_cond["levelUpCost"].as_array().cloned().unwrap_or_default();
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+        let lvl_up_time = cost_cond["lvlUpTime"].as_u64().unwrap_or(0);
+        let Some(skill) = skills_mut(&mut char_data).and_then(|skills| skills.get_mut(skill_index)) else {
+            return error_result();
+        };
+        skill["completeUpgradeTime"] = json!(time() + lvl_up_time);
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        track_missions(
+            &mut user_data["user"],
+            &[MissionEvent::Count("UpgradeSpecialization", 1)],
+            &mut modified,
+        )
+        .await;
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
+
+    pub async fn char_build_complete_upgrade_specialized(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let skill_index = payload["skillIndex"].as_u64().unwrap() as usize;
+        let target_level = payload["targetLevel"].as_u64().unwrap();
+
+        let mut modified = json!({});
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+
+        let Some(skill) = skills_mut(&mut char_data).and_then(|skills| skills.get_mut(skill_index)) else {
+            return error_result();
+        };
+        let complete_upgrade_time = skill["completeUpgradeTime"].as_i64().unwrap_or(-1);
+        if complete_upgrade_time == -1
+            || (time() as i64) < complete_upgrade_time
+            || target_level != skill["specializeLevel"].as_u64().unwrap_or(0) + 1
+        {
+            return error_result();
+        }
+        skill["specializeLevel"] = json!(target_level);
+        skill["completeUpgradeTime"] = json!(-1);
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        award_medals(&mut user_data["user"], &MedalContext::default(), &mut modified).await;
+        track_missions(&mut user_data["user"], &[], &mut modified).await;
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
+
+    pub async fn char_build_unlock_equipment(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let equip_id = payload["equipId"].as_str().unwrap();
+        let equip_table = update_data(EQUIP_TABLE_URL).await;
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+        let equip = &equip_table["equipDict"][equip_id];
+
+        if equip["charId"] != char_data["charId"]
+            || !char_data["equip"][equip_id].is_null()
+            || !meets_equip_cond(&char_data, equip)
+            || !equip_missions_complete(&user_data["user"], equip)
+        {
+            return error_result();
+        }
+
+        let costs = equip["itemCost"]["1"].as_array().cloned().unwrap_or_default();
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+        char_data["equip"][equip_id] = json!({
+            "hide": 0,
+            "locked": 0,
+            "level": 1
+        });
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
+
+    pub async fn char_build_upgrade_equipment(Json(payload): JSON) -> JSON {
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let equip_id = payload["equipId"].as_str().unwrap();
+        let target_level = payload["targetLevel"].as_u64().unwrap();
+        let equip_table = update_data(EQUIP_TABLE_URL).await;
+        let battleequip_table = update_data(BATTLEEQUIP_TABLE_URL).await;
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let mut char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
+        if char_data.is_null() {
+            return error_result();
+        }
+
+        let max_level = battleequip_table[equip_id]["phases"]
+            .as_array()
+            .map(|phases| phases.len())
+            .unwrap_or(1) as u64;
+        match char_data["equip"][equip_id]["level"].as_u64() {
+            Some(level) if target_level == level + 1 && target_level <= max_level => (),
+            _ => return error_result(),
+        }
+
+        let costs = equip_table["equipDict"][equip_id]["itemCost"][target_level.to_string()]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+        char_data["equip"][equip_id]["level"] = json!(target_level);
+
+        user_data["user"]["troop"]["chars"][&char_inst_id] = char_data.clone();
+        write_json(USER_JSON_PATH, user_data);
+        char_delta(&char_inst_id, &char_data, modified)
+    }
 
     pub async fn char_build_batch_set_char_voice_lan() -> JSON {
         Json(json!({
@@ -191,69 +631,101 @@ pub mod char_build {
         Json(data)
     }
 
+    /// Equips a module the operator (or its current template) has unlocked, or unequips with a `null` `equipId`.
     pub async fn char_build_set_char_equipment(Json(payload): JSON) -> JSON {
-        let char_inst_id = payload["charInstId"].clone();
-        let char_inst_id = if char_inst_id.is_string() {
-            char_inst_id.as_str().unwrap().to_string()
-        } else {
-            char_inst_id.as_u64().unwrap().to_string()
-        };
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
         let equip_id = payload["equipId"].clone();
 
-        let mut data = json!({
-            "playerDataDelta":{
-                "modified":{
-                    "troop":{
-                        "chars":{}
-                    }
-                },
-                "deleted":{}
-            }
-        });
-
         let mut user_data = read_json(USER_JSON_PATH);
-
-        data["playerDataDelta"]["modified"]["troop"]["chars"] = json!({
-            &char_inst_id: {
-                "currentEquip": equip_id
+        let char_data = &mut user_data["user"]["troop"]["chars"][&char_inst_id];
+        if char_data.is_null() {
+            return error_result();
+        }
+        let current_tmpl = char_data["currentTmpl"].as_str().map(str::to_string);
+        let owner = match current_tmpl {
+            Some(current_tmpl) if char_data["tmpl"][&current_tmpl].is_object() => &mut char_data["tmpl"][&current_tmpl],
+            _ => char_data,
+        };
+        if let Some(equip_id) = equip_id.as_str() {
+            if owner["equip"][equip_id].is_null() {
+                return error_result();
             }
-        });
-
-        user_data["user"]["troop"]["chars"][char_inst_id]["currentEquip"] = equip_id;
+        }
+        owner["currentEquip"] = equip_id.clone();
+        let char_data = user_data["user"]["troop"]["chars"][&char_inst_id].clone();
         write_json(USER_JSON_PATH, user_data);
 
-        Json(data)
+        char_delta(&char_inst_id, &char_data, json!({}))
+    }
+
+    /// A template can be switched to once the operator has it and the stages in its `char_patch_table` unlock conditions are cleared.
+    fn template_unlocked(patch_table: &Value, user: &Value, char_data: &Value, template_id: &str) -> bool {
+        let char_id = char_data["charId"].as_str().unwrap_or("");
+        let known = patch_table["infos"][char_id]["tmplIds"]
+            .as_array()
+            .is_some_and(|tmpl_ids| tmpl_ids.iter().any(|tmpl_id| tmpl_id == template_id));
+        known && !char_data["tmpl"][template_id].is_null() && tmpl_conds_met(patch_table, &user["dungeon"]["stages"], template_id)
     }
 
     pub async fn char_build_change_char_template(Json(payload): JSON) -> JSON {
-        let char_inst_id = payload["charInstId"].clone();
-        let char_inst_id = if char_inst_id.is_string() {
-            char_inst_id.as_str().unwrap().to_string()
-        } else {
-            char_inst_id.as_u64().unwrap().to_string()
-        };
-        let template_id = payload["templateId"].clone();
+        let char_inst_id = get_char_inst_id(&payload["charInstId"]);
+        let template_id = payload["templateId"].as_str().unwrap_or("");
+        let patch_table = update_data(CHAR_PATCH_TABLE_URL).await;
 
-        let data = json!({
-            "playerDataDelta":{
-                "modified":{
-                    "troop":{
-                        "chars":{
+        let mut user_data = read_json(USER_JSON_PATH);
+        let char_data = &user_data["user"]["troop"]["chars"][&char_inst_id];
+        if !template_unlocked(&patch_table, &user_data["user"], char_data, template_id) {
+            return error_result();
+        }
+        user_data["user"]["troop"]["chars"][&char_inst_id]["currentTmpl"] = json!(template_id);
+        write_json(USER_JSON_PATH, user_data);
+
+        Json(json!({
+            "playerDataDelta": {
+                "modified": {
+                    "troop": {
+                        "chars": {
                             &char_inst_id: {
                                 "currentTmpl": template_id
                             }
                         }
                     }
                 },
-                "deleted":{}
+                "deleted": {}
             }
-        });
-
-        let mut user_data = read_json(USER_JSON_PATH);
-        user_data["user"]["troop"]["chars"][char_inst_id]["currentTmpl"] = template_id;
+        }))
+    }
 
-        write_json(USER_JSON_PATH, user_data);
-        Json(data)
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn unlocks_skills_of_every_template() {
+            let skills = json!([
+                { "skillId": "skchr_test_1", "unlockCond": { "phase": "PHASE_0", "level": 1 } },
+                { "skillId": "skchr_test_2", "unlockCond": { "phase": "PHASE_1", "level": 1 } }
+            ]);
+            let char_table = json!({ "char_test": { "skills": [] }, "char_test_a": { "skills": skills } });
+            let patch_table = json!({ "patchChars": { "char_test_b": { "skills": skills } } });
+            let mut char_data = json!({
+                "charId": "char_test",
+                "evolvePhase": 1,
+                "level": 1,
+                "skills": [],
+                "currentTmpl": "char_test_b",
+                "tmpl": {
+                    "char_test_a": { "skills": [{ "unlock": 1 }, { "unlock": 0 }] },
+                    "char_test_b": { "skills": [{ "unlock": 1 }, { "unlock": 0 }] }
+                }
+            });
+            unlock_skills(&mut char_data, &char_table, &patch_table);
+            assert_eq!(char_data["tmpl"]["char_test_a"]["skills"][1]["unlock"], 1);
+            assert_eq!(char_data["tmpl"]["char_test_b"]["skills"][1]["unlock"], 1);
+            assert_eq!(char_data["skills"], json!([]));
+            assert_eq!(skills_mut(&mut char_data).map(|skills| skills.len()), Some(2));
+            assert_eq!(skill_data(&char_table, &patch_table, &char_data)["skills"], skills);
+        }
     }
 }
 
@@ -0,0 +1,229 @@
+use axum::Json;
+use chrono::{DateTime, Datelike};
+use serde_json::{json, Value};
+
+use crate::{
+    constants::{url::ACTIVITY_TABLE_URL, user::USER_JSON_PATH},
+    core::time,
+    utils::{
+        game::{day_start, error_result, reset_offset, update_data},
+        item::grant_items,
+        json::{get_values, read_json, write_json, JSON},
+    },
+};
+
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+/// Activity types whose events hand out one reward from their `checkInList` per day the player logs in.
+const EVENT_CHECK_IN_TYPES: [&str; 2] = ["CHECKIN_ONLY", "CHECKIN_ALL_PLAYER"];
+
+/// The reset starting the calendar month `ts` falls in.
+fn month_start(ts: u64) -> u64 {
+    let today = day_start(ts);
+    let date = DateTime::from_timestamp((today + reset_offset()) as i64, 0).unwrap();
+    today - (date.day() as u64 - 1) * DAY_SECONDS
+}
+
+/// The sign-in group running at `now` as `(group id, first day, rewards)`.
+fn sign_in_period(checkin_table: &Value, now: u64) -> (String, u64, Vec<Value>) {
+    let groups = get_values(&checkin_table["groups"]);
+    let start = |group: &Value| group["signStartTime"].as_u64().unwrap_or(0);
+    let current = groups
+        .iter()
+        .find(|group| start(group) <= now && now <= group["signEndTime"].as_u64().unwrap_or(0));
+    // Past the last group in the table, its rewards keep cycling on calendar months.
+    let (group, first_day) = match current {
+        Some(group) => (group, start(group)),
+        None => (groups.iter().max_by_key(|group| start(group)).unwrap(), month_start(now)),
+    };
+    let items = group["items"].as_array().cloned().unwrap_or_default();
+    (group["groupId"].as_str().unwrap().to_string(), first_day, items)
+}
+
+/// Moves the monthly sign-in to today, starting over on a new group or month, and returns whether anything changed.
+fn refresh_sign_in(user: &mut Value, checkin_table: &Value, now: u64) -> bool {
+    let (group_id, first_day, items) = sign_in_period(checkin_table, now);
+    let today = ((day_start(now).max(first_day) - first_day) / DAY_SECONDS) as usize;
+    let previous = user["checkIn"].clone();
+    let check_in = &mut user["checkIn"];
+
+    // The fallback group repeats every month, so the period is told apart by its first day as well as its group.
+    let mut history = check_in["checkInHistory"].as_array().cloned().unwrap_or_default();
+    let new_period = match check_in["checkInStartTs"].as_u64() {
+        Some(start_ts) => start_ts != first_day,
+        None => history.len() > today + 1,
+    };
+    if check_in["checkInGroupId"] != group_id.as_str() || new_period {
+        check_in["checkInGroupId"] = json!(group_id);
+        check_in["checkInRewardIndex"] = json!(0);
+        history.clear();
+    }
+    check_in["checkInStartTs"] = json!(first_day);
+    history.resize(today + 1, json!(0));
+    let reward_index = check_in["checkInRewardIndex"].as_u64().unwrap_or(0) as usize;
+    let can_check_in = history[today] == 0 && reward_index < items.len();
+    check_in["checkInHistory"] = json!(history);
+    check_in["canCheckIn"] = json!(can_check_in as u8);
+    *check_in != previous
+}
+
+/// Marks today as checked in for every running check-in event that still has rewards left.
+fn event_check_ins(user: &mut Value, activity_table: &Value, now: u64) -> bool {
+    let mut changed = false;
+    for (activity_id, info) in activity_table["basicInfo"].as_object().into_iter().flatten() {
+        let activity_type = info["type"].as_str().unwrap_or("");
+        let running = info["startTime"].as_u64().unwrap_or(u64::MAX) <= now && now <= info["endTime"].as_u64().unwrap_or(0);
+        if !EVENT_CHECK_IN_TYPES.contains(&activity_type) || !running {
+            continue;
+        }
+        let days = activity_table["activity"][activity_type][activity_id]["checkInList"]
+            .as_object()
+            .map_or(0, |list| list.len());
+        let state = &mut user["activity"][activity_type][activity_id];
+        let mut history = state["history"].as_array().cloned().unwrap_or_default();
+        if state["lastTs"].as_u64().unwrap_or(0) >= day_start(now) || history.len() >= days {
+            continue;
+        }
+        history.push(json!(1));
+        state["history"] = json!(history);
+        state["lastTs"] = json!(now);
+        changed = true;
+    }
+    changed
+}
+
+/// Adds the state of every check-in event type to `modified`.
+pub fn event_check_in_delta(user: &Value, modified: &mut Value) {
+    for activity_type in EVENT_CHECK_IN_TYPES {
+        if user["activity"][activity_type].is_object() {
+            modified["activity"][activity_type] = user["activity"][activity_type].clone();
+        }
+    }
+}
+
+/// Brings the monthly sign-in and event check-ins up to today, returning whether anything changed.
+pub fn refresh_check_in(user: &mut Value, checkin_table: &Value, activity_table: &Value) -> bool {
+    let now = time();
+    let signed = refresh_sign_in(user, checkin_table, now);
+    let events = event_check_ins(user, activity_table, now);
+    signed || events
+}
+
+/// Claims today's monthly sign-in reward, or returns `None` if it was already claimed.
+pub async fn sign_in(user: &mut Value, checkin_table: &Value, modified: &mut Value) -> Option<Vec<Value>> {
+    if user["checkIn"]["canCheckIn"] != 1 {
+        return None;
+    }
+    let (_, _, items) = sign_in_period(checkin_table, time());
+    let check_in = &mut user["checkIn"];
+    let reward_index = check_in["checkInRewardIndex"].as_u64().unwrap_or(0);
+    let item = &items[reward_index as usize];
+    if let Some(today) = check_in["checkInHistory"].as_array_mut().and_then(|history| history.last_mut()) {
+        *today = json!(1);
+    }
+    check_in["checkInRewardIndex"] = json!(reward_index + 1);
+    check_in["canCheckIn"] = json!(0);
+
+    let reward = json!({
+        "id": item["itemId"],
+        "type": item["itemType"],
+        "count": item["count"]
+    });
+    Some(grant_items(user, &[reward], modified).await)
+}
+
+/// Claims one day's reward from a check-in event.
+pub async fn activity_get_activity_check_in_reward(Json(payload): JSON) -> JSON {
+    let activity_id = payload["activityId"].as_str().unwrap_or("");
+    let index = payload["index"].as_u64().unwrap_or(0) as usize;
+    let activity_table = update_data(ACTIVITY_TABLE_URL).await;
+    let activity_type = activity_table["basicInfo"][activity_id]["type"].as_str().unwrap_or("");
+    let reward = &activity_table["activity"][activity_type][activity_id]["checkInList"][index.to_string()];
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let state = &mut user_data["user"]["activity"][activity_type][activity_id];
+    let Some(day) = state["history"].as_array_mut().and_then(|history| history.get_mut(index)) else {
+        return error_result();
+    };
+    if !EVENT_CHECK_IN_TYPES.contains(&activity_type) || reward.is_null() || *day != 1 {
+        return error_result();
+    }
+    *day = json!(0);
+
+    let mut modified = json!({
+        "activity": {
+            activity_type: {
+                activity_id: state
+            }
+        }
+    });
+    let item_list = reward["itemList"].as_array().cloned().unwrap_or_default();
+    let items = grant_items(&mut user_data["user"], &item_list, &mut modified).await;
+    write_json(USER_JSON_PATH, user_data);
+    Json(json!({
+        "items": items,
+        "playerDataDelta": {
+            "modified": modified,
+            "deleted": {}
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    /// Noon on a server calendar date.
+    fn noon(month: u32, day: u32) -> u64 {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, month, day, 12, 0, 0)
+            .unwrap()
+            .timestamp() as u64
+    }
+
+    fn checkin_table() -> Value {
+        let items: Vec<Value> = (0..31)
+            .map(|_| json!({ "itemId": "4001", "itemType": "GOLD", "count": 1 }))
+            .collect();
+        json!({
+            "groups": {
+                "signin1": {
+                    "groupId": "signin1",
+                    "signStartTime": 0,
+                    "signEndTime": 1,
+                    "items": items
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn fallback_group_starts_over_every_month() {
+        let table = checkin_table();
+        let mut user = json!({ "checkIn": {} });
+        refresh_sign_in(&mut user, &table, noon(9, 1));
+        user["checkIn"]["checkInHistory"] = json!([1]);
+        user["checkIn"]["checkInRewardIndex"] = json!(1);
+
+        assert!(refresh_sign_in(&mut user, &table, noon(10, 1)));
+        assert_eq!(user["checkIn"]["checkInHistory"], json!([0]));
+        assert_eq!(user["checkIn"]["checkInRewardIndex"], 0);
+        assert_eq!(user["checkIn"]["canCheckIn"], 1);
+    }
+
+    #[test]
+    fn same_month_keeps_history() {
+        let table = checkin_table();
+        let mut user = json!({ "checkIn": {} });
+        refresh_sign_in(&mut user, &table, noon(10, 1));
+        user["checkIn"]["checkInHistory"] = json!([1]);
+        user["checkIn"]["checkInRewardIndex"] = json!(1);
+
+        refresh_sign_in(&mut user, &table, noon(10, 3));
+        assert_eq!(user["checkIn"]["checkInHistory"], json!([1, 0, 0]));
+        assert_eq!(user["checkIn"]["checkInRewardIndex"], 1);
+    }
+}
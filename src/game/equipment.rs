@@ -0,0 +1,105 @@
+use axum::Json;
+use serde_json::{json, Value};
+
+use crate::{
+    constants::{url::EQUIP_TABLE_URL, user::USER_JSON_PATH},
+    utils::{
+        game::{error_result, phase_index, update_data},
+        json::{get_keys, read_json, write_json, JSON},
+    },
+};
+
+/// Whether an operator's elite phase, level and trust are high enough for a module.
+pub fn meets_equip_cond(char_data: &Value, equip: &Value) -> bool {
+    let phase = char_data["evolvePhase"].as_i64().unwrap_or(0);
+    let level = char_data["level"].as_i64().unwrap_or(1);
+    let cond_phase = phase_index(&equip["unlockEvolvePhase"]);
+    let phase_met = phase > cond_phase || (phase == cond_phase && level >= equip["unlockLevel"].as_i64().unwrap_or(0));
+    phase_met && char_data["favorPoint"].as_u64().unwrap_or(0) >= equip["unlockFavorPoint"].as_u64().unwrap_or(0)
+}
+
+/// Every module of an operator (or template) that `char_data` has unlocked, at its highest level, with the last one equipped.
+pub fn unlocked_equips(equip_table: &Value, battleequip_table: &Value, char_id: &str, char_data: &Value) -> (Value, Value) {
+    let mut equips = json!({});
+    let mut current_equip = Value::Null;
+    for equip_id in equip_table["charEquip"][char_id].as_array().unwrap_or(&Vec::new()) {
+        let equip_id = equip_id.as_str().unwrap();
+        if !meets_equip_cond(char_data, &equip_table["equipDict"][equip_id]) {
+            continue;
+        }
+        let level = battleequip_table[equip_id]["phases"].as_array().map(Vec::len).unwrap_or(1);
+        equips[equip_id] = json!({
+            "hide": 0,
+            "locked": 0,
+            "level": level
+        });
+        current_equip = json!(equip_id);
+    }
+    (equips, current_equip)
+}
+
+fn completed_mission() -> Value {
+    json!({
+        "value": 1,
+        "target": 1
+    })
+}
+
+/// Module missions for `equipment.missions`, all completed so every module can be unlocked.
+pub fn completed_equip_missions(equip_table: &Value) -> Value {
+    let mut missions = json!({});
+    for mission_id in get_keys(&equip_table["missionList"]) {
+        missions[&mission_id] = completed_mission();
+    }
+    missions
+}
+
+/// Marks the missions of every module an operator has, its templates' included, as completed.
+pub fn complete_owned_equip_missions(missions: &mut Value, equip_table: &Value, char: &Value) {
+    let tmpls = char["tmpl"].as_object().into_iter().flat_map(|tmpls| tmpls.values());
+    for owner in std::iter::once(char).chain(tmpls) {
+        for equip_id in get_keys(&owner["equip"]) {
+            for mission_id in equip_table["equipDict"][&equip_id]["missionList"].as_array().into_iter().flatten() {
+                missions[mission_id.as_str().unwrap()] = completed_mission();
+            }
+        }
+    }
+}
+
+/// Whether every mission a module asks for has been completed.
+pub fn equip_missions_complete(user: &Value, equip: &Value) -> bool {
+    equip["missionList"].as_array().unwrap_or(&Vec::new()).iter().all(|mission_id| {
+        let mission = &user["equipment"]["missions"][mission_id.as_str().unwrap()];
+        mission["value"].as_u64().unwrap_or(0) >= mission["target"].as_u64().unwrap_or(1)
+    })
+}
+
+/// Marks a module's missions as completed, for accounts that didn't start with them done.
+pub async fn equipment_complete_missions(Json(payload): JSON) -> JSON {
+    let equip_table = update_data(EQUIP_TABLE_URL).await;
+    let equip_id = payload["equipId"].as_str().unwrap_or("");
+    let Some(mission_ids) = equip_table["equipDict"][equip_id]["missionList"].as_array() else {
+        return error_result();
+    };
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let mut missions = json!({});
+    for mission_id in mission_ids {
+        let mission_id = mission_id.as_str().unwrap();
+        missions[mission_id] = completed_mission();
+        user_data["user"]["equipment"]["missions"][mission_id] = completed_mission();
+    }
+    write_json(USER_JSON_PATH, user_data);
+
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": {
+                "equipment": {
+                    "missions": missions
+                }
+            },
+            "deleted": {}
+        }
+    }))
+}
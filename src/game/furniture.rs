@@ -0,0 +1,243 @@
+use axum::Json;
+use serde_json::{json, Value};
+
+use crate::{
+    constants::{
+        config::{CONFIG_JSON_PATH, DIY_PRESETS_PATH},
+        url::BUILDING_TABLE_URL,
+        user::BUILDING_JSON_PATH,
+    },
+    utils::{
+        game::{error_result, update_data},
+        json::{get_keys, read_json, write_json, JSON},
+    },
+};
+
+/// Every piece of furniture a DIY solution places, once per placement.
+fn placed_furniture(solution: &Value) -> Vec<String> {
+    let mut placed = Vec::new();
+    for field in ["wallPaper", "floor"] {
+        if let Some(furniture_id) = solution[field].as_str() {
+            placed.push(furniture_id.to_string());
+        }
+    }
+    for field in ["carpet", "other"] {
+        for furniture in solution[field].as_array().unwrap_or(&Vec::new()) {
+            placed.push(furniture["id"].as_str().unwrap_or("").to_string());
+        }
+    }
+    placed
+}
+
+/// How many of each furniture the dormitories use, leaving out `except_slot_id`.
+fn furniture_in_use(building: &Value, except_slot_id: Option<&str>) -> Value {
+    let mut in_use = json!({});
+    for slot_id in get_keys(&building["rooms"]["DORMITORY"]) {
+        if except_slot_id == Some(slot_id.as_str()) {
+            continue;
+        }
+        for furniture_id in placed_furniture(&building["rooms"]["DORMITORY"][&slot_id]["diySolution"]) {
+            in_use[&furniture_id] = json!(in_use[&furniture_id].as_u64().unwrap_or(0) + 1);
+        }
+    }
+    in_use
+}
+
+/// A dormitory's ambience: the comfort of each placed piece plus the bonus of every completed set, capped by the room level.
+fn comfort(building_table: &Value, solution: &Value, level: u64) -> u64 {
+    let custom_data = &building_table["customData"];
+    let placed = placed_furniture(solution);
+    let mut comfort: u64 = placed
+        .iter()
+        .map(|furniture_id| custom_data["furnitures"][furniture_id]["comfort"].as_u64().unwrap_or(0))
+        .sum();
+    for group in custom_data["groups"].as_object().unwrap().values() {
+        let complete = group["furniture"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|furniture_id| placed.iter().any(|placed_id| furniture_id == placed_id.as_str()));
+        if complete {
+            comfort += group["comfort"].as_u64().unwrap_or(0);
+        }
+    }
+    let phase = level.max(1) as usize - 1;
+    comfort.min(building_table["dormData"]["phases"][phase]["decorationLimit"].as_u64().unwrap_or(0))
+}
+
+/// Recomputes every furniture's `inUse` and every dormitory's comfort from the placed DIY solutions.
+pub fn refresh_furniture(building: &mut Value, building_table: &Value) {
+    let in_use = furniture_in_use(building, None);
+    for furniture_id in get_keys(&building["furniture"]) {
+        building["furniture"][&furniture_id]["inUse"] = json!(in_use[&furniture_id].as_u64().unwrap_or(0));
+    }
+    for slot_id in get_keys(&building["rooms"]["DORMITORY"]) {
+        let level = building["roomSlots"][&slot_id]["level"].as_u64().unwrap_or(1);
+        let comfort = comfort(building_table, &building["rooms"]["DORMITORY"][&slot_id]["diySolution"], level);
+        building["rooms"]["DORMITORY"][&slot_id]["comfort"] = json!(comfort);
+    }
+}
+
+/// Adds the furniture granted as items since the base was last opened, returning whether there was any.
+pub fn take_furniture_grants(building: &mut Value, user: &mut Value) -> bool {
+    let Some(grants) = user.as_object_mut().and_then(|user| user.remove("furnitureGrants")) else {
+        return false;
+    };
+    for (furniture_id, count) in grants.as_object().into_iter().flatten() {
+        let furniture = &mut building["furniture"][furniture_id];
+        furniture["count"] = json!(furniture["count"].as_u64().unwrap_or(0) + count.as_u64().unwrap_or(0));
+        furniture["inUse"] = json!(furniture["inUse"].as_u64().unwrap_or(0));
+    }
+    true
+}
+
+/// Sets up furniture ownership on sync. With `userConfig.grantAllFurniture` every furniture is owned in bulk,
+/// otherwise owned counts are kept, and a base without any furniture, or one leaving bulk ownership, starts over
+/// with a default set per dormitory. The mode the counts were built in is kept as `grantAllFurniture`.
+pub fn sync_furniture(building: &mut Value, building_table: &Value) {
+    let config = read_json(CONFIG_JSON_PATH);
+    let grant_all = config["userConfig"]["grantAllFurniture"].as_bool().unwrap_or(false);
+    let was_grant_all = building["grantAllFurniture"].as_bool().unwrap_or(false);
+    building["grantAllFurniture"] = json!(grant_all);
+    if grant_all {
+        let mut furniture = json!({});
+        for furniture_id in get_keys(&building_table["customData"]["furnitures"]) {
+            furniture[&furniture_id] = json!({
+                "count": 9999,
+                "inUse": 0
+            })
+        }
+        building["furniture"] = furniture;
+    } else if was_grant_all || get_keys(&building["furniture"]).is_empty() {
+        let dormitories = get_keys(&building["rooms"]["DORMITORY"]).len() as u64;
+        building["furniture"] = json!({});
+        for default_furniture in building_table["customData"]["defaultFurnitures"].as_object().unwrap().values() {
+            for furniture in default_furniture.as_array().unwrap() {
+                let furniture_id = furniture["furnitureId"].as_str().unwrap();
+                let count = building["furniture"][furniture_id]["count"].as_u64().unwrap_or(0);
+                building["furniture"][furniture_id] = json!({
+                    "count": count + dormitories,
+                    "inUse": 0
+                });
+            }
+        }
+    }
+    refresh_furniture(building, building_table);
+}
+
+/// Places a DIY solution in a dormitory if the player owns enough of every piece it uses beyond the other dormitories.
+pub fn apply_diy_solution(building: &mut Value, building_table: &Value, room_slot_id: &str, solution: &Value) -> bool {
+    if building["rooms"]["DORMITORY"][room_slot_id].is_null() {
+        return false;
+    }
+    let mut in_use = furniture_in_use(building, Some(room_slot_id));
+    for furniture_id in placed_furniture(solution) {
+        let used = in_use[&furniture_id].as_u64().unwrap_or(0) + 1;
+        if used > building["furniture"][&furniture_id]["count"].as_u64().unwrap_or(0) {
+            return false;
+        }
+        in_use[&furniture_id] = json!(used);
+    }
+    building["rooms"]["DORMITORY"][room_slot_id]["diySolution"] = solution.clone();
+    refresh_furniture(building, building_table);
+    true
+}
+
+/// The DIY solution a theme's quick setup lays out.
+fn theme_solution(building_table: &Value, theme_id: &str) -> Option<Value> {
+    let custom_data = &building_table["customData"];
+    let mut solution = json!({
+        "wallPaper": null,
+        "floor": null,
+        "carpet": [],
+        "other": []
+    });
+    for furniture in custom_data["themes"][theme_id]["quickSetup"].as_array()? {
+        let furniture_id = furniture["furnitureId"].as_str().unwrap();
+        let placed = json!({
+            "id": furniture_id,
+            "coordinate": {
+                "x": furniture["pos0"],
+                "y": furniture["pos1"],
+                "dir": furniture["dir"]
+            }
+        });
+        match custom_data["furnitures"][furniture_id]["type"].as_str().unwrap_or("") {
+            "WALLPAPER" => solution["wallPaper"] = json!(furniture_id),
+            "FLOOR" => solution["floor"] = json!(furniture_id),
+            "CARPET" => solution["carpet"].as_array_mut().unwrap().push(placed),
+            _ => solution["other"].as_array_mut().unwrap().push(placed),
+        }
+    }
+    Some(solution)
+}
+
+pub async fn diy_preset_list() -> JSON {
+    Json(json!({
+        "result": 0,
+        "presets": read_json(DIY_PRESETS_PATH)
+    }))
+}
+
+/// Saves a dormitory's current layout to `config/diyPresets.json` under a name.
+pub async fn diy_preset_save(Json(payload): JSON) -> JSON {
+    let building = read_json(BUILDING_JSON_PATH);
+    let solution = &building["rooms"]["DORMITORY"][payload["roomSlotId"].as_str().unwrap_or("")]["diySolution"];
+    let Some(name) = payload["name"].as_str().filter(|_| solution.is_object()) else {
+        return error_result();
+    };
+
+    let mut presets = read_json(DIY_PRESETS_PATH);
+    presets[name] = solution.clone();
+    write_json(DIY_PRESETS_PATH, &presets);
+    Json(json!({
+        "result": 0,
+        "presets": presets
+    }))
+}
+
+pub async fn diy_preset_delete(Json(payload): JSON) -> JSON {
+    let mut presets = read_json(DIY_PRESETS_PATH);
+    if presets
+        .as_object_mut()
+        .unwrap()
+        .remove(payload["name"].as_str().unwrap_or(""))
+        .is_none()
+    {
+        return error_result();
+    }
+    write_json(DIY_PRESETS_PATH, &presets);
+    Json(json!({
+        "result": 0,
+        "presets": presets
+    }))
+}
+
+/// Lays out a saved preset (`name`) or a furniture theme's quick setup (`themeId`) in a dormitory.
+pub async fn diy_preset_load(Json(payload): JSON) -> JSON {
+    let building_table = update_data(BUILDING_TABLE_URL).await;
+    let room_slot_id = payload["roomSlotId"].as_str().unwrap_or("");
+    let solution = match (payload["name"].as_str(), payload["themeId"].as_str()) {
+        (Some(name), _) => Some(read_json(DIY_PRESETS_PATH)[name].clone()).filter(Value::is_object),
+        (None, Some(theme_id)) => theme_solution(&building_table, theme_id),
+        (None, None) => None,
+    };
+
+    let mut building = read_json(BUILDING_JSON_PATH);
+    let Some(solution) = solution else {
+        return error_result();
+    };
+    if !apply_diy_solution(&mut building, &building_table, room_slot_id, &solution) {
+        return error_result();
+    }
+    write_json(BUILDING_JSON_PATH, &building);
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": {
+                "building": building
+            },
+            "deleted": {}
+        }
+    }))
+}
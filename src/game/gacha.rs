@@ -1,3 +1,139 @@
-pub mod normal {}
+pub mod normal {
+    use axum::Json;
+    use serde_json::{json, Value};
+
+    use crate::{
+        constants::{
+            url::GACHA_TABLE_URL,
+            user::{GACHA_JSON_PATH, USER_JSON_PATH},
+        },
+        core::time,
+        game::mission::{track_missions, MissionEvent},
+        utils::{
+            game::{error_result, update_data},
+            item::consume_items,
+            json::{get_values, read_json, write_json, JSON},
+        },
+    };
+
+    const SLOT_IDLE: u64 = 1;
+    const SLOT_RECRUITING: u64 = 2;
+
+    fn slot_key(payload: &Value) -> Option<String> {
+        match &payload["slotId"] {
+            Value::Number(slot_id) => Some(slot_id.to_string()),
+            Value::String(slot_id) => Some(slot_id.clone()),
+            _ => None,
+        }
+    }
+
+    fn slot_delta(slot_id: &str, slot: &Value, mut modified: Value) -> Value {
+        modified["recruit"]["normal"]["slots"][slot_id] = slot.clone();
+        json!({
+            "modified": modified,
+            "deleted": {}
+        })
+    }
+
+    /// Starts a recruitment in an idle slot, paying a recruitment permit and the LMD its duration costs.
+    pub async fn gacha_normal_gacha(Json(payload): JSON) -> JSON {
+        let Some(slot_id) = slot_key(&payload) else {
+            return error_result();
+        };
+        let duration = payload["duration"].as_u64().unwrap_or(0);
+        let gacha_table = update_data(GACHA_TABLE_URL).await;
+        let price = gacha_table["recruitPool"]["recruitTimeTable"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|time_length| time_length["timeLength"].as_u64() == Some(duration / 60))
+            .and_then(|time_length| time_length["recruitPrice"].as_u64());
+        let Some(price) = price else {
+            return error_result();
+        };
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        if user_data["user"]["recruit"]["normal"]["slots"][&slot_id]["state"] != SLOT_IDLE {
+            return error_result();
+        }
+        let costs = [
+            json!({ "id": "7001", "count": 1, "type": "TKT_RECRUIT" }),
+            json!({ "id": "4001", "count": price, "type": "GOLD" }),
+        ];
+        let mut modified = json!({});
+        if consume_items(&mut user_data["user"], &costs, &mut modified).await.is_err() {
+            return error_result();
+        }
+
+        let select_tags = payload["tagList"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|tag_id| json!({ "tagId": tag_id, "pick": 1 }))
+            .collect::<Vec<_>>();
+        let now = time();
+        let slot = &mut user_data["user"]["recruit"]["normal"]["slots"][&slot_id];
+        slot["state"] = json!(SLOT_RECRUITING);
+        slot["selectTags"] = json!(select_tags);
+        slot["startTs"] = json!(now);
+        slot["durationInSec"] = json!(duration);
+        slot["maxFinishTs"] = json!(now + duration);
+        slot["realFinishTs"] = json!(now + duration);
+        let slot = slot.clone();
+        write_json(USER_JSON_PATH, user_data);
+
+        Json(json!({
+            "result": 0,
+            "playerDataDelta": slot_delta(&slot_id, &slot, modified)
+        }))
+    }
+
+    /// Finishes a recruitment that has run its course with the operator set in `data/user/gacha.json`.
+    pub async fn gacha_finish_normal_gacha(Json(payload): JSON) -> JSON {
+        let Some(slot_id) = slot_key(&payload) else {
+            return error_result();
+        };
+        let mut user_data = read_json(USER_JSON_PATH);
+        let slot = &user_data["user"]["recruit"]["normal"]["slots"][&slot_id];
+        let finished = slot["realFinishTs"].as_u64().is_some_and(|finish_ts| finish_ts <= time());
+        if slot["state"] != SLOT_RECRUITING || !finished {
+            return error_result();
+        }
+
+        // Every operator comes with the synced save, so the recruit is always one the player already has.
+        let gacha = read_json(GACHA_JSON_PATH);
+        let char_id = gacha["normal"]["charId"].as_str().unwrap_or("");
+        let inst_id = get_values(&user_data["user"]["troop"]["chars"])
+            .into_iter()
+            .find(|char| char["charId"] == char_id)
+            .map(|char| char["instId"].clone());
+        let Some(inst_id) = inst_id else {
+            return error_result();
+        };
+
+        let slot = &mut user_data["user"]["recruit"]["normal"]["slots"][&slot_id];
+        slot["state"] = json!(SLOT_IDLE);
+        slot["selectTags"] = json!([]);
+        for field in ["startTs", "durationInSec", "maxFinishTs", "realFinishTs"] {
+            slot[field] = json!(-1);
+        }
+        let slot = slot.clone();
+
+        let mut modified = json!({});
+        track_missions(&mut user_data["user"], &[MissionEvent::Count("NormalGacha", 1)], &mut modified).await;
+        write_json(USER_JSON_PATH, user_data);
+
+        Json(json!({
+            "result": 0,
+            "charGet": {
+                "charInstId": inst_id,
+                "charId": char_id,
+                "isNew": 0,
+                "itemGet": []
+            },
+            "playerDataDelta": slot_delta(&slot_id, &slot, modified)
+        }))
+    }
+}
 
 pub mod advanced {}
@@ -0,0 +1,323 @@
+use axum::Json;
+use serde_json::{json, Value};
+
+use crate::{
+    constants::{
+        url::MEDAL_TABLE_URL,
+        user::{MEDAL_COUNTERS_JSON_PATH, USER_JSON_PATH},
+    },
+    core::time,
+    utils::{
+        game::{error_result, update_data},
+        item::grant_items,
+        json::{get_values, read_json, write_json, JSON},
+    },
+};
+
+/// Medal templates counted from events rather than read off the save, keyed in `medalCounters.json`.
+pub const MANUFACTURE_PRODUCT_TIMES: &str = "BuildingManufactureProductTimes";
+
+/// What medal conditions are checked against besides the save, passed in from what the caller has loaded.
+/// Conditions whose source is left out keep their saved progress.
+#[derive(Default)]
+pub struct MedalContext<'a> {
+    /// `favor_table`, for trust medals.
+    pub favor_table: Option<&'a Value>,
+    /// `building_data` and the building save, for furniture theme medals.
+    pub base: Option<(&'a Value, &'a Value)>,
+}
+
+impl MedalContext<'_> {
+    /// How many furniture themes have every piece of their quick setup owned.
+    fn furniture_themes(&self) -> Option<u64> {
+        let (building_table, building) = self.base?;
+        let owns_furniture = |furniture_id: &Value| {
+            building["furniture"][furniture_id.as_str().unwrap_or("")]["count"]
+                .as_u64()
+                .unwrap_or(0)
+                > 0
+        };
+        let themes = get_values(&building_table["customData"]["themes"])
+            .iter()
+            .filter(|theme| {
+                theme["quickSetup"]
+                    .as_array()
+                    .is_some_and(|setup| !setup.is_empty() && setup.iter().all(|piece| owns_furniture(&piece["furnitureId"])))
+            })
+            .count() as u64;
+        Some(themes)
+    }
+}
+
+/// The favor points an operator needs for a trust `percent`.
+pub fn favor_point(favor_table: &Value, percent: u64) -> u64 {
+    favor_table["favorFrames"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|frame| frame["data"]["percent"].as_u64().unwrap_or(0) >= percent)
+        .and_then(|frame| frame["data"]["favorPoint"].as_u64())
+        .unwrap_or(u64::MAX)
+}
+
+/// Adds `count` to an event-counted medal condition, such as products collected.
+pub fn count_medal_event(template: &str, count: u64) {
+    let mut counters = read_json(MEDAL_COUNTERS_JSON_PATH);
+    counters[template] = json!(counters[template].as_u64().unwrap_or(0) + count);
+    write_json(MEDAL_COUNTERS_JSON_PATH, counters);
+}
+
+fn param(medal: &Value, index: usize) -> &str {
+    medal["unlockParam"][index].as_str().unwrap_or("")
+}
+
+fn number_param(medal: &Value, index: usize) -> u64 {
+    param(medal, index).parse().unwrap_or(u64::MAX)
+}
+
+fn id_list_param(medal: &Value, index: usize) -> Vec<&str> {
+    param(medal, index).split(';').filter(|id| !id.is_empty()).collect()
+}
+
+/// Every skill of an operator, those of its templates included.
+fn all_skills(char: &Value) -> Vec<&Value> {
+    let mut skills: Vec<&Value> = char["skills"].as_array().into_iter().flatten().collect();
+    for tmpl in char["tmpl"].as_object().into_iter().flat_map(|tmpls| tmpls.values()) {
+        skills.extend(tmpl["skills"].as_array().into_iter().flatten());
+    }
+    skills
+}
+
+/// Progress towards a medal as `[current, target]`, or `None` for conditions the server doesn't track.
+fn medal_progress(medal: &Value, user: &Value, context: &MedalContext, counters: &Value) -> Option<(u64, u64)> {
+    let chars = get_values(&user["troop"]["chars"]);
+    let owned = |char_id: &str| chars.iter().find(|char| char["charId"] == char_id);
+    let count_chars = |matches: &dyn Fn(&Value) -> bool| chars.iter().filter(|char| matches(char)).count() as u64;
+
+    let progress = match medal["template"].as_str()? {
+        "PlayerLevel" => (user["status"]["level"].as_u64().unwrap_or(0), number_param(medal, 0)),
+        "JoinGameDays" => {
+            let register_ts = user["status"]["registerTs"].as_u64().unwrap_or(time());
+            (time().saturating_sub(register_ts) / 86400, number_param(medal, 0))
+        }
+        "PassStageSome" => {
+            let state = number_param(medal, 0);
+            let stages = id_list_param(medal, 1);
+            let passed = stages
+                .iter()
+                .filter(|stage_id| user["dungeon"]["stages"][**stage_id]["state"].as_u64().unwrap_or(0) >= state)
+                .count() as u64;
+            (passed, number_param(medal, 2))
+        }
+        "CharNum" => (chars.len() as u64, number_param(medal, 0)),
+        "GotChars" => {
+            let char_ids = id_list_param(medal, 0);
+            let got = char_ids.iter().filter(|char_id| owned(char_id).is_some()).count() as u64;
+            (got, char_ids.len() as u64)
+        }
+        "CharPotential" => {
+            let char_ids = id_list_param(medal, 0);
+            let rank = number_param(medal, 1);
+            let got = char_ids
+                .iter()
+                .filter_map(|char_id| owned(char_id))
+                .filter(|char| char["potentialRank"].as_u64().unwrap_or(0) >= rank)
+                .count() as u64;
+            (got, char_ids.len() as u64)
+        }
+        "CharEvolveCount" => {
+            let phase = number_param(medal, 1);
+            let count = count_chars(&|char| char["evolvePhase"].as_u64().unwrap_or(0) >= phase);
+            (count, number_param(medal, 0))
+        }
+        "CharFavorCount" => {
+            let favor_point = favor_point(context.favor_table?, number_param(medal, 1));
+            let count = count_chars(&|char| char["favorPoint"].as_u64().unwrap_or(0) >= favor_point);
+            (count, number_param(medal, 0))
+        }
+        "CharSkillCount" => {
+            let level = number_param(medal, 1);
+            let count = count_chars(&|char| char["mainSkillLvl"].as_u64().unwrap_or(0) >= level);
+            (count, number_param(medal, 0))
+        }
+        "CharSkillSpecCount" => {
+            let level = number_param(medal, 1);
+            let count = chars
+                .iter()
+                .flat_map(|char| all_skills(char))
+                .filter(|skill| skill["specializeLevel"].as_u64().unwrap_or(0) >= level)
+                .count() as u64;
+            (count, number_param(medal, 0))
+        }
+        "BuildingGotFurnitureThemeCount" => (context.furniture_themes()?, number_param(medal, 0)),
+        MANUFACTURE_PRODUCT_TIMES => (counters[MANUFACTURE_PRODUCT_TIMES].as_u64().unwrap_or(0), number_param(medal, 0)),
+        _ => return None,
+    };
+    Some((progress.0.min(progress.1), progress.1))
+}
+
+pub fn is_earned(user: &Value, medal_id: &str) -> bool {
+    user["medal"]["medals"][medal_id]["fts"].as_i64().unwrap_or(-1) > 0
+}
+
+/// Updates medal progress and awards every medal whose condition and prerequisite medals are now met,
+/// adding the changed medals to `modified`.
+pub async fn award_medals(user: &mut Value, context: &MedalContext<'_>, modified: &mut Value) {
+    let medal_table = update_data(MEDAL_TABLE_URL).await;
+    let counters = read_json(MEDAL_COUNTERS_JSON_PATH);
+    if !user["medal"]["medals"].is_object() {
+        user["medal"]["medals"] = json!({});
+    }
+
+    for medal in medal_table["medalList"].as_array().unwrap() {
+        let medal_id = medal["medalId"].as_str().unwrap();
+        if is_earned(user, medal_id) {
+            continue;
+        }
+        let Some((current, target)) = medal_progress(medal, user, context, &counters) else {
+            continue;
+        };
+        let unlocked = medal["preMedalIdList"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|pre_medal_id| is_earned(user, pre_medal_id.as_str().unwrap_or("")));
+
+        let earned = unlocked && current >= target;
+        let entry = json!({
+            "id": medal_id,
+            "val": [[current, target]],
+            "fts": if earned { time() as i64 } else { -1 },
+            "rts": -1
+        });
+        if user["medal"]["medals"][medal_id] != entry {
+            user["medal"]["medals"][medal_id] = entry.clone();
+            modified["medal"]["medals"][medal_id] = entry;
+        }
+    }
+}
+
+pub fn is_medal(medal_table: &Value, medal_id: &str) -> bool {
+    medal_table["medalList"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|medal| medal["medalId"] == medal_id)
+}
+
+/// A medal set from `medalTypeData`, which a `TEMPLATE` medal board shows.
+pub fn medal_group<'a>(medal_table: &'a Value, group_id: &str) -> Option<&'a Value> {
+    medal_table["medalTypeData"]
+        .as_object()
+        .unwrap()
+        .values()
+        .flat_map(|medal_type| medal_type["groupData"].as_array().into_iter().flatten())
+        .find(|group| group["groupId"] == group_id)
+}
+
+/// The medal board other players see: the custom layout or the template, as set in `social.medalBoard`.
+pub fn public_medal_board(user: &Value) -> Value {
+    let medal_board = &user["social"]["medalBoard"];
+    match medal_board["type"].as_str().unwrap_or("EMPTY") {
+        "CUSTOM" => json!({
+            "type": "CUSTOM",
+            "custom": user["medal"]["custom"]["customs"][medal_board["custom"].as_str().unwrap_or("")],
+            "template": null
+        }),
+        "TEMPLATE" => json!({
+            "type": "TEMPLATE",
+            "custom": null,
+            "template": medal_board["template"]
+        }),
+        _ => json!({
+            "type": "EMPTY",
+            "custom": null,
+            "template": null
+        }),
+    }
+}
+
+/// Saves a custom medal board layout (`data.layout`, medals with their `pos`) under `index`.
+pub async fn medal_set_custom_data(Json(payload): JSON) -> JSON {
+    let medal_table = update_data(MEDAL_TABLE_URL).await;
+    let Some(index) = payload["index"].as_str() else {
+        return error_result();
+    };
+    let layout = payload["data"]["layout"].as_array().cloned().unwrap_or_default();
+    let mut medal_ids = Vec::new();
+    for medal in &layout {
+        let medal_id = medal["id"].as_str().unwrap_or("");
+        if !is_medal(&medal_table, medal_id) || medal_ids.contains(&medal_id) || !medal["pos"].is_array() {
+            return error_result();
+        }
+        medal_ids.push(medal_id);
+    }
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    if !medal_ids.iter().all(|medal_id| is_earned(&user_data["user"], medal_id)) {
+        return error_result();
+    }
+    let custom = &mut user_data["user"]["medal"]["custom"];
+    custom["customs"][index] = json!({
+        "layout": layout
+    });
+    custom["currentIndex"] = json!(index);
+    let modified_custom = custom.clone();
+    write_json(USER_JSON_PATH, user_data);
+
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": {
+                "medal": {
+                    "custom": modified_custom
+                }
+            },
+            "deleted": {}
+        }
+    }))
+}
+
+/// Claims the items of an earned medal's reward group.
+pub async fn medal_reward_medal(Json(payload): JSON) -> JSON {
+    let medal_table = update_data(MEDAL_TABLE_URL).await;
+    let medal_id = payload["medalId"].as_str().unwrap_or("");
+    let Some(medal) = medal_table["medalList"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|medal| medal["medalId"] == medal_id)
+    else {
+        return error_result();
+    };
+    let group_id = payload["groupId"].as_str().unwrap_or("default");
+    let Some(reward_group) = medal["medalRewardGroup"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|group| group["groupId"] == group_id)
+    else {
+        return error_result();
+    };
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let user = &mut user_data["user"];
+    if !is_earned(user, medal_id) || user["medal"]["medals"][medal_id]["rts"].as_i64().unwrap_or(-1) > 0 {
+        return error_result();
+    }
+    let mut modified = json!({});
+    let items = grant_items(user, reward_group["itemList"].as_array().unwrap(), &mut modified).await;
+    user["medal"]["medals"][medal_id]["rts"] = json!(time());
+    user["medal"]["medals"][medal_id]["reward"] = json!(group_id);
+    modified["medal"]["medals"][medal_id] = user["medal"]["medals"][medal_id].clone();
+    write_json(USER_JSON_PATH, user_data);
+
+    Json(json!({
+        "result": 0,
+        "items": items,
+        "playerDataDelta": {
+            "modified": modified,
+            "deleted": {}
+        }
+    }))
+}
@@ -0,0 +1,380 @@
+use axum::Json;
+use rand::seq::SliceRandom;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    constants::{url::CLUE_TABLE_URL, user::USER_JSON_PATH},
+    core::time,
+    utils::{
+        game::{day_start, error_result, update_data},
+        json::{get_keys, read_json, write_json, JSON},
+    },
+};
+
+use super::{
+    building::{open_base, Base},
+    mission::{track_missions, MissionEvent},
+    players::{list_players, local_uid, publish_player, read_player, write_player},
+};
+
+/// Visits older than this drop out of a player's visit log.
+const VISIT_LOG_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+fn default_meeting() -> Value {
+    json!({
+        "state": 1,
+        "speed": 1.0,
+        "processPoint": 0,
+        "ownStock": [],
+        "receiveStock": [],
+        "board": {},
+        "socialReward": {
+            "daily": 0,
+            "search": 0
+        },
+        "dailyReward": null,
+        "expiredReward": 0,
+        "received": 0,
+        "infoShare": {
+            "ts": 0,
+            "reward": 0
+        },
+        "lastVisitTs": 0,
+        "startTime": -1,
+        "mfc": {}
+    })
+}
+
+fn credit(count: u64) -> Value {
+    json!({
+        "id": "4003",
+        "type": "SOCIAL_PT",
+        "count": count
+    })
+}
+
+/// A clue from `clue_data.json`, as it sits in a stock or on the board, marked as found by `from`.
+fn new_clue(clue: &Value, from: &Value) -> Value {
+    json!({
+        "id": Uuid::new_v4().to_string(),
+        "type": clue["clueType"],
+        "number": clue["number"],
+        "uid": from["uid"],
+        "name": from["nickName"],
+        "nickNum": from["nickNumber"],
+        "chars": [],
+        "inUse": 0,
+        "ts": time()
+    })
+}
+
+fn random_clue(clue_table: &Value, from: &Value) -> Value {
+    let clue = clue_table["clues"].as_array().unwrap().choose(&mut rand::thread_rng()).unwrap();
+    new_clue(clue, from)
+}
+
+/// The base's meeting room slot and state, with the clues and credit other players left for the local player moved in.
+/// The local player's emptied record is only written by `save_meeting`, so a refused request doesn't lose them.
+async fn open_meeting() -> Option<(Base, String, Value)> {
+    let mut base = open_base().await;
+    let slot_id = get_keys(&base.building["rooms"]["MEETING"]).into_iter().next()?;
+    let meeting = &mut base.building["rooms"]["MEETING"][&slot_id];
+    for (field, value) in default_meeting().as_object().unwrap() {
+        if meeting[field].is_null() && !value.is_null() {
+            meeting[field] = value.clone();
+        }
+    }
+
+    let mut player = publish_player(&base.user_data["user"]);
+    let inbox = player["clueInbox"].as_array().cloned().unwrap_or_default();
+    base.building["rooms"]["MEETING"][&slot_id]["receiveStock"]
+        .as_array_mut()
+        .unwrap()
+        .extend(inbox);
+    let pending_credit = player["pendingCredit"].as_u64().unwrap_or(0);
+    if pending_credit > 0 {
+        base.grant(&[credit(pending_credit)]).await;
+    }
+    player["clueInbox"] = json!([]);
+    player["pendingCredit"] = json!(0);
+    Some((base, slot_id, player))
+}
+
+fn save_meeting(base: Base, player: &Value, response: Value) -> JSON {
+    write_player(player);
+    base.save(response)
+}
+
+fn take_clue(stock: &mut Value, clue_id: &str) -> Option<Value> {
+    let stock = stock.as_array_mut()?;
+    let index = stock.iter().position(|clue| clue["id"] == clue_id)?;
+    Some(stock.remove(index))
+}
+
+pub async fn building_get_clue_box() -> JSON {
+    let Some((base, slot_id, player)) = open_meeting().await else {
+        return error_result();
+    };
+    let receive_stock = base.building["rooms"]["MEETING"][&slot_id]["receiveStock"].clone();
+    save_meeting(base, &player, json!({ "box": receive_stock }))
+}
+
+/// Other local players, with whether their clue inbox still has room.
+pub async fn building_get_clue_friend_list() -> JSON {
+    let user_data = read_json(USER_JSON_PATH);
+    let uid = local_uid(&user_data["user"]);
+    let clue_table = update_data(CLUE_TABLE_URL).await;
+    let inventory_limit = clue_table["inventoryLimit"].as_u64().unwrap_or(10) as usize;
+
+    let friends: Vec<Value> = list_players()
+        .into_iter()
+        .filter(|player| player["uid"] != uid.as_str())
+        .map(|player| {
+            json!({
+                "uid": player["uid"],
+                "nickName": player["nickName"],
+                "nickNumber": player["nickNumber"],
+                "level": player["level"],
+                "avatarId": player["avatarId"],
+                "avatar": player["avatar"],
+                "lastOnlineTime": player["lastOnlineTime"],
+                "canReceive": player["clueInbox"].as_array().map(Vec::len).unwrap_or(0) < inventory_limit
+            })
+        })
+        .collect();
+    Json(json!({
+        "result": friends,
+        "playerDataDelta": {
+            "modified": {},
+            "deleted": {}
+        }
+    }))
+}
+
+/// The clue the meeting room turns up once a day.
+pub async fn building_get_daily_clue() -> JSON {
+    let Some((mut base, slot_id, player)) = open_meeting().await else {
+        return error_result();
+    };
+    let clue_table = update_data(CLUE_TABLE_URL).await;
+    let meeting = &mut base.building["rooms"]["MEETING"][&slot_id];
+    let now = time();
+    if meeting["dailyReward"]["ts"].as_u64().unwrap_or(0) >= day_start(now)
+        || meeting["ownStock"].as_array().unwrap().len() >= clue_table["inventoryLimit"].as_u64().unwrap_or(10) as usize
+    {
+        return error_result();
+    }
+
+    let clue = random_clue(&clue_table, &player);
+    meeting["ownStock"].as_array_mut().unwrap().push(clue.clone());
+    meeting["dailyReward"] = json!({ "ts": now });
+    save_meeting(base, &player, json!({ "clue": clue }))
+}
+
+pub async fn building_receive_clue_to_stock(Json(payload): JSON) -> JSON {
+    let Some((mut base, slot_id, player)) = open_meeting().await else {
+        return error_result();
+    };
+    let clue_table = update_data(CLUE_TABLE_URL).await;
+    let inventory_limit = clue_table["inventoryLimit"].as_u64().unwrap_or(10) as usize;
+    let meeting = &mut base.building["rooms"]["MEETING"][&slot_id];
+
+    let clue_ids = payload["clues"].as_array().cloned().unwrap_or_default();
+    if meeting["ownStock"].as_array().unwrap().len() + clue_ids.len() > inventory_limit {
+        return error_result();
+    }
+    for clue_id in clue_ids {
+        let Some(clue) = take_clue(&mut meeting["receiveStock"], clue_id.as_str().unwrap_or("")) else {
+            return error_result();
+        };
+        meeting["ownStock"].as_array_mut().unwrap().push(clue);
+    }
+    let received = meeting["received"].as_u64().unwrap_or(0);
+    meeting["received"] = json!(received + 1);
+    save_meeting(base, &player, json!({}))
+}
+
+/// Gives a clue from the own stock to another local player, for the transfer bonus in credit.
+pub async fn building_send_clue(Json(payload): JSON) -> JSON {
+    let Some((mut base, slot_id, player)) = open_meeting().await else {
+        return error_result();
+    };
+    let clue_table = update_data(CLUE_TABLE_URL).await;
+    let friend_id = payload["friendId"].as_str().unwrap_or("");
+    let Some(mut friend) = read_player(friend_id).filter(|_| friend_id != player["uid"]) else {
+        return error_result();
+    };
+    if friend["clueInbox"].as_array().map(Vec::len).unwrap_or(0) >= clue_table["inventoryLimit"].as_u64().unwrap_or(10) as usize {
+        return error_result();
+    }
+    let meeting = &mut base.building["rooms"]["MEETING"][&slot_id];
+    let Some(mut clue) = take_clue(&mut meeting["ownStock"], payload["clueId"].as_str().unwrap_or("")) else {
+        return error_result();
+    };
+
+    clue["uid"] = player["uid"].clone();
+    clue["name"] = player["nickName"].clone();
+    clue["nickNum"] = player["nickNumber"].clone();
+    clue["ts"] = json!(time());
+    friend["clueInbox"].as_array_mut().unwrap().push(clue);
+    write_player(&friend);
+    base.grant(&[credit(clue_table["transferBonus"].as_u64().unwrap_or(0))]).await;
+    base.track(&[MissionEvent::Count("SendClue", 1)]).await;
+    save_meeting(base, &player, json!({}))
+}
+
+/// Pins a clue to the board, swapping out the one of the same type.
+pub async fn building_put_clue_to_the_board(Json(payload): JSON) -> JSON {
+    let Some((mut base, slot_id, player)) = open_meeting().await else {
+        return error_result();
+    };
+    let meeting = &mut base.building["rooms"]["MEETING"][&slot_id];
+    let Some(clue) = take_clue(&mut meeting["ownStock"], payload["clueId"].as_str().unwrap_or("")) else {
+        return error_result();
+    };
+    let clue_type = clue["type"].as_str().unwrap().to_string();
+    if meeting["board"][&clue_type].is_object() {
+        let previous = meeting["board"][&clue_type].take();
+        meeting["ownStock"].as_array_mut().unwrap().push(previous);
+    }
+    meeting["board"][&clue_type] = clue;
+    save_meeting(base, &player, json!({}))
+}
+
+pub async fn building_take_clue_from_board(Json(payload): JSON) -> JSON {
+    let Some((mut base, slot_id, player)) = open_meeting().await else {
+        return error_result();
+    };
+    let meeting = &mut base.building["rooms"]["MEETING"][&slot_id];
+    let clue_type = payload["type"].as_str().unwrap_or("");
+    let Some(clue) = meeting["board"].as_object_mut().and_then(|board| board.remove(clue_type)) else {
+        return error_result();
+    };
+    meeting["ownStock"].as_array_mut().unwrap().push(clue);
+    save_meeting(base, &player, json!({}))
+}
+
+/// Starts a clue exchange with a full board. The local player gets the initiator bonus, and every other player
+/// whose clue is on the board gets the participant bonus the next time they open their meeting room.
+pub async fn building_start_info_share() -> JSON {
+    let Some((mut base, slot_id, player)) = open_meeting().await else {
+        return error_result();
+    };
+    let clue_table = update_data(CLUE_TABLE_URL).await;
+    let now = time();
+    let meeting = &mut base.building["rooms"]["MEETING"][&slot_id];
+    let board_full = clue_table["clueTypes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|clue_type| meeting["board"][clue_type["clueType"].as_str().unwrap()].is_object());
+    if !board_full || meeting["infoShare"]["ts"].as_u64().unwrap_or(0) > now {
+        return error_result();
+    }
+
+    let participants_bonus = clue_table["participantsBonus"].as_u64().unwrap_or(0);
+    for clue in meeting["board"].as_object().unwrap().values() {
+        let Some(mut participant) = clue["uid"].as_str().filter(|uid| player["uid"] != *uid).and_then(read_player) else {
+            continue;
+        };
+        let pending_credit = participant["pendingCredit"].as_u64().unwrap_or(0);
+        participant["pendingCredit"] = json!(pending_credit + participants_bonus);
+        write_player(&participant);
+    }
+    meeting["board"] = json!({});
+    meeting["startTime"] = json!(now);
+    meeting["infoShare"] = json!({
+        "ts": now + clue_table["communicationDuration"].as_u64().unwrap_or(0),
+        "reward": 1
+    });
+    base.grant(&[credit(clue_table["initiatorBonus"].as_u64().unwrap_or(0))]).await;
+    base.track(&[MissionEvent::Count("StartInfoShare", 1)]).await;
+    save_meeting(base, &player, json!({}))
+}
+
+/// Visits another local player's base, leaving an entry in their visit log.
+pub async fn building_visit_building(Json(payload): JSON) -> JSON {
+    let mut user_data = read_json(USER_JSON_PATH);
+    let player = publish_player(&user_data["user"]);
+    let friend_id = payload["friendId"].as_str().unwrap_or("");
+    let Some(mut friend) = read_player(friend_id).filter(|_| friend_id != player["uid"]) else {
+        return error_result();
+    };
+    log_visit(&mut friend, &player);
+    write_player(&friend);
+    let mut modified = json!({});
+    track_missions(&mut user_data["user"], &[MissionEvent::Count("VisitBuilding", 1)], &mut modified).await;
+    write_json(USER_JSON_PATH, user_data);
+    Json(json!({
+        "result": 0,
+        "friend": friend,
+        "playerDataDelta": {
+            "modified": modified,
+            "deleted": {}
+        }
+    }))
+}
+
+fn log_visit(host: &mut Value, visitor: &Value) {
+    let now = time();
+    let mut visitors: Vec<Value> = host["visitors"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|visit| visit["ts"].as_u64().unwrap_or(0) + VISIT_LOG_SECONDS > now)
+        .collect();
+    visitors.push(json!({
+        "uid": visitor["uid"],
+        "nickName": visitor["nickName"],
+        "nickNumber": visitor["nickNumber"],
+        "ts": now
+    }));
+    host["visitors"] = json!(visitors);
+}
+
+fn local_visitors() -> Vec<Value> {
+    let user_data = read_json(USER_JSON_PATH);
+    let player = publish_player(&user_data["user"]);
+    player["visitors"].as_array().cloned().unwrap_or_default()
+}
+
+pub async fn building_get_recent_visitors() -> JSON {
+    let mut visitors = local_visitors();
+    visitors.reverse();
+    Json(json!({ "visitors": visitors }))
+}
+
+/// How many players have visited since the daily reset.
+pub async fn building_get_info_share_visitor_num() -> JSON {
+    let today = day_start(time());
+    let num = local_visitors()
+        .iter()
+        .filter(|visit| visit["ts"].as_u64().unwrap_or(0) >= today)
+        .count();
+    Json(json!({ "num": num }))
+}
+
+/// Has another local player send the local player a random clue and visit their base.
+pub async fn meeting_debug_send_clue(Json(payload): JSON) -> JSON {
+    let clue_table = update_data(CLUE_TABLE_URL).await;
+    let user_data = read_json(USER_JSON_PATH);
+    let mut player = publish_player(&user_data["user"]);
+    let Some(friend) = payload["uid"]
+        .as_str()
+        .and_then(read_player)
+        .filter(|friend| friend["uid"] != player["uid"])
+    else {
+        return error_result();
+    };
+
+    let clue = random_clue(&clue_table, &friend);
+    player["clueInbox"].as_array_mut().unwrap().push(clue.clone());
+    log_visit(&mut player, &friend);
+    write_player(&player);
+    Json(json!({
+        "result": 0,
+        "clue": clue
+    }))
+}
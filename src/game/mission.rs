@@ -0,0 +1,554 @@
+use axum::Json;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    constants::{
+        url::{BUILDING_TABLE_URL, FAVOR_TABLE_URL, MISSION_TABLE_URL},
+        user::{BUILDING_JSON_PATH, MISSION_JSON_PATH, USER_JSON_PATH},
+    },
+    core::time,
+    utils::{
+        game::{day_start, error_result, update_data, week_start, weekday},
+        item::grant_items,
+        json::{get_keys, get_values, read_json, write_json, JSON},
+    },
+};
+
+use super::{building::room_count, medal::favor_point};
+
+const MISSION_LOCKED: u64 = 1;
+const MISSION_ACTIVE: u64 = 2;
+const MISSION_CONFIRMED: u64 = 3;
+
+/// Mission types the server keeps, in the order they're checked.
+const MISSION_TYPES: [&str; 4] = ["DAILY", "WEEKLY", "GUIDE", "MAIN"];
+
+/// Mission types whose groups unlock one after another and never reset.
+const PROGRESSION_TYPES: [&str; 2] = ["GUIDE", "MAIN"];
+
+/// Something the player did that missions may count.
+pub enum MissionEvent<'a> {
+    /// A battle finished, failed or not.
+    StageClear {
+        stage_id: &'a str,
+        complete_state: i64,
+        kills: u64,
+        with_assist: bool,
+        is_practice: bool,
+    },
+    /// An action done `count` times, named after the mission template counting it.
+    Count(&'a str, u64),
+    /// An action handling `count` of an item: once for missions of that template, or `count` for those naming the item.
+    Items(&'a str, &'a str, u64),
+}
+
+enum Tracking {
+    /// Counted up from events.
+    Event,
+    /// Read off the save whenever missions are checked.
+    State,
+    /// Nothing the server serves feeds it, so it stays at zero.
+    Untracked,
+}
+
+/// Everything mission conditions are checked against besides the save itself.
+struct MissionContext<'a> {
+    mission_table: Value,
+    favor_table: Value,
+    building_table: Value,
+    building: &'a Value,
+}
+
+impl<'a> MissionContext<'a> {
+    async fn load(building: &'a Value) -> Self {
+        MissionContext {
+            mission_table: update_data(MISSION_TABLE_URL).await,
+            favor_table: update_data(FAVOR_TABLE_URL).await,
+            building_table: update_data(BUILDING_TABLE_URL).await,
+            building,
+        }
+    }
+}
+
+fn param(mission: &Value, index: usize) -> &str {
+    mission["param"][index].as_str().unwrap_or("")
+}
+
+fn number_param(mission: &Value, index: usize) -> u64 {
+    param(mission, index).parse().unwrap_or(u64::MAX)
+}
+
+fn template(mission: &Value) -> &str {
+    mission["template"].as_str().unwrap_or("")
+}
+
+fn tracking(mission: &Value) -> Tracking {
+    match (template(mission), param(mission, 0)) {
+        ("UpgradeChar" | "UpgradeSkill" | "UpgradeSpecialization", "1")
+        | (
+            "UpgradePlayer" | "EvolveChar" | "BoostPotential" | "CharIntimacy" | "HasRoom" | "DiyComfort" | "SetAssistCharList"
+            | "CompleteMainStage",
+            _,
+        ) => Tracking::State,
+        (
+            "CompleteStageAnyType"
+            | "CompleteStage"
+            | "CompleteAnyStage"
+            | "EnemyKillInAnyStage"
+            | "StageWithEnemyKill"
+            | "StageWithAssistChar"
+            | "UpgradeChar"
+            | "UpgradeSkill"
+            | "UpgradeSpecialization"
+            | "ManufactureItem"
+            | "DeliveryOrder"
+            | "WorkshopSynthesis"
+            | "AccelerateOrder"
+            | "VisitBuilding"
+            | "SendClue"
+            | "StartInfoShare"
+            | "SetBuildingAssist"
+            | "EditBusinessCard"
+            | "ChangeSquadName"
+            | "RecoverCharBaseAp"
+            | "NormalGacha"
+            | "CostAp",
+            _,
+        ) => Tracking::Event,
+        _ => Tracking::Untracked,
+    }
+}
+
+/// How much progress a mission needs; most templates keep it in their last parameter.
+fn mission_target(mission: &Value) -> u64 {
+    let last = mission["param"]
+        .as_array()
+        .and_then(|param| param.last())
+        .and_then(|value| value.as_str())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1);
+    match (template(mission), param(mission, 0)) {
+        ("UpgradeSkill" | "UpgradeSpecialization", "1") | ("CompleteAnyStage" | "CompleteMainStage", _) => 1,
+        ("UpgradeChar" | "DiyComfort" | "ManufactureItem" | "DeliveryOrder" | "WorkshopSynthesis", "0" | "1")
+            if mission["param"].as_array().is_some_and(|param| param.len() > 2) =>
+        {
+            number_param(mission, 1)
+        }
+        ("CompleteStageAnyType" | "EnemyKillInAnyStage" | "EvolveChar" | "BoostPotential" | "CharIntimacy" | "HasRoom", _) => {
+            number_param(mission, 1)
+        }
+        _ => last,
+    }
+}
+
+/// How much an event adds to a mission counted from events.
+fn event_progress(mission: &Value, event: &MissionEvent) -> u64 {
+    match event {
+        MissionEvent::StageClear {
+            stage_id,
+            complete_state,
+            kills,
+            with_assist,
+            is_practice,
+        } => {
+            let state = (*complete_state).max(0) as u64;
+            let cleared = state >= 2;
+            let progress = match (template(mission), param(mission, 0)) {
+                ("CompleteStageAnyType", _) => state >= number_param(mission, 2),
+                ("CompleteStage", "0") => cleared && *stage_id == param(mission, 1),
+                ("CompleteStage", "2") => state >= number_param(mission, 1),
+                ("CompleteStage", "3") => cleared && *is_practice,
+                ("CompleteStage", "4") => cleared && stage_id.ends_with("#f#"),
+                ("CompleteAnyStage", _) => state >= number_param(mission, 2) && param(mission, 1).split('^').any(|id| id == *stage_id),
+                ("EnemyKillInAnyStage", _) if state >= number_param(mission, 2) => return *kills,
+                // Battle data doesn't say whether a leader fell, so any main theme clear counts.
+                ("StageWithEnemyKill", _) => cleared && stage_id.starts_with("main_"),
+                ("StageWithAssistChar", _) => cleared && *with_assist,
+                _ => false,
+            };
+            progress as u64
+        }
+        MissionEvent::Count(name, count) if *name == template(mission) => *count,
+        MissionEvent::Items(name, item_id, count) if *name == template(mission) => match mission["param"][2].as_str() {
+            Some(mission_item) if mission_item == *item_id => *count,
+            Some(_) => 0,
+            None => 1,
+        },
+        _ => 0,
+    }
+}
+
+/// Progress of a mission read off the save.
+fn state_progress(mission: &Value, user: &Value, context: &MissionContext) -> u64 {
+    let chars = get_values(&user["troop"]["chars"]);
+    let count_chars = |filter: &dyn Fn(&Value) -> bool| chars.iter().filter(|char| filter(char)).count() as u64;
+    let number = |value: &Value| value.as_u64().unwrap_or(0);
+    match template(mission) {
+        "UpgradePlayer" => number(&user["status"]["level"]),
+        "UpgradeChar" => {
+            let (phase, level) = (number_param(mission, 2), number_param(mission, 3));
+            count_chars(&|char| {
+                let char_phase = number(&char["evolvePhase"]);
+                char_phase > phase || (char_phase == phase && number(&char["level"]) >= level)
+            })
+        }
+        "UpgradeSkill" => (count_chars(&|char| number(&char["mainSkillLvl"]) >= number_param(mission, 1)) > 0) as u64,
+        "UpgradeSpecialization" => {
+            let level = number_param(mission, 1);
+            let specialized = |char: &Value| {
+                char["skills"]
+                    .as_array()
+                    .is_some_and(|skills| skills.iter().any(|skill| number(&skill["specializeLevel"]) >= level))
+            };
+            (count_chars(&specialized) > 0) as u64
+        }
+        "EvolveChar" => count_chars(&|char| number(&char["evolvePhase"]) >= number_param(mission, 2)),
+        "BoostPotential" => count_chars(&|char| number(&char["potentialRank"]) + 1 >= number_param(mission, 2)),
+        "CharIntimacy" => {
+            let favor = favor_point(&context.favor_table, number_param(mission, 2));
+            count_chars(&|char| number(&char["favorPoint"]) >= favor)
+        }
+        "HasRoom" => room_count(
+            context.building,
+            &context.building_table,
+            param(mission, 3),
+            number_param(mission, 2),
+        ),
+        "DiyComfort" => {
+            let comforts = get_values(&context.building["rooms"]["DORMITORY"])
+                .iter()
+                .map(|room| number(&room["comfort"]))
+                .collect::<Vec<_>>();
+            match param(mission, 0) {
+                "0" => comforts.iter().filter(|comfort| **comfort >= number_param(mission, 2)).count() as u64,
+                _ => comforts.iter().sum(),
+            }
+        }
+        "SetAssistCharList" => user["social"]["assistCharList"]
+            .as_array()
+            .map_or(0, |assists| assists.iter().filter(|assist| !assist.is_null()).count() as u64),
+        "CompleteMainStage" => (number(&user["dungeon"]["stages"][param(mission, 1)]["state"]) >= 2) as u64,
+        _ => 0,
+    }
+}
+
+fn progress_value(entry: &Value) -> u64 {
+    entry["progress"][0]["value"].as_u64().unwrap_or(0)
+}
+
+fn is_finished(entry: &Value) -> bool {
+    progress_value(entry) >= entry["progress"][0]["target"].as_u64().unwrap_or(0)
+}
+
+/// A fresh mission entry, locked behind any missions it follows.
+fn new_mission(mission: &Value) -> Value {
+    let target = mission_target(mission);
+    let locked = mission["preMissionIds"].as_array().is_some_and(|ids| !ids.is_empty());
+    json!({
+        "state": if locked { MISSION_LOCKED } else { MISSION_ACTIVE },
+        "progress": [{ "target": target, "value": 0 }]
+    })
+}
+
+fn group_missions(mission_table: &Value, group_id: &str) -> Vec<(String, Value)> {
+    mission_table["missionGroups"][group_id]["missionIds"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|id| id.as_str())
+        .map(|id| (id.to_string(), new_mission(&mission_table["missions"][id])))
+        .collect()
+}
+
+fn in_period(start: &Value, end: &Value, now: u64) -> bool {
+    let now = now as i64;
+    let start = start.as_i64().unwrap_or(-1);
+    let end = end.as_i64().unwrap_or(-1);
+    (start < 0 || start <= now) && (end < 0 || now <= end)
+}
+
+/// Starts today's daily missions from the mission group scheduled for `weekday`, with their activity rewards.
+fn reset_daily(user: &mut Value, mission_table: &Value, now: u64, weekday: u64) {
+    let period = mission_table["dailyMissionPeriodInfo"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|info| in_period(&info["startTime"], &info["endTime"], now))
+        .flat_map(|info| info["periodList"].as_array().into_iter().flatten())
+        .find(|period| period["period"].as_array().is_some_and(|days| days.contains(&json!(weekday))))
+        .cloned()
+        .unwrap_or(Value::Null);
+
+    let missions: Map<_, _> = group_missions(mission_table, period["missionGroupId"].as_str().unwrap_or(""))
+        .into_iter()
+        .collect();
+    let rewards: Map<_, _> = get_values(&mission_table["periodicalRewards"])
+        .iter()
+        .filter(|reward| reward["groupId"] == period["rewardGroupId"])
+        .map(|reward| (reward["id"].as_str().unwrap().to_string(), json!(0)))
+        .collect();
+    let mission = &mut user["mission"];
+    mission["missions"]["DAILY"] = json!(missions);
+    mission["missionRewards"]["dailyPoint"] = json!(0);
+    mission["missionRewards"]["rewards"]["DAILY"] = json!(rewards);
+}
+
+/// Starts this week's weekly missions and activity rewards.
+fn reset_weekly(user: &mut Value, mission_table: &Value, now: u64) {
+    let missions: Map<_, _> = get_values(&mission_table["missionGroups"])
+        .iter()
+        .filter(|group| group["type"] == "WEEKLY" && in_period(&group["startTs"], &group["endTs"], now))
+        .flat_map(|group| group_missions(mission_table, group["id"].as_str().unwrap()))
+        .collect();
+    let rewards: Map<_, _> = get_values(&mission_table["weeklyRewards"])
+        .iter()
+        .filter(|reward| in_period(&reward["beginTime"], &reward["endTime"], now))
+        .map(|reward| (reward["id"].as_str().unwrap().to_string(), json!(0)))
+        .collect();
+    let mission = &mut user["mission"];
+    mission["missions"]["WEEKLY"] = json!(missions);
+    mission["missionRewards"]["weeklyPoint"] = json!(0);
+    mission["missionRewards"]["rewards"]["WEEKLY"] = json!(rewards);
+}
+
+/// Resets daily and weekly missions past their boundary, and returns whether any were.
+fn reset_missions(user: &mut Value, mission_table: &Value) -> bool {
+    let now = time();
+    let (day_start, week_start) = (day_start(now), week_start(now));
+
+    let mut resets = read_json(MISSION_JSON_PATH);
+    let missions = &user["mission"]["missions"];
+    let reset_daily_missions = resets["dailyResetTs"].as_u64().unwrap_or(0) < day_start || !missions["DAILY"].is_object();
+    let reset_weekly_missions = resets["weeklyResetTs"].as_u64().unwrap_or(0) < week_start || !missions["WEEKLY"].is_object();
+    if reset_daily_missions {
+        reset_daily(user, mission_table, now, weekday(now) + 1);
+        resets["dailyResetTs"] = json!(now);
+    }
+    if reset_weekly_missions {
+        reset_weekly(user, mission_table, now);
+        resets["weeklyResetTs"] = json!(now);
+    }
+    if reset_daily_missions || reset_weekly_missions {
+        write_json(MISSION_JSON_PATH, resets);
+    }
+    reset_daily_missions || reset_weekly_missions
+}
+
+fn is_confirmed(user: &Value, mission_table: &Value, mission_id: &str) -> bool {
+    let mission_type = mission_table["missions"][mission_id]["type"].as_str().unwrap_or("");
+    user["mission"]["missions"][mission_type][mission_id]["state"] == MISSION_CONFIRMED
+}
+
+fn group_finished(user: &Value, mission_table: &Value, group_id: &str) -> bool {
+    mission_table["missionGroups"][group_id]["missionIds"]
+        .as_array()
+        .is_some_and(|ids| ids.iter().all(|id| is_confirmed(user, mission_table, id.as_str().unwrap_or(""))))
+}
+
+/// Opens guide and main line groups whose previous group is done, and unlocks missions whose previous missions are confirmed.
+fn unlock_missions(user: &mut Value, mission_table: &Value) -> bool {
+    let mut changed = false;
+    for group in get_values(&mission_table["missionGroups"]) {
+        let group_type = group["type"].as_str().unwrap_or("");
+        let open = match group["preMissionGroup"].as_str() {
+            Some(pre_group) => group_finished(user, mission_table, pre_group),
+            None => true,
+        };
+        if !PROGRESSION_TYPES.contains(&group_type) || !open {
+            continue;
+        }
+        for (id, entry) in group_missions(mission_table, group["id"].as_str().unwrap()) {
+            if user["mission"]["missions"][group_type][&id].is_null() {
+                user["mission"]["missions"][group_type][&id] = entry;
+                changed = true;
+            }
+        }
+    }
+
+    for mission_type in MISSION_TYPES {
+        for id in get_keys(&user["mission"]["missions"][mission_type]) {
+            let pre_missions = &mission_table["missions"][&id]["preMissionIds"];
+            let unlocked = pre_missions
+                .as_array()
+                .into_iter()
+                .flatten()
+                .all(|pre_id| is_confirmed(user, mission_table, pre_id.as_str().unwrap_or("")));
+            let entry = &mut user["mission"]["missions"][mission_type][&id];
+            if entry["state"] == MISSION_LOCKED && unlocked {
+                entry["state"] = json!(MISSION_ACTIVE);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Counts `events` and rereads save-based progress on every unconfirmed mission, returning whether any moved.
+fn advance_missions(user: &mut Value, context: &MissionContext, events: &[MissionEvent]) -> bool {
+    let mut updates = Vec::new();
+    for mission_type in MISSION_TYPES {
+        for (id, entry) in user["mission"]["missions"][mission_type].as_object().into_iter().flatten() {
+            let mission = &context.mission_table["missions"][id];
+            if entry["state"] == MISSION_CONFIRMED || mission.is_null() {
+                continue;
+            }
+            let value = match tracking(mission) {
+                Tracking::Event => progress_value(entry) + events.iter().map(|event| event_progress(mission, event)).sum::<u64>(),
+                Tracking::State => state_progress(mission, user, context),
+                Tracking::Untracked => continue,
+            };
+            let value = value.min(mission_target(mission));
+            if value != progress_value(entry) {
+                updates.push((mission_type, id.clone(), value));
+            }
+        }
+    }
+    for (mission_type, id, value) in &updates {
+        user["mission"]["missions"][mission_type][id]["progress"][0]["value"] = json!(value);
+    }
+    !updates.is_empty()
+}
+
+/// Resets, unlocks and advances the player's missions against `building`, adding them to `modified` if anything changed.
+pub async fn update_missions(user: &mut Value, building: &Value, events: &[MissionEvent<'_>], modified: &mut Value) {
+    let context = MissionContext::load(building).await;
+    let reset = reset_missions(user, &context.mission_table);
+    let unlocked = unlock_missions(user, &context.mission_table);
+    let advanced = advance_missions(user, &context, events);
+    if reset || unlocked || advanced {
+        modified["mission"] = user["mission"].clone();
+    }
+}
+
+/// Feeds `events` to the player's missions.
+pub async fn track_missions(user: &mut Value, events: &[MissionEvent<'_>], modified: &mut Value) {
+    let building = read_json(BUILDING_JSON_PATH);
+    update_missions(user, &building, events, modified).await;
+}
+
+/// Adds a mission's activity points and grants every activity reward they now reach.
+async fn add_periodical_points(user: &mut Value, mission_table: &Value, mission_type: &str, points: u64, modified: &mut Value) -> Vec<Value> {
+    let (point_field, reward_table) = match mission_type {
+        "DAILY" => ("dailyPoint", "periodicalRewards"),
+        "WEEKLY" => ("weeklyPoint", "weeklyRewards"),
+        _ => return Vec::new(),
+    };
+    let mission_rewards = &mut user["mission"]["missionRewards"];
+    let points = mission_rewards[point_field].as_u64().unwrap_or(0) + points;
+    mission_rewards[point_field] = json!(points);
+
+    let mut rewards: Vec<Value> = get_keys(&mission_rewards["rewards"][mission_type])
+        .iter()
+        .map(|id| mission_table[reward_table][id].clone())
+        .collect();
+    rewards.sort_by_key(|reward| reward["sortIndex"].as_u64().unwrap_or(0));
+
+    let mut items = Vec::new();
+    let mut cost = 0;
+    for reward in rewards {
+        cost += reward["periodicalPointCost"].as_u64().unwrap_or(0);
+        let claimed = &mut user["mission"]["missionRewards"]["rewards"][mission_type][reward["id"].as_str().unwrap()];
+        if points < cost || *claimed != 0 {
+            continue;
+        }
+        *claimed = json!(1);
+        items.extend(grant_items(user, reward["rewards"].as_array().unwrap(), modified).await);
+    }
+    items
+}
+
+/// Claims a finished mission's rewards and activity points, or returns `None` if it can't be claimed.
+async fn confirm_mission(user: &mut Value, mission_table: &Value, mission_id: &str, modified: &mut Value) -> Option<Vec<Value>> {
+    let mission = &mission_table["missions"][mission_id];
+    let mission_type = mission["type"].as_str()?;
+    let entry = &mut user["mission"]["missions"][mission_type][mission_id];
+    if entry["state"] != MISSION_ACTIVE || !is_finished(entry) {
+        return None;
+    }
+    entry["state"] = json!(MISSION_CONFIRMED);
+
+    let rewards = mission["rewards"].as_array().cloned().unwrap_or_default();
+    let mut items = grant_items(user, &rewards, modified).await;
+    let points = mission["periodicalPoint"].as_u64().unwrap_or(0);
+    if points > 0 {
+        items.extend(add_periodical_points(user, mission_table, mission_type, points, modified).await);
+    }
+    Some(items)
+}
+
+fn mission_response(user_data: Value, items: Vec<Value>, mut modified: Value) -> JSON {
+    modified["mission"] = user_data["user"]["mission"].clone();
+    write_json(USER_JSON_PATH, user_data);
+    Json(json!({
+        "items": items,
+        "playerDataDelta": {
+            "modified": modified,
+            "deleted": {}
+        }
+    }))
+}
+
+pub async fn mission_confirm_mission(Json(payload): JSON) -> JSON {
+    let mission_id = payload["missionId"].as_str().unwrap_or("");
+    let mission_table = update_data(MISSION_TABLE_URL).await;
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let mut modified = json!({});
+    track_missions(&mut user_data["user"], &[], &mut modified).await;
+    let Some(items) = confirm_mission(&mut user_data["user"], &mission_table, mission_id, &mut modified).await else {
+        return error_result();
+    };
+    unlock_missions(&mut user_data["user"], &mission_table);
+    mission_response(user_data, items, modified)
+}
+
+/// Claims every finished mission of a type, including those the claims unlock along the way.
+pub async fn mission_auto_confirm_missions(Json(payload): JSON) -> JSON {
+    let mission_type = payload["type"].as_str().unwrap_or("");
+    let mission_table = update_data(MISSION_TABLE_URL).await;
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let mut modified = json!({});
+    track_missions(&mut user_data["user"], &[], &mut modified).await;
+    let mut items = Vec::new();
+    loop {
+        let finished: Vec<String> = user_data["user"]["mission"]["missions"][mission_type]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, entry)| entry["state"] == MISSION_ACTIVE && is_finished(entry))
+            .map(|(id, _)| id.clone())
+            .collect();
+        if finished.is_empty() {
+            break;
+        }
+        for mission_id in finished {
+            items.extend(
+                confirm_mission(&mut user_data["user"], &mission_table, &mission_id, &mut modified)
+                    .await
+                    .unwrap_or_default(),
+            );
+        }
+        unlock_missions(&mut user_data["user"], &mission_table);
+    }
+    mission_response(user_data, items, modified)
+}
+
+/// Claims a mission group's own rewards once all of its missions are confirmed.
+pub async fn mission_confirm_mission_group(Json(payload): JSON) -> JSON {
+    let group_id = payload["missionGroupId"].as_str().unwrap_or("");
+    let mission_table = update_data(MISSION_TABLE_URL).await;
+    let group = &mission_table["missionGroups"][group_id];
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let claimed = &user_data["user"]["mission"]["missionGroups"][group_id];
+    if group.is_null() || *claimed == 1 || !group_finished(&user_data["user"], &mission_table, group_id) {
+        return error_result();
+    }
+    user_data["user"]["mission"]["missionGroups"][group_id] = json!(1);
+
+    let mut modified = json!({});
+    let rewards = group["rewards"].as_array().cloned().unwrap_or_default();
+    let items = grant_items(&mut user_data["user"], &rewards, &mut modified).await;
+    mission_response(user_data, items, modified)
+}
@@ -1,17 +1,29 @@
 pub mod account;
 pub mod background;
+pub mod battle_stats;
 pub mod building;
+pub mod building_sim;
 pub mod campaignv2;
 pub mod char_manager;
+pub mod check_in;
 pub mod crisis_manager;
 pub mod deep_sea;
+pub mod equipment;
+pub mod furniture;
 pub mod gacha;
+pub mod medal;
+pub mod meeting;
+pub mod mission;
 pub mod online;
+pub mod players;
+pub mod profile;
 pub mod quest_manager;
+pub mod replay;
 pub mod rlv2;
 pub mod sandboxv2;
 pub mod shop;
 pub mod social;
+pub mod squad;
 pub mod story;
 pub mod tower;
 
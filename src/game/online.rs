@@ -1,8 +1,16 @@
-use crate::utils::json::JSON;
+use crate::{
+    constants::user::USER_JSON_PATH,
+    utils::json::{read_json, JSON},
+};
 use axum::Json;
 use serde_json::json;
 
+use super::players::publish_player;
+
+/// Keeps the local player shown as online to the rest of the player directory.
 pub async fn online_v1_ping() -> JSON {
+    let user_data = read_json(USER_JSON_PATH);
+    publish_player(&user_data["user"]);
     Json(json!({
         "alertTime": 600,
         "interval": 3590,
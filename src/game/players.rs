@@ -0,0 +1,266 @@
+use std::{
+    fs::{create_dir_all, read_dir},
+    path::Path,
+};
+
+use axum::Json;
+use rand::Rng;
+use serde_json::{json, Value};
+
+use crate::{
+    constants::user::{PLAYERS_PATH, USER_JSON_PATH},
+    core::time,
+    utils::{
+        game::error_result,
+        json::{read_json, write_json, JSON},
+    },
+};
+
+use super::medal::public_medal_board;
+
+pub const FRIEND_NUM_LIMIT: usize = 50;
+/// How long a player counts as online after a ping, matching the ping interval the server hands out.
+const ONLINE_SECONDS: u64 = 3600;
+
+/// Fields of `status` other players can see.
+const PUBLIC_STATUS_FIELDS: [&str; 7] = [
+    "nickName",
+    "nickNumber",
+    "level",
+    "avatarId",
+    "avatar",
+    "secretary",
+    "secretarySkinId",
+];
+
+/// The uid of the player whose save is `data/user/user.json`.
+pub fn local_uid(user: &Value) -> String {
+    match &user["status"]["uid"] {
+        Value::String(uid) => uid.clone(),
+        Value::Null => "1".to_string(),
+        uid => uid.to_string(),
+    }
+}
+
+/// The record file for `uid`, or `None` if it is not a numeric uid.
+fn player_path(uid: &str) -> Option<String> {
+    (!uid.is_empty() && uid.bytes().all(|c| c.is_ascii_digit())).then(|| format!("{PLAYERS_PATH}{uid}.json"))
+}
+
+/// A player's record in the local player directory, if they have one.
+pub fn read_player(uid: &str) -> Option<Value> {
+    let path = player_path(uid)?;
+    Path::new(&path).exists().then(|| read_json(&path))
+}
+
+pub fn write_player(player: &Value) {
+    let Some(path) = player["uid"].as_str().and_then(player_path) else {
+        return;
+    };
+    create_dir_all(PLAYERS_PATH).unwrap();
+    write_json(&path, player);
+}
+
+/// Every player the server knows about, the local one included.
+pub fn list_players() -> Vec<Value> {
+    let Ok(entries) = read_dir(PLAYERS_PATH) else {
+        return Vec::new();
+    };
+    let mut players: Vec<Value> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .map(|path| read_json(path.to_str().unwrap()))
+        .collect();
+    players.sort_by_key(|player| player["uid"].as_str().unwrap_or("").to_string());
+    players
+}
+
+fn new_player(uid: &str) -> Value {
+    json!({
+        "uid": uid,
+        "clueInbox": [],
+        "pendingCredit": 0,
+        "visitors": [],
+        "friends": [],
+        "friendRequests": [],
+        "friendAlias": {},
+        "assistCharList": []
+    })
+}
+
+/// The uids in one of a record's uid lists, such as `friends` or `friendRequests`.
+pub fn uid_list(player: &Value, field: &str) -> Vec<String> {
+    player[field]
+        .as_array()
+        .unwrap_or(&Vec::new())
+        .iter()
+        .filter_map(|uid| uid.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Adds or removes `uid` in one of a record's uid lists.
+pub fn set_in_uid_list(player: &mut Value, field: &str, uid: &str, present: bool) {
+    let mut uids = uid_list(player, field);
+    uids.retain(|listed| listed != uid);
+    if present {
+        uids.push(uid.to_string());
+    }
+    player[field] = json!(uids);
+}
+
+/// Whether the player has pinged the server within the last ping interval.
+pub fn is_online(player: &Value) -> bool {
+    player["lastOnlineTime"].as_u64().unwrap_or(0) + ONLINE_SECONDS >= time()
+}
+
+/// An operator as other players see it in support selection.
+fn assist_char(char: &Value, skill_index: &Value, current_equip: &Value) -> Value {
+    let tmpl = match char["currentTmpl"].as_str() {
+        Some(tmpl_id) => &char["tmpl"][tmpl_id],
+        None => char,
+    };
+    json!({
+        "charId": char["charId"],
+        "skinId": tmpl.get("skinId").unwrap_or(&char["skin"]),
+        "skills": tmpl["skills"],
+        "mainSkillLvl": char["mainSkillLvl"],
+        "skillIndex": skill_index,
+        "evolvePhase": char["evolvePhase"],
+        "favorPoint": char["favorPoint"],
+        "potentialRank": char["potentialRank"],
+        "level": char["level"],
+        "crisisRecord": {},
+        "currentTmpl": char["currentTmpl"],
+        "currentEquip": current_equip,
+        "equip": tmpl["equip"]
+    })
+}
+
+/// Refreshes the local player's record from their save, so other players see them as they are now.
+pub fn publish_player(user: &Value) -> Value {
+    let uid = local_uid(user);
+    let mut player = read_player(&uid).unwrap_or_else(|| new_player(&uid));
+    for field in PUBLIC_STATUS_FIELDS {
+        player[field] = user["status"][field].clone();
+    }
+    let mut assist_chars = Vec::new();
+    let mut selection = Vec::new();
+    for slot in user["social"]["assistCharList"].as_array().unwrap_or(&Vec::new()) {
+        let char = &user["troop"]["chars"][slot["charInstId"].to_string()];
+        if slot.is_object() && char.is_object() {
+            assist_chars.push(assist_char(char, &slot["skillIndex"], &slot["currentEquip"]));
+            let mut slot = slot.clone();
+            slot["charId"] = char["charId"].clone();
+            selection.push(slot);
+        } else {
+            selection.push(Value::Null);
+        }
+    }
+    player["assistCharList"] = json!(assist_chars);
+    player["assistSelection"] = json!(selection);
+    player["resume"] = user["status"]["resume"].clone();
+    player["nameCardStyle"] = json!({
+        "componentOrder": user["nameCardStyle"]["componentOrder"],
+        "skin": user["nameCardStyle"]["skin"]["selected"]
+    });
+    player["medalBoard"] = public_medal_board(user);
+    player["lastOnlineTime"] = json!(time());
+    write_player(&player);
+    player
+}
+
+/// The local player's support selection as saved in their record, matched by `charId` onto a regenerated troop.
+/// Slots whose operator is no longer in the troop are left empty.
+pub fn saved_assist_selection(user: &Value) -> Option<Value> {
+    let player = read_player(&local_uid(user))?;
+    let selection = player["assistSelection"].as_array()?;
+    let chars = user["troop"]["chars"].as_object()?;
+    let mut restored = Vec::new();
+    for slot in selection {
+        let inst_id = chars
+            .iter()
+            .find(|(_, char)| !slot["charId"].is_null() && char["charId"] == slot["charId"]);
+        restored.push(match inst_id {
+            Some((inst_id, _)) => json!({
+                "charInstId": inst_id.parse::<u64>().unwrap(),
+                "skillIndex": slot["skillIndex"],
+                "currentEquip": slot["currentEquip"]
+            }),
+            None => Value::Null,
+        });
+    }
+    Some(json!(restored))
+}
+
+/// A player as shown in search results, friend lists and friend requests.
+pub fn public_profile(player: &Value) -> Value {
+    json!({
+        "uid": player["uid"],
+        "nickName": player["nickName"],
+        "nickNumber": player["nickNumber"],
+        "level": player["level"],
+        "avatarId": player["avatarId"],
+        "avatar": player["avatar"],
+        "secretary": player["secretary"],
+        "secretarySkinId": player["secretarySkinId"],
+        "assistCharList": player["assistCharList"],
+        "lastOnlineTime": player["lastOnlineTime"],
+        "isOnline": is_online(player),
+        "friendNumLimit": FRIEND_NUM_LIMIT,
+        "serverName": "泰拉",
+        "resume": player["resume"],
+        "componentOrder": player["nameCardStyle"]["componentOrder"],
+        "medalBoard": if player["medalBoard"].is_object() {
+            player["medalBoard"].clone()
+        } else {
+            public_medal_board(&Value::Null)
+        },
+        "skin": {
+            "selected": player["nameCardStyle"]["skin"].as_str().unwrap_or("nc_rhodes_default"),
+            "state": {}
+        }
+    })
+}
+
+/// Adds a player to the local directory, so social features can be tried without a second save.
+pub async fn players_add(Json(payload): JSON) -> JSON {
+    let Some(nick_name) = payload["nickName"].as_str() else {
+        return error_result();
+    };
+    let user_data = read_json(USER_JSON_PATH);
+    publish_player(&user_data["user"]);
+
+    let mut rng = rand::thread_rng();
+    let mut uid = rng.gen_range(10000000..100000000).to_string();
+    while read_player(&uid).is_some() {
+        uid = rng.gen_range(10000000..100000000).to_string();
+    }
+
+    let mut player = new_player(&uid);
+    player["nickName"] = json!(nick_name);
+    player["nickNumber"] = json!(format!("{:04}", rng.gen_range(0..10000)));
+    player["level"] = json!(payload["level"].as_u64().unwrap_or(1));
+    player["avatarId"] = json!("0");
+    player["avatar"] = json!({
+        "type": "ASSISTANT",
+        "id": "char_002_amiya#1"
+    });
+    player["secretary"] = json!("char_002_amiya");
+    player["secretarySkinId"] = json!("char_002_amiya#1");
+    player["lastOnlineTime"] = json!(0);
+    // Added players have no troop, so their supports are given as ready-made entries.
+    player["assistCharList"] = json!(payload["assistCharList"].as_array().cloned().unwrap_or_default());
+    write_player(&player);
+
+    Json(json!({
+        "result": 0,
+        "player": player
+    }))
+}
+
+pub async fn players_list() -> JSON {
+    Json(json!({
+        "players": list_players()
+    }))
+}
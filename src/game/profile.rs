@@ -0,0 +1,534 @@
+use serde_json::{json, Value};
+
+use crate::{
+    constants::{
+        config::PROFILE_PATH,
+        url::{BATTLEEQUIP_TABLE_URL, CHARACTER_TABLE_URL, CHAR_PATCH_TABLE_URL, EQUIP_TABLE_URL, GAMEDATA_CONST_URL, SKIN_TABLE_URL},
+    },
+    utils::{
+        comp::min,
+        game::{default_skin, phase_index, update_data},
+        json::{get_keys, get_values, read_json},
+    },
+};
+
+use super::equipment::{complete_owned_equip_missions, unlocked_equips};
+
+const STAGE_STATE_PASS: i64 = 2;
+const STAGE_STATE_COMPLETE: i64 = 3;
+
+/// How `syncData` builds the account, picked with `userConfig.profile`.
+pub enum Profile {
+    /// Every operator, skin, stage and story unlocked and maxed out.
+    Maxed,
+    /// A new account: the starter operators, tutorial flags and no progress.
+    Fresh,
+    /// The operators and progress listed in the manifest at `config/profile.json`.
+    Custom(Value),
+}
+
+impl Profile {
+    pub fn from_config(config: &Value) -> Self {
+        match config["userConfig"]["profile"].as_str() {
+            Some("fresh") => Profile::Fresh,
+            Some("custom") => Profile::Custom(read_json(PROFILE_PATH)),
+            _ => Profile::Maxed,
+        }
+    }
+
+    /// The `userConfig.profile` value, kept in the save to tell which profile built it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Maxed => "maxed",
+            Profile::Fresh => "fresh",
+            Profile::Custom(_) => "custom",
+        }
+    }
+}
+
+/// The manifest a fresh account corresponds to.
+async fn fresh_manifest() -> Value {
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+    let mut chars = json!({});
+    for char_id in const_table["initCharIdList"].as_array().unwrap() {
+        chars[char_id.as_str().unwrap()] = json!({});
+    }
+    json!({
+        "chars": chars,
+        "status": {
+            "level": 1,
+            "exp": 0,
+            "gold": const_table["initPlayerGold"],
+            "diamondShard": const_table["initPlayerDiamondShard"]
+        }
+    })
+}
+
+/// Locks the skills the operator can't have yet and returns the highest unlocked skill index, or `-1`.
+fn reset_skills(skills: &mut Value, char_data: &Value, evolve_phase: usize) -> i64 {
+    let mut default_skill_index = -1;
+    let Some(skills) = skills.as_array_mut() else {
+        return default_skill_index;
+    };
+    for (index, skill) in skills.iter_mut().enumerate() {
+        let unlocked = phase_index(&char_data["skills"][index]["unlockCond"]["phase"]) <= evolve_phase as i64;
+        skill["unlock"] = json!(unlocked as u8);
+        skill["completeUpgradeTime"] = json!(-1);
+        if unlocked {
+            default_skill_index = index as i64;
+        }
+    }
+    default_skill_index
+}
+
+/// Sets skill masteries from `levels`, a level for every skill or a list per skill, keeping those it leaves out.
+/// Only unlocked skills that have masteries can have one, and only at elite 2 with skill level 7.
+fn apply_masteries(skills: &mut Value, char_data: &Value, evolve_phase: usize, main_skill_lvl: u64, levels: &Value) {
+    for (index, skill) in skills.as_array_mut().into_iter().flatten().enumerate() {
+        let level = match levels {
+            Value::Array(levels) => levels.get(index).and_then(Value::as_u64),
+            level => level.as_u64(),
+        };
+        let level = level.or(skill["specializeLevel"].as_u64()).unwrap_or(0);
+        let specializable = !char_data["skills"][index]["levelUpCostCond"]
+            .as_array()
+            .map(Vec::is_empty)
+            .unwrap_or(true);
+        let allowed = skill["unlock"] == 1 && specializable && evolve_phase == 2 && main_skill_lvl == 7;
+        skill["specializeLevel"] = json!(if allowed { min(level, 3) } else { 0 });
+    }
+}
+
+/// Whether the stages a patch template (e.g. Amiya's other forms) unlocks with are cleared, completely where it asks for that.
+pub fn tmpl_conds_met(patch_table: &Value, stages: &Value, tmpl_id: &str) -> bool {
+    patch_table["unlockConds"][tmpl_id]["conds"]
+        .as_array()
+        .unwrap_or(&Vec::new())
+        .iter()
+        .all(|cond| {
+            let required = if cond["completeState"] == "COMPLETE" {
+                STAGE_STATE_COMPLETE
+            } else {
+                STAGE_STATE_PASS
+            };
+            stages[cond["stageId"].as_str().unwrap()]["state"].as_i64().unwrap_or(0) >= required
+        })
+}
+
+/// Patch templates whose unlock stages haven't been cleared.
+fn locked_tmpls(patch_table: &Value, stages: &Value) -> Vec<String> {
+    get_keys(&patch_table["unlockConds"])
+        .into_iter()
+        .filter(|tmpl_id| !tmpl_conds_met(patch_table, stages, tmpl_id))
+        .collect()
+}
+
+/// Applies an operator entry to one form of an operator: the operator itself, or one of its templates.
+fn apply_form(form: &mut Value, form_id: &str, form_data: &Value, entry: &Value, tables: [&Value; 5], rebuilt: Option<(Value, Value)>) {
+    let [_, _, skin_table, equip_table, battleequip_table] = tables;
+    let evolve_phase = entry["evolvePhase"].as_u64().unwrap_or(0) as usize;
+    let main_skill_lvl = entry["mainSkillLvl"].as_u64().unwrap_or(1);
+    let skin_field = if form.get("skinId").is_some() { "skinId" } else { "skin" };
+
+    if let Some((equip, current_equip)) = rebuilt {
+        form[skin_field] = json!(default_skin(skin_table, form_id, evolve_phase as i64));
+        form["defaultSkillIndex"] = json!(reset_skills(&mut form["skills"], form_data, evolve_phase));
+        form["equip"] = equip;
+        form["currentEquip"] = current_equip;
+    }
+    apply_masteries(
+        &mut form["skills"],
+        form_data,
+        evolve_phase,
+        main_skill_lvl,
+        &entry["skillsSpecializeLevel"],
+    );
+    if let Some(index) = entry["defaultSkillIndex"].as_i64() {
+        if index == -1 || form["skills"][index.max(0) as usize]["unlock"] == 1 {
+            form["defaultSkillIndex"] = json!(index);
+        }
+    }
+
+    // Modules
+    let form_equips = equip_table["charEquip"][form_id].as_array().cloned().unwrap_or_default();
+    for (equip_id, level) in entry["equip"].as_object().cloned().unwrap_or_default() {
+        if !form_equips.contains(&json!(equip_id)) {
+            continue;
+        }
+        match level.as_u64() {
+            Some(level) if level > 0 => {
+                let max_level = battleequip_table[&equip_id]["phases"].as_array().map(Vec::len).unwrap_or(1) as u64;
+                form["equip"][&equip_id] = json!({
+                    "hide": 0,
+                    "locked": 0,
+                    "level": min(level, max_level)
+                });
+            }
+            _ => {
+                if let Some(equips) = form["equip"].as_object_mut() {
+                    equips.remove(&equip_id);
+                }
+            }
+        }
+    }
+    if let Some(current_equip) = entry.get("currentEquip") {
+        if current_equip.is_null() || form_equips.contains(current_equip) {
+            form["currentEquip"] = current_equip.clone();
+        }
+    }
+    if let Some(current_equip) = form["currentEquip"].as_str() {
+        if form["equip"][current_equip].is_null() {
+            form["currentEquip"] = Value::Null;
+        }
+    }
+
+    // Skin
+    if let Some(skin) = entry["skin"].as_str() {
+        let skin_data = &skin_table["charSkins"][skin];
+        let owner = if skin_data["tmplId"].is_null() {
+            &skin_data["charId"]
+        } else {
+            &skin_data["tmplId"]
+        };
+        if owner == form_id {
+            form[skin_field] = json!(skin);
+        }
+    }
+}
+
+/// Applies a manifest or `charConfig` entry to an operator, changing only the fields it sets, and drops `locked_tmpls`.
+/// A negative `evolvePhase`/`level` means the highest one, `skillsSpecializeLevel` is a level for every skill or a list per skill,
+/// and `equip` maps module ids to levels, where `0` removes the module. Setting `evolvePhase` rebuilds the skills, skin and
+/// modules of the operator and each of its templates for that phase, and overrides apply to every template they fit.
+/// `tables` are the character, patch, skin, equip and battle equip tables.
+pub fn apply_unit(char: &mut Value, entry: &Value, tables: [&Value; 5], locked_tmpls: &[String]) {
+    let [char_table, patch_table, _, equip_table, battleequip_table] = tables;
+    let char_id = char["charId"].as_str().unwrap().to_string();
+    let char_data = &char_table[&char_id];
+
+    // Elite and level
+    let max_phase = char_data["phases"].as_array().unwrap().len() - 1;
+    let rebuild = entry["evolvePhase"].is_i64();
+    if let Some(evolve_phase) = entry["evolvePhase"].as_i64() {
+        char["evolvePhase"] = json!(if evolve_phase < 0 {
+            max_phase
+        } else {
+            min(evolve_phase as usize, max_phase)
+        });
+    }
+    let evolve_phase = char["evolvePhase"].as_u64().unwrap_or(0) as usize;
+    let max_level = char_data["phases"][evolve_phase]["maxLevel"].as_u64().unwrap();
+    let level = match entry["level"].as_i64() {
+        Some(level) if level > 0 => min(level as u64, max_level),
+        Some(_) => max_level,
+        None => min(char["level"].as_u64().unwrap_or(1), max_level),
+    };
+    char["level"] = json!(level);
+    if let Some(potential_rank) = entry["potentialRank"].as_u64() {
+        char["potentialRank"] = json!(min(potential_rank, 5));
+    }
+    if let Some(main_skill_lvl) = entry["mainSkillLvl"].as_u64() {
+        char["mainSkillLvl"] = json!(main_skill_lvl.clamp(1, 7));
+    }
+    for field in ["favorPoint", "voiceLan", "starMark"] {
+        if !entry[field].is_null() {
+            char[field] = entry[field].clone();
+        }
+    }
+
+    // The clamped progress every form is built for, along with the entry's other overrides
+    let mut form_entry = entry.clone();
+    form_entry["evolvePhase"] = json!(evolve_phase);
+    form_entry["mainSkillLvl"] = char["mainSkillLvl"].clone();
+
+    if !char["tmpl"].is_object() {
+        let rebuilt = rebuild.then(|| unlocked_equips(equip_table, battleequip_table, &char_id, char));
+        apply_form(char, &char_id, char_data, &form_entry, tables, rebuilt);
+        return;
+    }
+    for tmpl_id in get_keys(&char["tmpl"]) {
+        if locked_tmpls.contains(&tmpl_id) {
+            char["tmpl"].as_object_mut().unwrap().remove(&tmpl_id);
+            continue;
+        }
+        let tmpl_data = if char_table[&tmpl_id].is_null() {
+            &patch_table["patchChars"][&tmpl_id]
+        } else {
+            &char_table[&tmpl_id]
+        };
+        let rebuilt = rebuild.then(|| unlocked_equips(equip_table, battleequip_table, &tmpl_id, char));
+        apply_form(&mut char["tmpl"][&tmpl_id], &tmpl_id, tmpl_data, &form_entry, tables, rebuilt);
+    }
+    let current_tmpl = char["currentTmpl"].as_str().unwrap_or(&char_id).to_string();
+    let current_tmpl = if char["tmpl"][&current_tmpl].is_null() {
+        char_id
+    } else {
+        current_tmpl
+    };
+    char["currentTmpl"] = json!(current_tmpl);
+    let tmpl = char["tmpl"][&current_tmpl].clone();
+    char["skin"] = tmpl["skinId"].clone();
+    char["defaultSkillIndex"] = tmpl["defaultSkillIndex"].clone();
+    char["equip"] = tmpl["equip"].clone();
+    char["currentEquip"] = tmpl["currentEquip"].clone();
+}
+
+/// Scales a maxed operator down to a manifest entry, where missing progress means a new E0 level 1 operator.
+fn owned_char(mut char: Value, entry: &Value, tables: &[Value; 5], locked_tmpls: &[String]) -> Value {
+    let mut entry = entry.clone();
+    for (field, default) in [
+        ("evolvePhase", 0),
+        ("level", 1),
+        ("potentialRank", 0),
+        ("mainSkillLvl", 1),
+        ("favorPoint", 0),
+        ("skillsSpecializeLevel", 0),
+    ] {
+        if entry[field].is_null() {
+            entry[field] = json!(default);
+        }
+    }
+    char["exp"] = json!(0);
+    let [char_table, patch_table, skin_table, equip_table, battleequip_table] = tables;
+    apply_unit(
+        &mut char,
+        &entry,
+        [char_table, patch_table, skin_table, equip_table, battleequip_table],
+        locked_tmpls,
+    );
+    char
+}
+
+/// Cuts the maxed account down to the profile's operators and progress.
+/// Runs before the previous save's level and items are carried over, so the status it sets only seeds a save new to the profile.
+pub async fn apply_profile(profile: &Profile, user: &mut Value) {
+    let manifest = match profile {
+        Profile::Maxed => return,
+        Profile::Fresh => fresh_manifest().await,
+        Profile::Custom(manifest) => manifest.clone(),
+    };
+
+    // Stages
+    let mut stages = json!({});
+    for stage_id in get_keys(&manifest["stages"]) {
+        if user["dungeon"]["stages"][&stage_id].is_null() {
+            continue;
+        }
+        let state = manifest["stages"][&stage_id].as_i64().unwrap_or(0);
+        let times = (state >= STAGE_STATE_PASS) as u64;
+        stages[&stage_id] = user["dungeon"]["stages"][&stage_id].clone();
+        stages[&stage_id]["state"] = json!(state);
+        stages[&stage_id]["startTimes"] = json!(times);
+        stages[&stage_id]["completeTimes"] = json!(times);
+    }
+    user["dungeon"]["stages"] = stages;
+
+    // Operators
+    let tables = [
+        update_data(CHARACTER_TABLE_URL).await,
+        update_data(CHAR_PATCH_TABLE_URL).await,
+        update_data(SKIN_TABLE_URL).await,
+        update_data(EQUIP_TABLE_URL).await,
+        update_data(BATTLEEQUIP_TABLE_URL).await,
+    ];
+    let locked_tmpls = locked_tmpls(&tables[1], &user["dungeon"]["stages"]);
+    let owned = &manifest["chars"];
+
+    let mut chars = json!({});
+    let mut char_group = json!({});
+    let mut dex_chars = json!({});
+    let mut addons = json!({});
+    for char in get_values(&user["troop"]["chars"]) {
+        let char_id = char["charId"].as_str().unwrap().to_string();
+        if !owned[&char_id].is_object() {
+            continue;
+        }
+        let inst_id = char["instId"].to_string();
+        chars[&inst_id] = owned_char(char, &owned[&char_id], &tables, &locked_tmpls);
+        char_group[&char_id] = json!({"favorPoint": chars[&inst_id]["favorPoint"]});
+        dex_chars[&char_id] = user["dexNav"]["character"][&char_id].clone();
+        if !user["troop"]["addon"][&char_id].is_null() {
+            addons[&char_id] = user["troop"]["addon"][&char_id].clone();
+        }
+    }
+    // Modules the operators start with count as earned.
+    let mut equip_missions = json!({});
+    for char in get_values(&chars) {
+        complete_owned_equip_missions(&mut equip_missions, &tables[3], &char);
+    }
+    user["troop"]["chars"] = chars;
+    user["troop"]["charGroup"] = char_group;
+    user["troop"]["addon"] = addons;
+    user["dexNav"]["character"] = dex_chars;
+    user["equipment"]["missions"] = equip_missions;
+
+    // Skins
+    let mut skins = json!({});
+    for skin_id in manifest["skins"].as_array().unwrap_or(&Vec::new()) {
+        skins[skin_id.as_str().unwrap()] = json!(1);
+    }
+    user["skin"]["characterSkins"] = skins;
+
+    // Story
+    let mut flags = json!({"init": 1});
+    for flag in get_keys(&manifest["flags"]) {
+        flags[&flag] = manifest["flags"][&flag].clone();
+    }
+    user["status"]["flags"] = flags;
+
+    // Retrospective
+    for retro in get_keys(&user["retro"]["block"]) {
+        user["retro"]["block"][&retro] = json!({
+            "locked": 1,
+            "open": 0
+        });
+    }
+    user["retro"]["trail"] = json!({});
+
+    for field in get_keys(&manifest["status"]) {
+        user["status"][&field] = manifest["status"][&field].clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables() -> [Value; 5] {
+        let skills = json!([
+            { "skillId": "skchr_test_1", "unlockCond": { "phase": "PHASE_0" }, "levelUpCostCond": [{}, {}, {}] },
+            { "skillId": "skchr_test_2", "unlockCond": { "phase": "PHASE_1" }, "levelUpCostCond": [{}, {}, {}] },
+            { "skillId": "skchr_test_3", "unlockCond": { "phase": "PHASE_2" }, "levelUpCostCond": [{}, {}, {}] }
+        ]);
+        let char_table = json!({
+            "char_test": {
+                "phases": [{ "maxLevel": 50 }, { "maxLevel": 70 }, { "maxLevel": 90 }],
+                "skills": skills
+            }
+        });
+        let skin_table = json!({
+            "charSkins": {
+                "char_test#1": { "charId": "char_test", "tmplId": null },
+                "char_test#2": { "charId": "char_test", "tmplId": null },
+                "char_test@summer#1": { "charId": "char_test", "tmplId": null }
+            }
+        });
+        let equip_table = json!({
+            "charEquip": { "char_test": ["uniequip_001_test", "uniequip_002_test"] },
+            "equipDict": {
+                "uniequip_001_test": { "unlockEvolvePhase": "PHASE_0", "unlockLevel": 1, "unlockFavorPoint": 0 },
+                "uniequip_002_test": { "unlockEvolvePhase": "PHASE_2", "unlockLevel": 60, "unlockFavorPoint": 0 }
+            }
+        });
+        let battleequip_table = json!({ "uniequip_002_test": { "phases": [{}, {}, {}] } });
+        [char_table, json!({}), skin_table, equip_table, battleequip_table]
+    }
+
+    fn maxed_char() -> Value {
+        json!({
+            "instId": 1,
+            "charId": "char_test",
+            "evolvePhase": 2,
+            "level": 90,
+            "potentialRank": 5,
+            "mainSkillLvl": 7,
+            "favorPoint": 25570,
+            "skin": "char_test#2",
+            "defaultSkillIndex": 2,
+            "skills": [
+                { "skillId": "skchr_test_1", "unlock": 1, "specializeLevel": 3 },
+                { "skillId": "skchr_test_2", "unlock": 1, "specializeLevel": 3 },
+                { "skillId": "skchr_test_3", "unlock": 1, "specializeLevel": 3 }
+            ],
+            "equip": {
+                "uniequip_001_test": { "hide": 0, "locked": 0, "level": 1 },
+                "uniequip_002_test": { "hide": 0, "locked": 0, "level": 3 }
+            },
+            "currentEquip": "uniequip_002_test"
+        })
+    }
+
+    fn apply(char: &mut Value, entry: Value) {
+        let [char_table, patch_table, skin_table, equip_table, battleequip_table] = &tables();
+        apply_unit(
+            char,
+            &entry,
+            [char_table, patch_table, skin_table, equip_table, battleequip_table],
+            &[],
+        );
+    }
+
+    #[test]
+    fn lower_phase_rebuilds_skills_skin_and_modules() {
+        let mut char = maxed_char();
+        apply(&mut char, json!({ "evolvePhase": 1 }));
+        assert_eq!(char["evolvePhase"], 1);
+        assert_eq!(char["level"], 70);
+        assert_eq!(char["skin"], "char_test#1");
+        assert_eq!(char["defaultSkillIndex"], 1);
+        assert_eq!(char["skills"][2]["unlock"], 0);
+        assert!(char["skills"].as_array().unwrap().iter().all(|skill| skill["specializeLevel"] == 0));
+        assert_eq!(
+            char["equip"],
+            json!({ "uniequip_001_test": { "hide": 0, "locked": 0, "level": 1 } })
+        );
+        assert_eq!(char["currentEquip"], "uniequip_001_test");
+    }
+
+    #[test]
+    fn overrides_without_phase_keep_the_rest() {
+        let mut char = maxed_char();
+        apply(
+            &mut char,
+            json!({
+                "level": 120,
+                "skillsSpecializeLevel": [1, 5],
+                "equip": { "uniequip_002_test": 0 },
+                "skin": "char_test@summer#1",
+                "defaultSkillIndex": 0
+            }),
+        );
+        assert_eq!(char["evolvePhase"], 2);
+        assert_eq!(char["level"], 90);
+        assert_eq!(char["skills"][0]["specializeLevel"], 1);
+        assert_eq!(char["skills"][1]["specializeLevel"], 3);
+        assert_eq!(char["skills"][2]["specializeLevel"], 3);
+        assert!(char["equip"]["uniequip_002_test"].is_null());
+        assert_eq!(char["currentEquip"], Value::Null);
+        assert_eq!(char["skin"], "char_test@summer#1");
+        assert_eq!(char["defaultSkillIndex"], 0);
+    }
+
+    #[test]
+    fn masteries_need_skill_level_seven() {
+        let mut char = maxed_char();
+        apply(&mut char, json!({ "mainSkillLvl": 6 }));
+        assert!(char["skills"].as_array().unwrap().iter().all(|skill| skill["specializeLevel"] == 0));
+    }
+
+    #[test]
+    fn new_operator_from_manifest() {
+        let char = owned_char(maxed_char(), &json!({}), &tables(), &[]);
+        assert_eq!(char["evolvePhase"], 0);
+        assert_eq!(char["level"], 1);
+        assert_eq!(char["potentialRank"], 0);
+        assert_eq!(char["mainSkillLvl"], 1);
+        assert_eq!(char["favorPoint"], 0);
+        assert_eq!(char["defaultSkillIndex"], 0);
+        assert_eq!(char["skills"][1]["unlock"], 0);
+    }
+
+    #[test]
+    fn complete_conditions_need_a_complete_clear() {
+        let patch_table = json!({
+            "unlockConds": {
+                "char_test_tmpl": { "conds": [{ "stageId": "main_01-01", "completeState": "COMPLETE" }] }
+            }
+        });
+        let passed = json!({ "main_01-01": { "state": 2 } });
+        let completed = json!({ "main_01-01": { "state": 3 } });
+        assert!(!tmpl_conds_met(&patch_table, &passed, "char_test_tmpl"));
+        assert!(tmpl_conds_met(&patch_table, &completed, "char_test_tmpl"));
+        assert_eq!(locked_tmpls(&patch_table, &passed), vec!["char_test_tmpl".to_string()]);
+    }
+}
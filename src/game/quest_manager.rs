@@ -1,49 +1,322 @@
 pub mod quest {
     use axum::Json;
-    use serde_json::json;
+    use serde_json::{json, Value};
 
     use crate::{
         constants::{
-            config::CONFIG_JSON_PATH,
-            user::{BATTLE_REPLAY_JSON_PATH, USER_JSON_PATH},
+            url::{FAVOR_TABLE_URL, GAMEDATA_CONST_URL, STAGE_TABLE_URL},
+            user::{BATTLE_JSON_PATH, USER_JSON_PATH},
+        },
+        core::time,
+        game::{
+            battle_stats::record_battle,
+            medal::{award_medals, MedalContext},
+            mission::{track_missions, MissionEvent},
+            replay::register_battle,
+            social::assist_credit,
+            squad::save_squad_preset,
         },
         utils::{
-            battle_replay::{decrypt_battle_replay, encrypt_battle_replay},
-            json::{get_keys, read_json, write_json, JSON},
+            ap::{add_ap, cost_ap, infinite_ap, refresh_ap},
+            battle_data::{BattleData, BattleDataDecoder},
+            game::{error_result, update_data},
+            item::grant_items,
+            json::{get_keys, get_map, read_json, write_json, JSON},
+            level::{add_player_exp, add_squad_favor},
         },
     };
+    use rand::Rng;
+    use uuid::Uuid;
+
+    const COMPLETE_STATE_FAILED: i64 = 1;
+    const COMPLETE_STATE_COMPLETE: i64 = 3;
+
+    fn drop_chance(occ_percent: &str) -> f64 {
+        match occ_percent {
+            "ALWAYS" => 1.0,
+            "ALMOST" => 0.9,
+            "USUAL" => 0.6,
+            "OFTEN" => 0.3,
+            "SOMETIMES" => 0.1,
+            _ => 0.0,
+        }
+    }
+
+    /// Rolls the stage's drop table, grouping the results by the battle finish field they're shown in.
+    fn roll_drops(stage: &Value, complete_state: i64, first_clear: bool) -> Value {
+        let mut rng = rand::thread_rng();
+        let mut drops = json!({
+            "rewards": [],
+            "firstRewards": [],
+            "unusualRewards": [],
+            "additionalRewards": [],
+            "furnitureRewards": []
+        });
+
+        for reward in stage["stageDropInfo"]["displayDetailRewards"].as_array().unwrap_or(&Vec::new()) {
+            let field = match reward["dropType"].as_str().unwrap_or("") {
+                "ONCE" if first_clear => "firstRewards",
+                "NORMAL" => "rewards",
+                "COMPLETE" if complete_state >= COMPLETE_STATE_COMPLETE => "rewards",
+                "SPECIAL" => "unusualRewards",
+                "ADDITIONAL" => "additionalRewards",
+                "FURNITURE" => "furnitureRewards",
+                _ => continue,
+            };
+            let chance = if field == "firstRewards" {
+                1.0
+            } else {
+                drop_chance(reward["occPercent"].as_str().unwrap_or(""))
+            };
+            if rng.gen_bool(chance) {
+                drops[field].as_array_mut().unwrap().push(json!({
+                    "id": reward["id"],
+                    "count": reward["count"].as_u64().unwrap_or(1),
+                    "type": reward["type"]
+                }));
+            }
+        }
+
+        let diamond_once_drop = stage["diamondOnceDrop"].as_u64().unwrap_or(0);
+        if first_clear && diamond_once_drop > 0 {
+            drops["firstRewards"].as_array_mut().unwrap().push(json!({
+                "id": "4002",
+                "count": diamond_once_drop,
+                "type": "DIAMOND"
+            }));
+        }
+        drops
+    }
 
     pub async fn quest_battle_start(Json(payload): JSON) -> JSON {
         let stage_id = payload["stageId"].as_str().unwrap();
-        let mut replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
-        replay_data["current"] = json!(stage_id);
-        write_json(BATTLE_REPLAY_JSON_PATH, replay_data);
+
+        let stage_table = update_data(STAGE_TABLE_URL).await;
+        let const_table = update_data(GAMEDATA_CONST_URL).await;
+        let stage = &stage_table["stages"][stage_id];
+
+        let is_practice = payload["usePracticeTicket"].as_i64().unwrap_or(0) == 1;
+        let ap_cost = if is_practice { 0 } else { stage["apCost"].as_u64().unwrap_or(0) };
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        let cleared = user_data["user"]["dungeon"]["stages"][stage_id]["completeTimes"]
+            .as_u64()
+            .unwrap_or(0)
+            > 0;
+        let is_ap_protect = !cleared && stage["apProtectTimes"].as_i64().unwrap_or(0) > 0;
+        // A protected first attempt refunds its whole cost on failure.
+        let ap_fail_return = if is_ap_protect {
+            ap_cost
+        } else {
+            stage["apFailReturn"].as_u64().unwrap_or(0)
+        };
+
+        let status = &mut user_data["user"]["status"];
+        refresh_ap(status, &const_table);
+        if is_practice {
+            let ticket_cost = stage["practiceTicketCost"].as_u64().unwrap_or(1);
+            let tickets = status["practiceTicket"].as_u64().unwrap_or(0);
+            if tickets < ticket_cost {
+                return error_result();
+            }
+            status["practiceTicket"] = json!(tickets - ticket_cost);
+        }
+        if !cost_ap(status, ap_cost) {
+            return error_result();
+        }
+        let battle_id = Uuid::new_v4().to_string();
+        register_battle(&battle_id, stage_id);
+
+        let mut modified = json!({
+            "status": {
+                "ap": status["ap"],
+                "lastApAddTime": status["lastApAddTime"],
+                "practiceTicket": status["practiceTicket"]
+            }
+        });
+        track_missions(&mut user_data["user"], &[MissionEvent::Count("CostAp", ap_cost)], &mut modified).await;
+        write_json(USER_JSON_PATH, user_data);
+
+        write_json(
+            BATTLE_JSON_PATH,
+            json!({
+                "battleId": battle_id,
+                "stageId": stage_id,
+                "apCost": ap_cost,
+                "apFailReturn": ap_fail_return,
+                "isPractice": is_practice,
+                "squad": payload["squad"]["slots"],
+                "assistFriend": payload["assistFriend"],
+                "startTs": time()
+            }),
+        );
+
         Json(json!({
-            "apFailReturn": 0,
-            "battleId": "abcdefgh-1234-5678-a1b2c3d4e5f6",
-            "inApProtectPeriod": false,
-            "isApProtect": 0,
+            "apFailReturn": ap_fail_return,
+            "battleId": battle_id,
+            "inApProtectPeriod": is_ap_protect,
+            "isApProtect": is_ap_protect as u8,
             "notifyPowerScoreNotEnoughIfFailed": false,
             "playerDataDelta": {
-                "modified": {},
+                "modified": modified,
                 "deleted": {}
             },
             "result": 0
         }))
     }
 
-    pub async fn quest_battle_finish() -> JSON {
-        Json(json!({
-            "result":0,
-            "apFailReturn": 0,
-            "expScale": 1.2,
-            "goldScale": 1.2,
-            "rewards": [],
+    /// Takes the battle `battleStart` left pending if the finish is for it, so each paid start settles only once.
+    pub fn take_pending_battle(payload: &Value, battle_data: &BattleData) -> Option<Value> {
+        let battle = read_json(BATTLE_JSON_PATH);
+        let claimed = payload["battleId"].as_str().or_else(|| battle_data.other.get("battleId")?.as_str());
+        let pending = battle["battleId"].as_str().filter(|battle_id| !battle_id.is_empty());
+        if pending.is_none() || claimed != pending {
+            return None;
+        }
+        write_json(
+            BATTLE_JSON_PATH,
+            json!({
+                "battleId": "",
+                "stageId": "",
+                "apCost": 0,
+                "isPractice": false,
+                "startTs": 0
+            }),
+        );
+        Some(battle)
+    }
+
+    pub async fn quest_battle_finish(Json(payload): JSON) -> JSON {
+        let battle_data = payload["data"]
+            .as_str()
+            .and_then(|data| BattleDataDecoder::new().decrypt_battle_data(data.to_string()).ok());
+        let Some(battle_data) = battle_data else {
+            return error_result();
+        };
+        let Some(battle) = take_pending_battle(&payload, &battle_data) else {
+            return error_result();
+        };
+        let complete_state = battle_data.complete_state;
+
+        let is_practice = battle["isPractice"].as_bool().unwrap_or(false);
+        let stage_id = battle["stageId"].as_str().unwrap_or("");
+
+        let stage_table = update_data(STAGE_TABLE_URL).await;
+        let const_table = update_data(GAMEDATA_CONST_URL).await;
+        let favor_table = update_data(FAVOR_TABLE_URL).await;
+        let stage = &stage_table["stages"][stage_id];
+
+        let mut user_data = read_json(USER_JSON_PATH);
+        refresh_ap(&mut user_data["user"]["status"], &const_table);
+
+        let mut ap_fail_return = 0;
+        let mut scale = 1.0;
+        let mut rewards = Vec::new();
+        let mut drop_rewards = json!({
             "firstRewards": [],
-            "unlockStages": [],
             "unusualRewards": [],
             "additionalRewards": [],
-            "furnitureRewards": [],
+            "furnitureRewards": []
+        });
+        let mut modified = json!({});
+        if !is_practice {
+            let (exp, gold) = if complete_state == COMPLETE_STATE_FAILED {
+                ap_fail_return = battle["apFailReturn"].as_u64().unwrap_or(0);
+                if !infinite_ap() {
+                    add_ap(&mut user_data["user"]["status"], ap_fail_return);
+                }
+                (
+                    stage["loseExpGain"].as_u64().unwrap_or(0),
+                    stage["loseGoldGain"].as_u64().unwrap_or(0),
+                )
+            } else {
+                let favor = if complete_state >= COMPLETE_STATE_COMPLETE {
+                    scale = const_table["completeGainBonus"].as_f64().unwrap_or(1.0);
+                    stage["completeFavor"].as_u64().unwrap_or(0)
+                } else {
+                    stage["passFavor"].as_u64().unwrap_or(0)
+                };
+                add_squad_favor(
+                    &mut user_data["user"],
+                    &favor_table,
+                    &battle["squad"],
+                    &battle_data.deployed_chars(),
+                    favor,
+                    &mut modified,
+                );
+                (
+                    (stage["expGain"].as_u64().unwrap_or(0) as f64 * scale) as u64,
+                    (stage["goldGain"].as_u64().unwrap_or(0) as f64 * scale) as u64,
+                )
+            };
+
+            add_player_exp(&mut user_data["user"]["status"], &const_table, exp);
+            let gold_reward = [json!({ "id": "4001", "count": gold, "type": "GOLD" })];
+            rewards = grant_items(&mut user_data["user"], &gold_reward, &mut modified).await;
+
+            if complete_state != COMPLETE_STATE_FAILED {
+                let stage_data = &user_data["user"]["dungeon"]["stages"][stage_id];
+                let first_clear =
+                    complete_state >= COMPLETE_STATE_COMPLETE && stage_data["state"].as_i64().unwrap_or(0) < COMPLETE_STATE_COMPLETE;
+                let state = stage_data["state"].as_i64().unwrap_or(0).max(complete_state);
+                let complete_times = stage_data["completeTimes"].as_u64().unwrap_or(0) + 1;
+
+                let drops = roll_drops(stage, complete_state, first_clear);
+                for (field, items) in get_map(&drops) {
+                    let granted = grant_items(&mut user_data["user"], items.as_array().unwrap(), &mut modified).await;
+                    match field.as_str() {
+                        "rewards" => rewards.extend(granted),
+                        _ => drop_rewards[&field] = json!(granted),
+                    }
+                }
+
+                if let Some(credit) = assist_credit(&battle["assistFriend"]) {
+                    rewards.extend(grant_items(&mut user_data["user"], &[credit], &mut modified).await);
+                }
+
+                let stage_data = &mut user_data["user"]["dungeon"]["stages"][stage_id];
+                stage_data["stageId"] = json!(stage_id);
+                stage_data["state"] = json!(state);
+                stage_data["completeTimes"] = json!(complete_times);
+                modified["dungeon"]["stages"][stage_id] = stage_data.clone();
+            }
+        }
+
+        let status = &user_data["user"]["status"];
+        for field in ["ap", "maxAp", "lastApAddTime", "level", "exp"] {
+            modified["status"][field] = status[field].clone();
+        }
+        record_battle(&battle, &battle_data);
+        let battle_event = MissionEvent::StageClear {
+            stage_id,
+            complete_state,
+            kills: battle_data.kill_cnt,
+            with_assist: !battle["assistFriend"].is_null(),
+            is_practice,
+        };
+        track_missions(&mut user_data["user"], &[battle_event], &mut modified).await;
+        award_medals(
+            &mut user_data["user"],
+            &MedalContext {
+                favor_table: Some(&favor_table),
+                base: None,
+            },
+            &mut modified,
+        )
+        .await;
+        write_json(USER_JSON_PATH, user_data);
+
+        Json(json!({
+            "result":0,
+            "apFailReturn": ap_fail_return,
+            "expScale": scale,
+            "goldScale": scale,
+            "rewards": rewards,
+            "firstRewards": drop_rewards["firstRewards"],
+            "unlockStages": [],
+            "unusualRewards": drop_rewards["unusualRewards"],
+            "additionalRewards": drop_rewards["additionalRewards"],
+            "furnitureRewards": drop_rewards["furnitureRewards"],
             "overrideRewards": [],
             "alert": [],
             "suggestFriend": false,
@@ -56,75 +329,12 @@ pub mod quest {
             "tokenAdd": 0,
             "isTokenMax": false,
             "playerDataDelta": {
-                "modified": {},
+                "modified": modified,
                 "deleted": {}
             }
         }))
     }
 
-    pub async fn quest_save_battle_replay(Json(payload): JSON) -> JSON {
-        let config = read_json(CONFIG_JSON_PATH);
-        let anon = config["battleReplayConfig"]["anonymous"].as_bool().unwrap();
-        let replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
-
-        let char_config = payload["currentCharConfig"].as_str().unwrap();
-        let current = replay_data["current"].as_str().unwrap();
-
-        let encoded_battle_replay = payload["battleReplay"].as_str().unwrap();
-        let mut decoded_battle_replay = decrypt_battle_replay(encoded_battle_replay.to_string()).unwrap();
-
-        if anon {
-            decoded_battle_replay["campaignOnlyVersion"] = json!(0);
-            decoded_battle_replay["timestamp"] = json!("1700000000");
-            decoded_battle_replay["journal"]["metadata"]["saveTime"] = json!("2023-11-15T06:13:20Z");
-        }
-
-        let mut replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
-        if get_keys(&replay_data["saved"]).contains(&char_config.to_string()) {
-            replay_data["saved"][char_config][current] = decoded_battle_replay;
-        } else {
-            replay_data["saved"][char_config] = json!({
-                current: decoded_battle_replay
-            });
-        }
-        write_json(BATTLE_REPLAY_JSON_PATH, replay_data.clone());
-
-        let current = replay_data["current"].as_str().unwrap();
-        Json(json!({
-            "result": 0,
-            "playerDataDelta": {
-                "modified": {
-                    "dungeon": {
-                        "stages": {
-                            current: {
-                                "hasBattleReplay": 1
-                            }
-                        }
-                    }
-                },
-                "deleted": {}
-            }
-        }))
-    }
-
-    pub async fn quest_get_battle_replay(Json(payload): JSON) -> JSON {
-        let stage_id = payload["stageId"].as_str().unwrap();
-
-        let replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
-        let current_char_conf = replay_data["currentCharConfig"].as_str().unwrap();
-
-        let decoded_battle_replay = replay_data["saved"][current_char_conf][stage_id].clone();
-        let encoded_battle_replay = encrypt_battle_replay(decoded_battle_replay).unwrap();
-
-        Json(json!({
-            "battleReplay": encoded_battle_replay,
-            "playerDataDelta": {
-                "deleted": {},
-                "modified": {}
-            }
-        }))
-    }
-
     pub async fn squad_change_name(Json(payload): JSON) -> JSON {
         let mut data = json!({
             "playerDataDelta":{
@@ -143,6 +353,9 @@ pub mod quest {
             data["playerDataDelta"]["modified"]["troop"]["squads"][&squad_id]["name"] = name.clone();
             let mut user_data = read_json(USER_JSON_PATH);
             user_data["user"]["troop"]["squads"][&squad_id]["name"] = name;
+            save_squad_preset(&squad_id, &user_data["user"]);
+            let events = [MissionEvent::Count("ChangeSquadName", 1)];
+            track_missions(&mut user_data["user"], &events, &mut data["playerDataDelta"]["modified"]).await;
             write_json(USER_JSON_PATH, user_data);
         }
 
@@ -167,6 +380,7 @@ pub mod quest {
             data["playerDataDelta"]["modified"]["troop"]["squads"][&squad_id]["slots"] = slots.clone();
             let mut user_data = read_json(USER_JSON_PATH);
             user_data["user"]["troop"]["squads"][&squad_id]["slots"] = slots;
+            save_squad_preset(&squad_id, &user_data["user"]);
             write_json(USER_JSON_PATH, user_data);
         }
 
@@ -337,19 +551,20 @@ pub mod april_fools {
     use axum::Json;
     use serde_json::json;
 
-    use crate::utils::{
-        battle_data::BattleDataDecoder,
-        enumerate,
-        json::{get_keys, JSON},
-    };
+    use crate::utils::{battle_data::BattleDataDecoder, enumerate, game::error_result, json::JSON};
 
     pub async fn act5_fun_battle_finish(Json(payload): JSON) -> JSON {
         let decoder = BattleDataDecoder::new();
-        let battle_data = decoder.decrypt_battle_data(payload["data"].as_str().unwrap().to_string()).unwrap();
+        let Some(battle_data) = payload["data"]
+            .as_str()
+            .and_then(|data| decoder.decrypt_battle_data(data.to_string()).ok())
+        else {
+            return error_result();
+        };
         let mut score = 0;
-        for data in get_keys(&battle_data["battleData"]["stats"]["extraBattleInfo"]) {
+        for (data, value) in battle_data.extra_battle_info() {
             if data.starts_with("SIMPLE,money,") {
-                score = battle_data["battleData"]["stats"]["extraBattleInfo"][data]
+                score = value
                     .as_str()
                     .unwrap()
                     .split(',')
@@ -0,0 +1,301 @@
+use std::fs::{create_dir_all, read_to_string};
+
+use axum::Json;
+use serde_json::{json, Value};
+
+use crate::{
+    constants::{
+        config::CONFIG_JSON_PATH,
+        user::{BATTLE_REPLAY_EXPORT_PATH, BATTLE_REPLAY_JSON_PATH},
+    },
+    utils::{
+        battle_replay::{decode_replay_timeline, decrypt_battle_replay, encrypt_battle_replay},
+        game::error_result,
+        json::{get_keys, read_json, write_json, JSON},
+    },
+};
+
+/// Battles started but not yet saved as replays; older ones are dropped once this is exceeded.
+const MAX_PENDING_BATTLES: usize = 32;
+
+/// Remembers which stage a battle is for, so `saveBattleReplay` can file it by its `battleId`.
+pub fn register_battle(battle_id: &str, stage_id: &str) {
+    let mut replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+    if !replay_data["battles"].is_object() {
+        replay_data["battles"] = json!({});
+    }
+    let battles = replay_data["battles"].as_object_mut().unwrap();
+    battles.insert(battle_id.to_string(), json!(stage_id));
+    while battles.len() > MAX_PENDING_BATTLES {
+        let oldest = battles.keys().next().unwrap().clone();
+        battles.remove(&oldest);
+    }
+    write_json(BATTLE_REPLAY_JSON_PATH, replay_data);
+}
+
+fn replay_delta(stage_id: &str, has_battle_replay: u8) -> Value {
+    json!({
+        "dungeon": {
+            "stages": {
+                stage_id: {
+                    "hasBattleReplay": has_battle_replay
+                }
+            }
+        }
+    })
+}
+
+fn char_config_of(payload: &Value, replay_data: &Value) -> String {
+    payload["currentCharConfig"]
+        .as_str()
+        .or(payload["charConfig"].as_str())
+        .or(replay_data["currentCharConfig"].as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn anonymize(battle_replay: &mut Value) {
+    battle_replay["campaignOnlyVersion"] = json!(0);
+    battle_replay["timestamp"] = json!("1700000000");
+    battle_replay["journal"]["metadata"]["saveTime"] = json!("2023-11-15T06:13:20Z");
+}
+
+pub async fn quest_save_battle_replay(Json(payload): JSON) -> JSON {
+    let config = read_json(CONFIG_JSON_PATH);
+    let anon = config["battleReplayConfig"]["anonymous"].as_bool().unwrap();
+    let mut replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+
+    let char_config = char_config_of(&payload, &replay_data);
+    let encoded_battle_replay = payload["battleReplay"].as_str().unwrap();
+    let mut decoded_battle_replay = decrypt_battle_replay(encoded_battle_replay.to_string()).unwrap();
+
+    let battle_id = payload["battleId"].as_str().unwrap_or("");
+    let stage_id = match replay_data["battles"].as_object_mut().and_then(|battles| battles.remove(battle_id)) {
+        Some(stage_id) => stage_id.as_str().unwrap().to_string(),
+        None => match decoded_battle_replay["journal"]["metadata"]["stageId"].as_str() {
+            Some(stage_id) => stage_id.to_string(),
+            None => return error_result(),
+        },
+    };
+
+    if anon {
+        anonymize(&mut decoded_battle_replay);
+    }
+
+    replay_data["currentCharConfig"] = json!(char_config);
+    replay_data["saved"][&char_config][&stage_id] = decoded_battle_replay;
+    write_json(BATTLE_REPLAY_JSON_PATH, replay_data);
+
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": replay_delta(&stage_id, 1),
+            "deleted": {}
+        }
+    }))
+}
+
+pub async fn quest_get_battle_replay(Json(payload): JSON) -> JSON {
+    let stage_id = payload["stageId"].as_str().unwrap();
+
+    let replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+    let char_config = char_config_of(&payload, &replay_data);
+
+    let decoded_battle_replay = &replay_data["saved"][&char_config][stage_id];
+    if decoded_battle_replay.is_null() {
+        return error_result();
+    }
+    let encoded_battle_replay = encrypt_battle_replay(decoded_battle_replay.clone()).unwrap();
+
+    Json(json!({
+        "battleReplay": encoded_battle_replay,
+        "playerDataDelta": {
+            "deleted": {},
+            "modified": {}
+        }
+    }))
+}
+
+/// Lists every saved replay, optionally only those recorded under one character config.
+pub async fn replay_list(Json(payload): JSON) -> JSON {
+    let replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+
+    let mut replays = Vec::new();
+    for char_config in get_keys(&replay_data["saved"]) {
+        if payload["charConfig"].as_str().is_some_and(|filter| filter != char_config) {
+            continue;
+        }
+        for stage_id in get_keys(&replay_data["saved"][&char_config]) {
+            let metadata = &replay_data["saved"][&char_config][&stage_id]["journal"]["metadata"];
+            replays.push(json!({
+                "charConfig": char_config,
+                "stageId": stage_id,
+                "saveTime": metadata["saveTime"]
+            }));
+        }
+    }
+
+    Json(json!({
+        "result": 0,
+        "currentCharConfig": replay_data["currentCharConfig"],
+        "replays": replays
+    }))
+}
+
+pub async fn replay_delete(Json(payload): JSON) -> JSON {
+    let stage_id = payload["stageId"].as_str().unwrap();
+    let mut replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+    let char_config = char_config_of(&payload, &replay_data);
+
+    let removed = replay_data["saved"][&char_config]
+        .as_object_mut()
+        .and_then(|replays| replays.remove(stage_id))
+        .is_some();
+    if !removed {
+        return error_result();
+    }
+    if replay_data["saved"][&char_config].as_object().unwrap().is_empty() {
+        replay_data["saved"].as_object_mut().unwrap().remove(&char_config);
+    }
+    let is_current = replay_data["currentCharConfig"].as_str() == Some(char_config.as_str());
+    write_json(BATTLE_REPLAY_JSON_PATH, replay_data);
+
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": if is_current { replay_delta(stage_id, 0) } else { json!({}) },
+            "deleted": {}
+        }
+    }))
+}
+
+/// Whether `name` is safe to use as part of an export file name.
+fn valid_file_part(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '#' | '-'))
+}
+
+/// The export file for a stage and squad config, if both are safe to put in a file name.
+fn export_path(stage_id: &str, char_config: &str) -> Option<String> {
+    (valid_file_part(stage_id) && valid_file_part(char_config)).then(|| format!("{BATTLE_REPLAY_EXPORT_PATH}{stage_id}.{char_config}.json"))
+}
+
+/// Resolves an import `path`, given either as a file name or as a full export path, to a file in the export directory.
+fn import_path(path: &str) -> Option<String> {
+    let name = path.strip_prefix(BATTLE_REPLAY_EXPORT_PATH).unwrap_or(path);
+    let (stage_id, char_config) = name.strip_suffix(".json")?.split_once('.')?;
+    export_path(stage_id, char_config)
+}
+
+/// Exports a replay as a standalone file that another server can import.
+pub async fn replay_export(Json(payload): JSON) -> JSON {
+    let stage_id = payload["stageId"].as_str().unwrap();
+    let replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+    let char_config = char_config_of(&payload, &replay_data);
+    let Some(path) = export_path(stage_id, &char_config) else {
+        return error_result();
+    };
+
+    let decoded_battle_replay = &replay_data["saved"][&char_config][stage_id];
+    if decoded_battle_replay.is_null() {
+        return error_result();
+    }
+    let exported = json!({
+        "stageId": stage_id,
+        "charConfig": char_config,
+        "battleReplay": encrypt_battle_replay(decoded_battle_replay.clone()).unwrap()
+    });
+
+    create_dir_all(BATTLE_REPLAY_EXPORT_PATH).unwrap();
+    write_json(&path, &exported);
+
+    Json(json!({
+        "result": 0,
+        "path": path,
+        "replay": exported
+    }))
+}
+
+/// Imports a replay either inline (`replay`) or from a file in the export directory (`path`).
+pub async fn replay_import(Json(payload): JSON) -> JSON {
+    let imported = match payload["path"].as_str() {
+        Some(path) => match import_path(path).and_then(|path| read_to_string(path).ok()) {
+            Some(contents) => serde_json::from_str(&contents).unwrap_or(Value::Null),
+            None => return error_result(),
+        },
+        None => payload["replay"].clone(),
+    };
+    let (Some(stage_id), Some(encoded_battle_replay)) = (imported["stageId"].as_str(), imported["battleReplay"].as_str()) else {
+        return error_result();
+    };
+    let Ok(decoded_battle_replay) = decrypt_battle_replay(encoded_battle_replay.to_string()) else {
+        return error_result();
+    };
+
+    let mut replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+    let char_config = match payload["charConfig"].as_str().or(imported["charConfig"].as_str()) {
+        Some(char_config) => char_config.to_string(),
+        None => char_config_of(&payload, &replay_data),
+    };
+    replay_data["saved"][&char_config][stage_id] = decoded_battle_replay;
+    let is_current = replay_data["currentCharConfig"].as_str() == Some(char_config.as_str());
+    write_json(BATTLE_REPLAY_JSON_PATH, replay_data);
+
+    Json(json!({
+        "result": 0,
+        "charConfig": char_config,
+        "stageId": stage_id,
+        "playerDataDelta": {
+            "modified": if is_current { replay_delta(stage_id, 1) } else { json!({}) },
+            "deleted": {}
+        }
+    }))
+}
+
+/// Decodes a replay's recorded actions, either a saved one (`stageId`) or an encoded one (`battleReplay`).
+pub async fn replay_timeline(Json(payload): JSON) -> JSON {
+    let decoded_battle_replay = match payload["battleReplay"].as_str() {
+        Some(encoded_battle_replay) => match decrypt_battle_replay(encoded_battle_replay.to_string()) {
+            Ok(decoded_battle_replay) => decoded_battle_replay,
+            Err(_) => return error_result(),
+        },
+        None => {
+            let replay_data = read_json(BATTLE_REPLAY_JSON_PATH);
+            let char_config = char_config_of(&payload, &replay_data);
+            replay_data["saved"][&char_config][payload["stageId"].as_str().unwrap_or("")].clone()
+        }
+    };
+    if decoded_battle_replay.is_null() {
+        return error_result();
+    }
+
+    Json(json!({
+        "result": 0,
+        "timeline": decode_replay_timeline(&decoded_battle_replay)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_path_rejects_unsafe_names() {
+        assert_eq!(
+            export_path("main_01-07", "#1").as_deref(),
+            Some(format!("{BATTLE_REPLAY_EXPORT_PATH}main_01-07.#1.json").as_str())
+        );
+        assert_eq!(export_path("../user", "0"), None);
+        assert_eq!(export_path("main_01-07", "a/b"), None);
+        assert_eq!(export_path("", "0"), None);
+    }
+
+    #[test]
+    fn import_path_stays_in_export_directory() {
+        let expected = Some(format!("{BATTLE_REPLAY_EXPORT_PATH}main_01-07.0.json"));
+        assert_eq!(import_path("main_01-07.0.json"), expected);
+        assert_eq!(import_path(&format!("{BATTLE_REPLAY_EXPORT_PATH}main_01-07.0.json")), expected);
+        assert_eq!(import_path("./data/user/user.json"), None);
+        assert_eq!(import_path(&format!("{BATTLE_REPLAY_EXPORT_PATH}../user.json")), None);
+        assert_eq!(import_path("/etc/passwd"), None);
+    }
+}
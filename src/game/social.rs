@@ -1,76 +1,630 @@
-use crate::utils::json::JSON;
 use axum::Json;
 use serde_json::{json, Value};
 
+use crate::{
+    constants::{
+        config::ASSIST_JSON_PATH,
+        url::{BATTLEEQUIP_TABLE_URL, CHARACTER_TABLE_URL, EQUIP_TABLE_URL, MEDAL_TABLE_URL, SKIN_TABLE_URL},
+        user::USER_JSON_PATH,
+    },
+    core::time,
+    utils::{
+        game::{default_skin, error_result, phase_index, update_data},
+        json::{read_json, write_json, JSON},
+    },
+};
+
+use super::{
+    medal::{is_earned, medal_group},
+    mission::track_missions,
+    players::{
+        is_online, list_players, public_profile, publish_player, read_player, set_in_uid_list, uid_list, write_player, FRIEND_NUM_LIMIT,
+    },
+};
+
+/// The uid the configured support units are lent under.
+const SUPPORT_UNIT_UID: &str = "0";
+const FRIEND_ASSIST_CREDIT: u64 = 20;
+const ASSIST_CREDIT: u64 = 10;
+
+/// How the local player relates to another: 0 for none, 1 for a pending request from the local player, 2 for friends.
+fn friend_status(local: &Value, other: &Value) -> u64 {
+    let local_uid = local["uid"].as_str().unwrap_or("");
+    if uid_list(local, "friends")
+        .iter()
+        .any(|uid| uid == other["uid"].as_str().unwrap_or(""))
+    {
+        2
+    } else if uid_list(other, "friendRequests").iter().any(|uid| uid == local_uid) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Whether `query` names the player, as a uid, as `nickName#nickNumber` or as a bare nickname.
+fn matches_query(player: &Value, query: &str) -> bool {
+    let nick_name = player["nickName"].as_str().unwrap_or("");
+    match query.split_once('#') {
+        Some((name, number)) => name == nick_name && number == player["nickNumber"].as_str().unwrap_or(""),
+        None => query == player["uid"].as_str().unwrap_or("") || query == nick_name,
+    }
+}
+
+/// The local player's refreshed record, with every other player in the directory.
+fn open_players() -> (Value, Vec<Value>) {
+    let user_data = read_json(USER_JSON_PATH);
+    let local = publish_player(&user_data["user"]);
+    let others = list_players().into_iter().filter(|player| player["uid"] != local["uid"]).collect();
+    (local, others)
+}
+
+/// Brief entries of the players `getSortListInfo` lists, which the client then resolves by uid.
+fn sort_entries(players: &[Value]) -> Value {
+    json!(players
+        .iter()
+        .map(|player| json!({
+            "uid": player["uid"],
+            "level": player["level"],
+            "infoShare": 0,
+            "recentVisited": 0
+        }))
+        .collect::<Vec<Value>>())
+}
+
+fn social_delta(local: &Value) -> Value {
+    let has_friend_request = !uid_list(local, "friendRequests").is_empty();
+    json!({
+        "modified": {
+            "pushFlags": {
+                "hasFriendRequest": has_friend_request as u64
+            }
+        },
+        "deleted": {}
+    })
+}
+
+/// Makes two players friends, dropping any pending requests between them.
+fn befriend(a: &mut Value, b: &mut Value) {
+    let (a_uid, b_uid) = (a["uid"].as_str().unwrap().to_string(), b["uid"].as_str().unwrap().to_string());
+    set_in_uid_list(a, "friendRequests", &b_uid, false);
+    set_in_uid_list(b, "friendRequests", &a_uid, false);
+    set_in_uid_list(a, "friends", &b_uid, true);
+    set_in_uid_list(b, "friends", &a_uid, true);
+}
+
+fn has_room_for_friend(player: &Value) -> bool {
+    uid_list(player, "friends").len() < FRIEND_NUM_LIMIT
+}
+
 pub async fn social_search_player(Json(payload): JSON) -> JSON {
-    let username = payload["idList"].as_array().unwrap()[0].clone();
-    Json(json!({
-        "players": [
-            {
-                "nickName": username,
-                "nickNumber": "6666",
-                "uid": "66666666",
-                "friendNumLimit": 50,
-                "serverName": "泰拉",
-                "level": 120,
-                "avatarId": "0",
-                "avatar": {},
-                "assistCharList": [
-                    Value::Null
-                ],
-                "lastOnlineTime": 0,
-                "medalBoard": {
-                    "type": "EMPTY",
-                    "custom": Value::Null,
-                    "template": Value::Null
-                },
-                "skin": {
-                    "selected": "nc_rhodes_default",
-                    "state": {}
-                }
+    let (local, others) = open_players();
+    let mut players = Vec::new();
+    let mut friend_status_list = Vec::new();
+    let mut result_id_list = Vec::new();
+    for query in payload["idList"].as_array().into_iter().flatten() {
+        let query = query.as_str().unwrap_or("");
+        for player in others.iter().filter(|player| matches_query(player, query)) {
+            if result_id_list.contains(&player["uid"]) {
+                continue;
             }
-        ],
-        "friendStatusList": [
-            0
-        ],
-        "resultIdList": [
-            "66666666"
-        ],
+            players.push(public_profile(player));
+            friend_status_list.push(friend_status(&local, player));
+            result_id_list.push(player["uid"].clone());
+        }
+    }
+    Json(json!({
+        "players": players,
+        "friendStatusList": friend_status_list,
+        "resultIdList": result_id_list,
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+/// Lists search results (type 0, by `param.nickName` and `param.nickNumber`), friends (type 1) or friend requests (type 2).
+pub async fn social_get_sort_list_info(Json(payload): JSON) -> JSON {
+    let (local, others) = open_players();
+    let players: Vec<Value> = match payload["type"].as_u64().unwrap_or(0) {
+        0 => {
+            let nick_name = payload["param"]["nickName"].as_str().unwrap_or("");
+            let nick_number = payload["param"]["nickNumber"].as_str().unwrap_or("");
+            others
+                .into_iter()
+                .filter(|player| {
+                    player["nickName"].as_str().unwrap_or("").contains(nick_name)
+                        && (nick_number.is_empty() || player["nickNumber"] == nick_number)
+                })
+                .collect()
+        }
+        1 => {
+            let friends = uid_list(&local, "friends");
+            others
+                .into_iter()
+                .filter(|player| friends.contains(&player["uid"].as_str().unwrap_or("").to_string()))
+                .collect()
+        }
+        2 => uid_list(&local, "friendRequests")
+            .iter()
+            .filter_map(|uid| read_player(uid))
+            .collect(),
+        _ => Vec::new(),
+    };
+    Json(json!({
+        "result": sort_entries(&players),
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+pub async fn social_get_friend_list(Json(payload): JSON) -> JSON {
+    let (local, _) = open_players();
+    let friends = uid_list(&local, "friends");
+    let mut friend_list = Vec::new();
+    let mut friend_alias = Vec::new();
+    let mut result_id_list = Vec::new();
+    for uid in payload["idList"].as_array().into_iter().flatten() {
+        let uid = uid.as_str().unwrap_or("");
+        let Some(friend) = read_player(uid).filter(|_| friends.iter().any(|friend| friend == uid)) else {
+            continue;
+        };
+        friend_list.push(public_profile(&friend));
+        friend_alias.push(local["friendAlias"][uid].clone());
+        result_id_list.push(uid);
+    }
+    Json(json!({
+        "friends": friend_list,
+        "friendAlias": friend_alias,
+        "resultIdList": result_id_list,
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+pub async fn social_get_friend_request_list() -> JSON {
+    let (local, _) = open_players();
+    let request_list: Vec<Value> = uid_list(&local, "friendRequests")
+        .iter()
+        .filter_map(|uid| read_player(uid))
+        .map(|player| public_profile(&player))
+        .collect();
+    Json(json!({
+        "requestList": request_list,
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+/// Sends a friend request, or accepts the other player's if they already sent one.
+pub async fn social_send_friend_request(Json(payload): JSON) -> JSON {
+    let (mut local, _) = open_players();
+    let friend_id = payload["friendId"].as_str().unwrap_or("");
+    let Some(mut friend) = read_player(friend_id).filter(|friend| friend["uid"] != local["uid"]) else {
+        return error_result();
+    };
+    if friend_status(&local, &friend) == 2 || !has_room_for_friend(&local) {
+        return error_result();
+    }
+
+    if uid_list(&local, "friendRequests").iter().any(|uid| uid == friend_id) {
+        if !has_room_for_friend(&friend) {
+            return error_result();
+        }
+        befriend(&mut local, &mut friend);
+        write_player(&local);
+    } else {
+        set_in_uid_list(&mut friend, "friendRequests", local["uid"].as_str().unwrap(), true);
+    }
+    write_player(&friend);
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+/// Accepts (`action` 1) or refuses a friend request.
+pub async fn social_process_friend_request(Json(payload): JSON) -> JSON {
+    let (mut local, _) = open_players();
+    let friend_id = payload["friendId"].as_str().unwrap_or("");
+    if !uid_list(&local, "friendRequests").iter().any(|uid| uid == friend_id) {
+        return error_result();
+    }
+
+    if payload["action"].as_u64() == Some(1) {
+        let Some(mut friend) = read_player(friend_id) else {
+            return error_result();
+        };
+        if !has_room_for_friend(&local) || !has_room_for_friend(&friend) {
+            return error_result();
+        }
+        befriend(&mut local, &mut friend);
+        write_player(&friend);
+    } else {
+        set_in_uid_list(&mut local, "friendRequests", friend_id, false);
+    }
+    write_player(&local);
+    Json(json!({
+        "result": 0,
+        "friendNum": uid_list(&local, "friends").len(),
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+pub async fn social_delete_friend(Json(payload): JSON) -> JSON {
+    let (mut local, _) = open_players();
+    let friend_id = payload["friendId"].as_str().unwrap_or("");
+    if !uid_list(&local, "friends").iter().any(|uid| uid == friend_id) {
+        return error_result();
+    }
+
+    set_in_uid_list(&mut local, "friends", friend_id, false);
+    local["friendAlias"].as_object_mut().map(|alias| alias.remove(friend_id));
+    write_player(&local);
+    if let Some(mut friend) = read_player(friend_id) {
+        set_in_uid_list(&mut friend, "friends", local["uid"].as_str().unwrap(), false);
+        write_player(&friend);
+    }
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+pub async fn social_set_friend_alias(Json(payload): JSON) -> JSON {
+    let (mut local, _) = open_players();
+    let friend_id = payload["friendId"].as_str().unwrap_or("");
+    if !uid_list(&local, "friends").iter().any(|uid| uid == friend_id) {
+        return error_result();
+    }
+
+    if !local["friendAlias"].is_object() {
+        local["friendAlias"] = json!({});
+    }
+    match payload["alias"].as_str().filter(|alias| !alias.is_empty()) {
+        Some(alias) => local["friendAlias"][friend_id] = json!(alias),
+        None => {
+            local["friendAlias"].as_object_mut().unwrap().remove(friend_id);
+        }
+    }
+    write_player(&local);
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+/// Saves the local player's supports, which other players then see in their support selection.
+pub async fn social_set_assist_char_list(Json(payload): JSON) -> JSON {
+    let mut user_data = read_json(USER_JSON_PATH);
+    user_data["user"]["social"]["assistCharList"] = payload["assistCharList"].clone();
+    let mut modified = json!({
+        "social": {
+            "assistCharList": user_data["user"]["social"]["assistCharList"]
+        }
+    });
+    track_missions(&mut user_data["user"], &[], &mut modified).await;
+    write_json(USER_JSON_PATH, &user_data);
+    publish_player(&user_data["user"]);
+
+    Json(json!({
+        "result": 0,
         "playerDataDelta": {
-            "modified": {},
+            "modified": modified,
             "deleted": {}
         }
     }))
 }
 
-pub async fn social_get_sort_list_info() -> JSON {
-    Json(json!({"result": [], "playerDataDelta": {"modified": {}, "deleted": {}}}))
+/// An operator from `config/assist.json`, built as a support at the highest elite and level unless the entry says otherwise.
+/// Entries may set `evolvePhase`, `level`, `potentialRank`, `mainSkillLvl`, `specializeLevel` and `equipLevel`.
+fn support_unit(entry: &Value, [character_table, skin_table, equip_table, battleequip_table]: [&Value; 4]) -> Option<Value> {
+    let char_id = entry["charId"].as_str()?;
+    let char_data = character_table[char_id].as_object()?;
+    let phases = char_data["phases"].as_array()?;
+    let evolve_phase = entry["evolvePhase"].as_u64().unwrap_or(u64::MAX).min(phases.len() as u64 - 1);
+    let max_level = phases[evolve_phase as usize]["maxLevel"].as_u64().unwrap_or(1);
+    let specialize_level = entry["specializeLevel"].as_u64().unwrap_or(3).min(3);
+
+    let mut skills = Vec::new();
+    let mut unlocked_skills = 0;
+    for skill in char_data["skills"].as_array().unwrap_or(&Vec::new()) {
+        let unlocked = phase_index(&skill["unlockCond"]["phase"]) <= evolve_phase as i64;
+        let specializable = !skill["levelUpCostCond"].as_array().map(Vec::is_empty).unwrap_or(true);
+        unlocked_skills += unlocked as u64;
+        skills.push(json!({
+            "skillId": skill["skillId"],
+            "unlock": unlocked as u64,
+            "state": 0,
+            "specializeLevel": if unlocked && specializable && evolve_phase == 2 { specialize_level } else { 0 },
+            "completeUpgradeTime": -1
+        }));
+    }
+
+    let mut equip = json!({});
+    let current_equip = entry["currentEquip"].as_str().filter(|equip_id| {
+        evolve_phase == 2
+            && equip_table["charEquip"][char_id]
+                .as_array()
+                .is_some_and(|equips| equips.contains(&json!(equip_id)))
+    });
+    if let Some(equip_id) = current_equip {
+        let max_level = battleequip_table[equip_id]["phases"].as_array().map(Vec::len).unwrap_or(1) as u64;
+        equip[equip_id] = json!({
+            "hide": 0,
+            "locked": 0,
+            "level": entry["equipLevel"].as_u64().unwrap_or(max_level).clamp(1, max_level)
+        });
+    }
+
+    Some(json!({
+        "charId": char_id,
+        "skinId": default_skin(skin_table, char_id, evolve_phase as i64),
+        "skills": skills,
+        "mainSkillLvl": entry["mainSkillLvl"].as_u64().unwrap_or(7).clamp(1, 7),
+        "skillIndex": entry["skillIndex"].as_u64().unwrap_or(0).min(unlocked_skills.max(1) - 1),
+        "evolvePhase": evolve_phase,
+        "favorPoint": 25570,
+        "potentialRank": entry["potentialRank"].as_u64().unwrap_or(5).min(5),
+        "level": entry["level"].as_u64().unwrap_or(max_level).clamp(1, max_level),
+        "crisisRecord": {},
+        "currentEquip": current_equip,
+        "equip": equip
+    }))
 }
 
-pub async fn social_set_assist_char_list(Json(payload): JSON) -> JSON {
+/// The supports from `config/assist.json`, lent by a stand-in player that is never a friend.
+async fn support_unit_player() -> Value {
+    let character_table = update_data(CHARACTER_TABLE_URL).await;
+    let skin_table = update_data(SKIN_TABLE_URL).await;
+    let equip_table = update_data(EQUIP_TABLE_URL).await;
+    let battleequip_table = update_data(BATTLEEQUIP_TABLE_URL).await;
+    let tables = [&character_table, &skin_table, &equip_table, &battleequip_table];
+
+    let assist_chars: Vec<Value> = read_json(ASSIST_JSON_PATH)
+        .as_array()
+        .unwrap_or(&Vec::new())
+        .iter()
+        .filter_map(|entry| support_unit(entry, tables))
+        .collect();
+    json!({
+        "uid": SUPPORT_UNIT_UID,
+        "nickName": "Rhodes Island",
+        "nickNumber": "0001",
+        "level": 120,
+        "avatarId": "0",
+        "avatar": {
+            "type": "ASSISTANT",
+            "id": "char_002_amiya#1"
+        },
+        "lastOnlineTime": time(),
+        "assistCharList": assist_chars
+    })
+}
+
+/// Supports of a `profession`, from friends first, then other players, then the configured support units.
+pub async fn social_get_assist_list(Json(payload): JSON) -> JSON {
+    let character_table = update_data(CHARACTER_TABLE_URL).await;
+    let (local, others) = open_players();
+    let profession = payload["profession"].as_str().unwrap_or("");
+    let friends = uid_list(&local, "friends");
+
+    let mut lenders: Vec<(Value, bool, bool)> = others
+        .into_iter()
+        .map(|player| {
+            let is_friend = friends.iter().any(|uid| uid == player["uid"].as_str().unwrap_or(""));
+            let can_request_friend = !is_friend && friend_status(&local, &player) == 0;
+            (player, is_friend, can_request_friend)
+        })
+        .collect();
+    lenders.sort_by_key(|(_, is_friend, _)| !is_friend);
+    lenders.push((support_unit_player().await, false, false));
+
+    let mut assist_list = Vec::new();
+    for (player, is_friend, can_request_friend) in &lenders {
+        let assist_chars = player["assistCharList"].as_array().cloned().unwrap_or_default();
+        for (assist_slot_index, assist_char) in assist_chars.into_iter().enumerate() {
+            let char_id = assist_char["charId"].as_str().unwrap_or("");
+            if character_table[char_id]["profession"] != profession {
+                continue;
+            }
+            assist_list.push(json!({
+                "uid": player["uid"],
+                "aliasName": local["friendAlias"][player["uid"].as_str().unwrap_or("")],
+                "nickName": player["nickName"],
+                "nickNumber": player["nickNumber"],
+                "level": player["level"],
+                "avatarId": player["avatarId"],
+                "avatar": player["avatar"],
+                "lastOnlineTime": player["lastOnlineTime"],
+                "isOnline": is_online(player),
+                "assistCharList": [assist_char],
+                "assistSlotIndex": assist_slot_index,
+                "isFriend": is_friend,
+                "canRequestFriend": can_request_friend,
+                "powerScore": 0,
+                "medalBoard": {
+                    "type": "EMPTY",
+                    "custom": null,
+                    "template": null
+                }
+            }));
+        }
+    }
+
     Json(json!({
+        "allowAskTs": time(),
+        "assistList": assist_list,
         "playerDataDelta": {
-            "modified": {
-                "social": payload
-            },
+            "modified": {},
             "deleted": {}
         }
     }))
 }
 
+/// The credit for clearing a stage with a support (`assistFriend` from the battle start), also added to the lender's pending credit.
+pub fn assist_credit(assist_friend: &Value) -> Option<Value> {
+    let lender_uid = assist_friend["uid"].as_str()?;
+    let user_data = read_json(USER_JSON_PATH);
+    let local = publish_player(&user_data["user"]);
+    let is_friend = uid_list(&local, "friends").iter().any(|uid| uid == lender_uid);
+    let count = if is_friend { FRIEND_ASSIST_CREDIT } else { ASSIST_CREDIT };
+
+    // Only supports lent by a player in the directory or by the configured support units earn credit.
+    if lender_uid != SUPPORT_UNIT_UID {
+        let mut lender = read_player(lender_uid).filter(|lender| lender["uid"] != local["uid"])?;
+        lender["pendingCredit"] = json!(lender["pendingCredit"].as_u64().unwrap_or(0) + count);
+        write_player(&lender);
+    }
+    Some(json!({
+        "id": "4003",
+        "type": "SOCIAL_PT",
+        "count": count
+    }))
+}
+
+/// Shows an empty medal board, a saved custom layout (`customIndex`) or a medal set (`templateGroup`) on the name card.
 pub async fn social_set_card_medal(Json(payload): JSON) -> JSON {
-    let data = payload;
+    let medal_table = update_data(MEDAL_TABLE_URL).await;
+    let mut user_data = read_json(USER_JSON_PATH);
+    let board_type = payload["type"].as_str().unwrap_or("");
+    let medal_board = match board_type {
+        "EMPTY" => json!({
+            "type": board_type,
+            "custom": null,
+            "template": null
+        }),
+        "CUSTOM" => {
+            let custom_index = payload["customIndex"].as_str().unwrap_or("");
+            if !user_data["user"]["medal"]["custom"]["customs"][custom_index].is_object() {
+                return error_result();
+            }
+            json!({
+                "type": board_type,
+                "custom": custom_index,
+                "template": null
+            })
+        }
+        "TEMPLATE" => {
+            let template_group = payload["templateGroup"].as_str().unwrap_or("");
+            // A medal set can be shown once any of its medals is earned.
+            let shown = medal_group(&medal_table, template_group).is_some_and(|group| {
+                group["medalId"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|medal_id| is_earned(&user_data["user"], medal_id.as_str().unwrap_or("")))
+            });
+            if !shown {
+                return error_result();
+            }
+            json!({
+                "type": board_type,
+                "custom": null,
+                "template": template_group
+            })
+        }
+        _ => return error_result(),
+    };
+
+    user_data["user"]["social"]["medalBoard"] = medal_board.clone();
+    write_json(USER_JSON_PATH, &user_data);
+    publish_player(&user_data["user"]);
     Json(json!({
+        "result": 0,
         "playerDataDelta": {
             "modified": {
                 "social": {
-                    "medalBoard": {
-                        "type": data["type"],
-                        "template": data["templateGroup"]
-                    }
+                    "medalBoard": medal_board
                 }
             },
             "deleted": {}
         }
     }))
 }
+
+/// A player's name card as other players see it, for rendering `searchPlayer` and friend list entries.
+pub async fn social_get_player_profile(Json(payload): JSON) -> JSON {
+    let (local, _) = open_players();
+    let Some(player) = payload["uid"].as_str().and_then(read_player) else {
+        return error_result();
+    };
+    Json(json!({
+        "result": 0,
+        "player": public_profile(&player),
+        "friendStatus": friend_status(&local, &player),
+        "playerDataDelta": social_delta(&local)
+    }))
+}
+
+/// Has a directory player (`uid`) send the local player a friend request.
+pub async fn social_debug_send_friend_request(Json(payload): JSON) -> JSON {
+    let (mut local, _) = open_players();
+    let uid = payload["uid"].as_str().unwrap_or("");
+    let Some(player) = read_player(uid).filter(|player| player["uid"] != local["uid"]) else {
+        return error_result();
+    };
+    if friend_status(&local, &player) == 2 {
+        return error_result();
+    }
+
+    set_in_uid_list(&mut local, "friendRequests", uid, true);
+    write_player(&local);
+    Json(json!({
+        "result": 0,
+        "player": public_profile(&player)
+    }))
+}
+
+/// Has a directory player (`uid`) accept the local player's friend request.
+pub async fn social_debug_accept_friend_request(Json(payload): JSON) -> JSON {
+    let (mut local, _) = open_players();
+    let uid = payload["uid"].as_str().unwrap_or("");
+    let Some(mut player) = read_player(uid).filter(|player| friend_status(&local, player) == 1) else {
+        return error_result();
+    };
+    if !has_room_for_friend(&local) {
+        return error_result();
+    }
+
+    befriend(&mut local, &mut player);
+    write_player(&local);
+    write_player(&player);
+    Json(json!({
+        "result": 0,
+        "player": public_profile(&player)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn character_table() -> Value {
+        json!({
+            "char_test": {
+                "phases": [{ "maxLevel": 50 }, { "maxLevel": 70 }, { "maxLevel": 90 }],
+                "skills": [
+                    { "skillId": "skchr_test_1", "unlockCond": { "phase": "PHASE_0" }, "levelUpCostCond": [{}, {}, {}] },
+                    { "skillId": "skchr_test_2", "unlockCond": { "phase": "PHASE_1" }, "levelUpCostCond": [{}, {}, {}] },
+                    { "skillId": "skchr_test_3", "unlockCond": { "phase": "PHASE_2" }, "levelUpCostCond": [{}, {}, {}] }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn support_unit_locks_skills_above_its_elite() {
+        let character_table = character_table();
+        let tables = [&character_table, &json!({}), &json!({}), &json!({})];
+        let unit = support_unit(&json!({ "charId": "char_test", "evolvePhase": 1, "skillIndex": 2 }), tables).unwrap();
+        let unlocks: Vec<_> = unit["skills"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|skill| skill["unlock"].clone())
+            .collect();
+        assert_eq!(unlocks, [json!(1), json!(1), json!(0)]);
+        assert_eq!(unit["skillIndex"], 1);
+
+        let unit = support_unit(&json!({ "charId": "char_test", "skillIndex": 2 }), tables).unwrap();
+        assert_eq!(unit["skills"][2]["unlock"], 1);
+        assert_eq!(unit["skills"][2]["specializeLevel"], 3);
+        assert_eq!(unit["skillIndex"], 2);
+    }
+}
@@ -0,0 +1,149 @@
+use axum::Json;
+use serde_json::{json, Value};
+
+use crate::{
+    constants::{
+        config::SQUADS_PATH,
+        user::{SQUAD_EDITS_JSON_PATH, USER_JSON_PATH},
+    },
+    utils::json::{get_keys, get_values, read_json, write_json, JSON},
+};
+
+const SQUAD_SIZE: usize = 12;
+
+fn char_inst_id(chars: &Value, char_id: &str) -> Option<Value> {
+    get_values(chars)
+        .into_iter()
+        .find(|char| char["charId"] == char_id)
+        .map(|char| char["instId"].clone())
+}
+
+/// Resolves `charId` based squad presets to the player's `instId`s, skipping operators they don't own.
+pub fn resolve_squads(presets: &Value, chars: &Value) -> Value {
+    let mut squads = json!({});
+    for squad_id in get_keys(presets) {
+        let mut slots = Vec::new();
+        for slot in presets[&squad_id]["slots"].as_array().unwrap_or(&Vec::new()) {
+            if slots.len() >= SQUAD_SIZE {
+                break;
+            }
+            let Some(inst_id) = slot["charId"].as_str().and_then(|char_id| char_inst_id(chars, char_id)) else {
+                continue;
+            };
+            let current_equip = match slot["currentEquip"].as_str() {
+                Some(equip) if !chars[inst_id.to_string()]["equip"][equip].is_null() => json!(equip),
+                _ => Value::Null,
+            };
+            slots.push(json!({
+                "charInstId": inst_id,
+                "skillIndex": slot["skillIndex"],
+                "currentEquip": current_equip
+            }));
+        }
+        slots.resize(SQUAD_SIZE, Value::Null);
+
+        squads[&squad_id] = json!({
+            "squadId": squad_id,
+            "name": presets[&squad_id]["name"],
+            "slots": slots
+        });
+    }
+    squads
+}
+
+/// Turns a troop squad back into a `charId` based preset.
+fn squad_preset(squad: &Value, chars: &Value) -> Value {
+    let mut slots = Vec::new();
+    for slot in squad["slots"].as_array().unwrap_or(&Vec::new()) {
+        let inst_id = match &slot["charInstId"] {
+            Value::Number(inst_id) => inst_id.to_string(),
+            Value::String(inst_id) => inst_id.to_string(),
+            _ => continue,
+        };
+        let Some(char_id) = chars[&inst_id]["charId"].as_str() else {
+            continue;
+        };
+        slots.push(json!({
+            "charId": char_id,
+            "skillIndex": slot["skillIndex"],
+            "currentEquip": slot["currentEquip"]
+        }));
+    }
+    json!({
+        "squadId": squad["squadId"],
+        "name": squad["name"],
+        "slots": slots
+    })
+}
+
+pub fn export_squads(squads: &Value, chars: &Value) -> Value {
+    let mut presets = json!({});
+    for squad_id in get_keys(squads) {
+        presets[&squad_id] = squad_preset(&squads[&squad_id], chars);
+    }
+    presets
+}
+
+/// The presets from `config/squads.json` with the player's own squad edits laid over them.
+pub fn squad_presets() -> Value {
+    let mut presets = read_json(SQUADS_PATH);
+    let edits = read_json(SQUAD_EDITS_JSON_PATH);
+    for squad_id in get_keys(&edits) {
+        presets[&squad_id] = edits[&squad_id].clone();
+    }
+    presets
+}
+
+/// Stores a troop squad in `data/user/squads.json` so it survives the save being regenerated.
+pub fn save_squad_preset(squad_id: &str, user: &Value) {
+    let mut edits = read_json(SQUAD_EDITS_JSON_PATH);
+    edits[squad_id] = squad_preset(&user["troop"]["squads"][squad_id], &user["troop"]["chars"]);
+    write_json(SQUAD_EDITS_JSON_PATH, edits);
+}
+
+pub async fn squad_export() -> JSON {
+    let user_data = read_json(USER_JSON_PATH);
+    let troop = &user_data["user"]["troop"];
+
+    Json(json!({
+        "result": 0,
+        "squads": export_squads(&troop["squads"], &troop["chars"])
+    }))
+}
+
+/// Imports `charId` based presets, merging them into the existing ones unless `replace` is set.
+pub async fn squad_import(Json(payload): JSON) -> JSON {
+    let replace = payload["replace"].as_bool().unwrap_or(false);
+    let mut presets = if replace { json!({}) } else { read_json(SQUADS_PATH) };
+    for squad_id in get_keys(&payload["squads"]) {
+        presets[&squad_id] = payload["squads"][&squad_id].clone();
+        presets[&squad_id]["squadId"] = json!(squad_id);
+    }
+    write_json(SQUADS_PATH, &presets);
+
+    // Imported squads replace whatever the player had edited them into.
+    let mut edits = read_json(SQUAD_EDITS_JSON_PATH);
+    if let Some(edits) = edits.as_object_mut() {
+        edits.retain(|squad_id, _| !replace && payload["squads"].get(squad_id).is_none());
+    }
+    write_json(SQUAD_EDITS_JSON_PATH, edits);
+
+    let mut user_data = read_json(USER_JSON_PATH);
+    let squads = resolve_squads(&squad_presets(), &user_data["user"]["troop"]["chars"]);
+    for squad_id in get_keys(&squads) {
+        user_data["user"]["troop"]["squads"][&squad_id] = squads[&squad_id].clone();
+    }
+    write_json(USER_JSON_PATH, user_data);
+
+    Json(json!({
+        "result": 0,
+        "playerDataDelta": {
+            "modified": {
+                "troop": {
+                    "squads": squads
+                }
+            },
+            "deleted": {}
+        }
+    }))
+}
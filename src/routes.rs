@@ -4,12 +4,15 @@ use crate::{
         user::{self, app, business_card},
     },
     game::{
-        account, background, building, campaignv2,
+        account, background, battle_stats, building, campaignv2,
         char_manager::{char, char_build, charm},
+        check_in,
         crisis_manager::crisis_v2,
-        deep_sea, online, pay,
+        deep_sea, equipment, furniture,
+        gacha::normal,
+        medal, meeting, mission, online, pay, players,
         quest_manager::{april_fools, bossrush, quest, story_review},
-        shop, social, story,
+        replay, shop, social, squad, story,
     },
     utils::json::JSON,
 };
@@ -47,6 +50,9 @@ pub fn routes() -> Router {
         .nest("/config/prod", config_routes())
         .nest("/crisisV2", crisis_v2_routes())
         .nest("/deepSea", deep_sea_routes())
+        .nest("/gacha", gacha_routes())
+        .nest("/medal", medal_routes())
+        .nest("/mission", mission_routes())
         .nest("/online", online_routes())
         .nest("/quest", quest_routes())
         .nest("/retro", retro_routes())
@@ -85,6 +91,7 @@ fn activity_routes() -> Router {
         .route("/act24side/battleStart", post(quest::quest_battle_start))
         .route("/act24side/battleFinish", post(quest::quest_battle_finish))
         .route("/act24side/setTool", post(quest::set_tool))
+        .route("/getActivityCheckInReward", post(check_in::activity_get_activity_check_in_reward))
 }
 
 fn act25side_routes() -> Router {
@@ -107,12 +114,32 @@ fn april_fools_routes() -> Router {
 fn building_routes() -> Router {
     Router::new()
         .route("/sync", post(building::building_sync))
-        .route("/getRecentVisitors", post(building::building_get_recent_visitors))
-        .route("/getInfoShareVisitorsNum", post(building::building_get_info_share_visitor_num))
+        .route("/getRecentVisitors", post(meeting::building_get_recent_visitors))
+        .route("/getInfoShareVisitorsNum", post(meeting::building_get_info_share_visitor_num))
         .route("/getAssistReport", post(building::building_get_assist_report))
         .route("/changeDiySolution", post(building::building_change_diy_solution))
         .route("/assignChar", post(building::building_assign_char))
         .route("/setBuildingAssist", post(building::building_set_building_assist))
+        .route("/settleManufacture", post(building::building_settle_manufacture))
+        .route("/changeManufactureSolution", post(building::building_change_manufacture_solution))
+        .route("/deliveryOrder", post(building::building_delivery_order))
+        .route("/deliveryBatchOrder", post(building::building_delivery_batch_order))
+        .route("/workshopSynthesis", post(building::building_workshop_synthesis))
+        .route("/cleanRoomSlot", post(building::building_clean_room_slot))
+        .route("/buildRoom", post(building::building_build_room))
+        .route("/upgradeRoom", post(building::building_upgrade_room))
+        .route("/changeRoomLevel", post(building::building_change_room_level))
+        .route("/boostManufacture", post(building::building_boost_manufacture))
+        .route("/boostTrading", post(building::building_boost_trading))
+        .route("/getClueBox", post(meeting::building_get_clue_box))
+        .route("/getClueFriendList", post(meeting::building_get_clue_friend_list))
+        .route("/getDailyClue", post(meeting::building_get_daily_clue))
+        .route("/receiveClueToStock", post(meeting::building_receive_clue_to_stock))
+        .route("/sendClue", post(meeting::building_send_clue))
+        .route("/putClueToTheBoard", post(meeting::building_put_clue_to_the_board))
+        .route("/takeClueFromBoard", post(meeting::building_take_clue_from_board))
+        .route("/startInfoShare", post(meeting::building_start_info_share))
+        .route("/visitBuilding", post(meeting::building_visit_building))
 }
 
 fn business_card_routes() -> Router {
@@ -143,6 +170,17 @@ fn char_build_routes() -> Router {
         .route("/changeCharSkin", post(char_build::char_build_change_char_skin))
         .route("/setEquipment", post(char_build::char_build_set_char_equipment))
         .route("/changeCharTemplate", post(char_build::char_build_change_char_template))
+        .route("/upgradeChar", post(char_build::char_build_upgrade_char))
+        .route("/evolveChar", post(char_build::char_build_evolve_char))
+        .route("/boostPotential", post(char_build::char_build_boost_potential))
+        .route("/upgradeSkill", post(char_build::char_build_upgrade_skill))
+        .route("/upgradeSpecializedSkill", post(char_build::char_build_upgrade_specialized_skill))
+        .route(
+            "/completeUpgradeSpecialized",
+            post(char_build::char_build_complete_upgrade_specialized),
+        )
+        .route("/unlockEquipment", post(char_build::char_build_unlock_equipment))
+        .route("/upgradeEquipment", post(char_build::char_build_upgrade_equipment))
 }
 
 fn config_routes() -> Router {
@@ -169,6 +207,25 @@ fn deep_sea_routes() -> Router {
         .route("/event", post(deep_sea::deep_sea_event))
 }
 
+fn gacha_routes() -> Router {
+    Router::new()
+        .route("/normalGacha", post(normal::gacha_normal_gacha))
+        .route("/finishNormalGacha", post(normal::gacha_finish_normal_gacha))
+}
+
+fn medal_routes() -> Router {
+    Router::new()
+        .route("/setCustomData", post(medal::medal_set_custom_data))
+        .route("/rewardMedal", post(medal::medal_reward_medal))
+}
+
+fn mission_routes() -> Router {
+    Router::new()
+        .route("/confirmMission", post(mission::mission_confirm_mission))
+        .route("/autoConfirmMissions", post(mission::mission_auto_confirm_missions))
+        .route("/confirmMissionGroup", post(mission::mission_confirm_mission_group))
+}
+
 fn online_routes() -> Router {
     Router::new()
         .route("/v1/ping", post(online::online_v1_ping))
@@ -181,8 +238,9 @@ fn quest_routes() -> Router {
         .route("/battleFinish", post(quest::quest_battle_finish))
         .route("/changeSquadName", post(quest::squad_change_name))
         .route("/squadFormation", post(quest::squad_set_formation))
-        .route("/saveBattleReplay", post(quest::quest_save_battle_replay))
-        .route("/getBattleReplay", post(quest::quest_get_battle_replay))
+        .route("/saveBattleReplay", post(replay::quest_save_battle_replay))
+        .route("/getBattleReplay", post(replay::quest_get_battle_replay))
+        .route("/getAssistList", post(social::social_get_assist_list))
 }
 
 fn retro_routes() -> Router {
@@ -201,6 +259,13 @@ fn social_routes() -> Router {
         .route("/setCardShowMedal", post(social::social_set_card_medal))
         .route("/getSortListInfo", post(social::social_get_sort_list_info))
         .route("/searchPlayer", post(social::social_search_player))
+        .route("/getFriendList", post(social::social_get_friend_list))
+        .route("/getFriendRequestList", post(social::social_get_friend_request_list))
+        .route("/sendFriendRequest", post(social::social_send_friend_request))
+        .route("/processFriendRequest", post(social::social_process_friend_request))
+        .route("/deleteFriend", post(social::social_delete_friend))
+        .route("/setFriendAliasName", post(social::social_set_friend_alias))
+        .route("/getPlayerProfile", post(social::social_get_player_profile))
 }
 
 fn story_routes() -> Router {
@@ -225,9 +290,13 @@ fn user_routes() -> Router {
         .route("/auth", post(user::user_auth))
         .route("/auth/v1/token_by_phone_password", post(user::auth_v1_token_by_phone_password))
         .route("/agreement", get(user::user_agreement))
-        .route("/checkIn", get(user::user_check_in))
+        .route("/checkIn", post(user::user_check_in))
         .route("/changeAvatar", post(user::user_change_avatar))
+        .route("/changeResume", post(user::user_change_resume))
         .route("/changeSecretary", post(user::user_change_secretary))
+        .route("/buyAp", post(user::user_buy_ap))
+        .route("/useItem", post(user::user_use_item))
+        .route("/useItems", post(user::user_use_items))
         .route("/info/v1/need_cloud_auth", post(user::user_need_cloud_auth))
         .route("/info/v1/basic", get(user::info_v1_basic))
         .route("/login", post(user::user_login))
@@ -250,6 +319,26 @@ fn misc_routes() -> Router {
 
 fn debug_routes() -> Router {
     Router::new()
+        .route("/battleStats/summary", post(battle_stats::battle_stats_summary))
+        .route("/battleStats/operators", post(battle_stats::battle_stats_operators))
+        .route("/battleStats/recent", post(battle_stats::battle_stats_recent))
+        .route("/diyPreset/list", post(furniture::diy_preset_list))
+        .route("/diyPreset/save", post(furniture::diy_preset_save))
+        .route("/diyPreset/load", post(furniture::diy_preset_load))
+        .route("/diyPreset/delete", post(furniture::diy_preset_delete))
+        .route("/equipment/completeMissions", post(equipment::equipment_complete_missions))
+        .route("/players/acceptFriendRequest", post(social::social_debug_accept_friend_request))
+        .route("/players/add", post(players::players_add))
+        .route("/players/list", post(players::players_list))
+        .route("/players/sendFriendRequest", post(social::social_debug_send_friend_request))
+        .route("/players/sendClue", post(meeting::meeting_debug_send_clue))
+        .route("/replay/list", post(replay::replay_list))
+        .route("/replay/delete", post(replay::replay_delete))
+        .route("/replay/export", post(replay::replay_export))
+        .route("/replay/import", post(replay::replay_import))
+        .route("/replay/timeline", post(replay::replay_timeline))
+        .route("/squad/export", post(squad::squad_export))
+        .route("/squad/import", post(squad::squad_import))
 }
 
 async fn fallback() -> JSON {
@@ -1,3 +1,4 @@
+mod cli;
 mod constants;
 mod core;
 mod game;
@@ -11,6 +12,11 @@ use utils::{json::read_json, server::Server};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if let Some(result) = cli::run(&args) {
+        return result;
+    }
+
     // TITLE
     println!(r#"{}"#, TITLE);
     println!("IN CASE YOU PAID MONEY FOR THIS, YOU'VE BEEN SCAMMED.");
@@ -0,0 +1,61 @@
+use serde_json::{json, Value};
+
+use crate::{constants::config::CONFIG_JSON_PATH, core::time};
+
+use super::{comp::min, json::read_json};
+
+pub fn infinite_ap() -> bool {
+    read_json(CONFIG_JSON_PATH)["apConfig"]["infiniteAp"].as_bool().unwrap_or(false)
+}
+
+pub fn max_ap(const_table: &Value, level: u64) -> u64 {
+    let ap_map = const_table["playerApMap"].as_array().unwrap();
+    let index = min(level.saturating_sub(1) as usize, ap_map.len() - 1);
+    ap_map[index].as_u64().unwrap()
+}
+
+/// Regenerates sanity from `lastApAddTime` up to `maxAp` using the server clock.
+pub fn refresh_ap(status: &mut Value, const_table: &Value) {
+    let now = time();
+    let regen_time = const_table["playerApRegenSpeed"].as_u64().unwrap_or(6) * 60;
+    let ap = status["ap"].as_u64().unwrap_or(0);
+    let max_ap = status["maxAp"].as_u64().unwrap_or(0);
+    let last_ap_add_time = status["lastApAddTime"].as_u64().unwrap_or(now);
+
+    if ap >= max_ap {
+        status["lastApAddTime"] = json!(now);
+        return;
+    }
+
+    let gained = now.saturating_sub(last_ap_add_time) / regen_time;
+    if gained == 0 {
+        return;
+    }
+
+    let ap = min(ap + gained, max_ap);
+    status["ap"] = json!(ap);
+    status["lastApAddTime"] = if ap >= max_ap {
+        json!(now)
+    } else {
+        json!(last_ap_add_time + gained * regen_time)
+    };
+}
+
+/// Adds sanity without capping it, as potions and refunds may overflow `maxAp`.
+pub fn add_ap(status: &mut Value, amount: u64) {
+    let ap = status["ap"].as_u64().unwrap_or(0);
+    status["ap"] = json!(ap + amount);
+}
+
+/// Deducts sanity, returning `false` if the player can't afford it.
+pub fn cost_ap(status: &mut Value, amount: u64) -> bool {
+    if infinite_ap() {
+        return true;
+    }
+    let ap = status["ap"].as_u64().unwrap_or(0);
+    if ap < amount {
+        return false;
+    }
+    status["ap"] = json!(ap - amount);
+    true
+}
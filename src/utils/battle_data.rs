@@ -1,18 +1,95 @@
 use aes::{
-    cipher::{block_padding::NoPadding, BlockDecryptMut, KeyIvInit},
+    cipher::{
+        block_padding::{NoPadding, Pkcs7},
+        BlockDecryptMut, BlockEncryptMut, KeyIvInit,
+    },
     Aes128,
 };
-use anyhow::Result;
-use cbc::Decryptor;
-use hex::decode;
-use serde_json::Value;
+use anyhow::{anyhow, bail, Result};
+use cbc::{Decryptor, Encryptor};
+use hex::{decode, encode};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-use super::crypto::md5::md5_digest;
+use crate::constants::user::USER_JSON_PATH;
+
+use super::{crypto::md5::md5_digest, json::read_json};
 
 const DEFAULT_LOGIN_TIME: u32 = 1672502400;
 const LOG_TOKEN_KEY: &str = "pM6Umv*^hVQuB6t&";
+const IV_LENGTH: usize = 16;
+const SIGNATURE_LENGTH: usize = 32;
 
 type Aes128CbcDec = Decryptor<Aes128>;
+type Aes128CbcEnc = Encryptor<Aes128>;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BattleStats {
+    #[serde(default)]
+    pub extra_battle_info: Map<String, Value>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BattleDataInfo {
+    #[serde(default)]
+    pub complete_time: f64,
+    #[serde(default)]
+    pub stats: BattleStats,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// The decrypted `data` field the client sends when a battle finishes.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BattleData {
+    pub complete_state: i64,
+    #[serde(default)]
+    pub kill_cnt: u64,
+    #[serde(default)]
+    pub missed_cnt: u64,
+    #[serde(default)]
+    pub battle_data: BattleDataInfo,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl BattleData {
+    pub fn extra_battle_info(&self) -> &Map<String, Value> {
+        &self.battle_data.stats.extra_battle_info
+    }
+
+    /// The operators deployed at least once, each listed once, from the per-operator `charStats` of the battle stats.
+    pub fn deployed_chars(&self) -> Vec<String> {
+        let char_stats = match self.battle_data.stats.other.get("charStats") {
+            Some(Value::Array(char_stats)) => char_stats.iter().collect(),
+            Some(Value::Object(char_stats)) => char_stats.values().collect(),
+            _ => Vec::new(),
+        };
+        let mut char_ids: Vec<String> = Vec::new();
+        for char_stat in char_stats {
+            let deploy_cnt = char_stat["counter"]["deployCnt"].as_u64().or(char_stat["deployCnt"].as_u64());
+            match char_stat["charId"].as_str() {
+                Some(char_id) if deploy_cnt != Some(0) && !char_ids.iter().any(|id| id == char_id) => char_ids.push(char_id.to_string()),
+                _ => (),
+            }
+        }
+        char_ids
+    }
+}
+
+/// The login time of the current session, which is the `ts` handed out by the last `syncData`.
+pub fn session_login_time() -> u32 {
+    read_json(USER_JSON_PATH)["ts"]
+        .as_u64()
+        .map(|ts| ts as u32)
+        .unwrap_or(DEFAULT_LOGIN_TIME)
+}
 
 pub struct BattleDataDecoder {
     login_time: u32,
@@ -21,11 +98,20 @@ pub struct BattleDataDecoder {
 impl Default for BattleDataDecoder {
     fn default() -> Self {
         Self {
-            login_time: DEFAULT_LOGIN_TIME,
+            login_time: session_login_time(),
         }
     }
 }
 
+fn key_source(login_time: u32) -> String {
+    format!("{LOG_TOKEN_KEY}{login_time}")
+}
+
+/// Battle data is `hex(iv + ciphertext)` followed by the hex MD5 of that body and the key source.
+fn signature(body: &str, login_time: u32) -> String {
+    encode(md5_digest(format!("{body}{}", key_source(login_time)).as_bytes()))
+}
+
 impl BattleDataDecoder {
     pub fn new() -> Self {
         Self::default()
@@ -36,16 +122,136 @@ impl BattleDataDecoder {
         Self { login_time }
     }
 
-    pub fn decrypt_battle_data(&self, mut data: String) -> Result<Value> {
-        let data = data.drain(..data.len() - 32);
-        let data = decode(data)?;
-        let mut src = LOG_TOKEN_KEY.to_string();
-        src.push_str(&self.login_time.to_string());
-        let key = md5_digest(src.as_bytes()).to_vec();
-        let iv = decode(&data)?;
-        let aes = Aes128CbcDec::new(key.as_slice().into(), iv.as_slice().into());
-        let res = aes.decrypt_padded_vec_mut::<NoPadding>(data.as_slice())?;
+    fn key(&self) -> Vec<u8> {
+        md5_digest(key_source(self.login_time).as_bytes())
+    }
+
+    /// Decodes signed battle data, falling back to the older layout of `hex(ciphertext)` followed by the hex IV, which
+    /// carries no signature and is only accepted if it decrypts to battle data under the session key.
+    pub fn decrypt_battle_data(&self, data: String) -> Result<BattleData> {
+        if data.len() < SIGNATURE_LENGTH + IV_LENGTH * 2 || !data.is_char_boundary(data.len() - SIGNATURE_LENGTH) {
+            bail!("Battle data is too short.");
+        }
+        let (body, suffix) = data.split_at(data.len() - SIGNATURE_LENGTH);
+        if suffix.eq_ignore_ascii_case(&signature(body, self.login_time)) {
+            let body = decode(body)?;
+            let (iv, ciphertext) = body.split_at(IV_LENGTH);
+            return self.decrypt(iv, ciphertext);
+        }
+        self.decrypt(&decode(suffix)?, &decode(body)?)
+            .map_err(|_| anyhow!("Battle data signature mismatch."))
+    }
+
+    fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> Result<BattleData> {
+        let key = self.key();
+        let aes = Aes128CbcDec::new(key.as_slice().into(), iv.into());
+        let res = aes.decrypt_padded_vec_mut::<NoPadding>(ciphertext)?;
         let json_string = String::from_utf8(res)?;
-        Ok(serde_json::from_str(json_string.trim())?)
+        let json_string = json_string.trim_end_matches(|c: char| c.is_control() || c.is_whitespace());
+        Ok(serde_json::from_str(json_string.trim_start())?)
+    }
+
+    /// Encrypts and signs battle data the way the client does, so payloads can be built without a client.
+    #[allow(dead_code)]
+    pub fn encrypt_battle_data(&self, battle_data: &BattleData) -> Result<String> {
+        let mut iv = [0u8; IV_LENGTH];
+        rand::thread_rng().fill_bytes(&mut iv);
+        let key = self.key();
+        let aes = Aes128CbcEnc::new(key.as_slice().into(), iv.as_slice().into());
+        let ciphertext = aes.encrypt_padded_vec_mut::<Pkcs7>(serde_json::to_string(battle_data)?.as_bytes());
+
+        let body = encode([iv.as_slice(), ciphertext.as_slice()].concat());
+        let sign = signature(&body, self.login_time);
+        Ok(format!("{body}{sign}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const LOGIN_TIME: u32 = 1700000000;
+
+    fn battle_data() -> BattleData {
+        serde_json::from_value(json!({
+            "completeState": 3,
+            "killCnt": 42,
+            "missedCnt": 1,
+            "battleData": {
+                "completeTime": 93.5,
+                "stats": {
+                    "extraBattleInfo": { "SIMPLE,money,1": "1,2,300" },
+                    "charStats": [
+                        { "charId": "char_002_amiya", "counter": { "deployCnt": 2 } },
+                        { "charId": "char_010_chen", "counter": { "deployCnt": 0 } },
+                        { "charId": "char_002_amiya", "counter": { "deployCnt": 1 } }
+                    ]
+                }
+            },
+            "battleId": "00000000-0000-0000-0000-000000000000"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_encryption() {
+        let decoder = BattleDataDecoder::new_with_login_time(LOGIN_TIME);
+        let data = decoder.encrypt_battle_data(&battle_data()).unwrap();
+        let decoded = decoder.decrypt_battle_data(data).unwrap();
+        assert_eq!(decoded.complete_state, 3);
+        assert_eq!(decoded.kill_cnt, 42);
+        assert_eq!(decoded.missed_cnt, 1);
+        assert_eq!(decoded.battle_data.complete_time, 93.5);
+        assert_eq!(decoded.extra_battle_info()["SIMPLE,money,1"], "1,2,300");
+        assert_eq!(decoded.other["battleId"], "00000000-0000-0000-0000-000000000000");
+    }
+
+    #[test]
+    fn lists_deployed_chars_once() {
+        assert_eq!(battle_data().deployed_chars(), ["char_002_amiya"]);
+        assert!(BattleData::default().deployed_chars().is_empty());
+    }
+
+    #[test]
+    fn decodes_unsigned_layout_with_trailing_iv() {
+        let decoder = BattleDataDecoder::new_with_login_time(LOGIN_TIME);
+        let iv = [7u8; IV_LENGTH];
+        let aes = Aes128CbcEnc::new(decoder.key().as_slice().into(), iv.as_slice().into());
+        let ciphertext = aes.encrypt_padded_vec_mut::<Pkcs7>(serde_json::to_string(&battle_data()).unwrap().as_bytes());
+        let data = format!("{}{}", encode(ciphertext), encode(iv));
+
+        let decoded = decoder.decrypt_battle_data(data.clone()).unwrap();
+        assert_eq!(decoded.complete_state, 3);
+        assert_eq!(decoded.kill_cnt, 42);
+        assert!(BattleDataDecoder::new_with_login_time(LOGIN_TIME + 1)
+            .decrypt_battle_data(data)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_body() {
+        let decoder = BattleDataDecoder::new_with_login_time(LOGIN_TIME);
+        let mut data = decoder.encrypt_battle_data(&battle_data()).unwrap();
+        let flipped = if data.starts_with('0') { "1" } else { "0" };
+        data.replace_range(..1, flipped);
+        assert!(decoder.decrypt_battle_data(data).is_err());
+    }
+
+    #[test]
+    fn rejects_other_login_time() {
+        let data = BattleDataDecoder::new_with_login_time(LOGIN_TIME)
+            .encrypt_battle_data(&battle_data())
+            .unwrap();
+        assert!(BattleDataDecoder::new_with_login_time(LOGIN_TIME + 1)
+            .decrypt_battle_data(data)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_short_data() {
+        let decoder = BattleDataDecoder::new_with_login_time(LOGIN_TIME);
+        assert!(decoder.decrypt_battle_data("abcdef".to_string()).is_err());
     }
 }
@@ -1,5 +1,6 @@
 use crate::utils::crypto::base64::decode_bytes;
 use anyhow::{Ok, Result};
+use serde::Serialize;
 use serde_json::{from_str, Value};
 use zip::{ZipArchive, ZipWriter};
 
@@ -28,3 +29,111 @@ pub fn encrypt_battle_replay(data: Value) -> Result<String> {
     let bytes = zip_writer.finish()?.into_inner();
     Ok(encode_bytes(bytes))
 }
+
+/// Frames per second the client records replay logs at.
+const REPLAY_FRAME_RATE: f64 = 30.0;
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReplayAction {
+    Deploy,
+    Retreat,
+    Skill,
+    Unknown,
+}
+
+impl From<i64> for ReplayAction {
+    fn from(op: i64) -> Self {
+        match op {
+            0 => ReplayAction::Deploy,
+            1 => ReplayAction::Retreat,
+            2 => ReplayAction::Skill,
+            _ => ReplayAction::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReplayDirection {
+    Up,
+    Right,
+    Down,
+    Left,
+    None,
+}
+
+impl From<i64> for ReplayDirection {
+    fn from(direction: i64) -> Self {
+        match direction {
+            0 => ReplayDirection::Up,
+            1 => ReplayDirection::Right,
+            2 => ReplayDirection::Down,
+            3 => ReplayDirection::Left,
+            _ => ReplayDirection::None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayEvent {
+    pub frame: u64,
+    pub timestamp: f64,
+    pub action: ReplayAction,
+    pub op: i64,
+    pub char_id: String,
+    pub unique_id: i64,
+    pub row: i64,
+    pub col: i64,
+    pub direction: ReplayDirection,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayTimeline {
+    pub stage_id: String,
+    pub save_time: String,
+    pub squad: Vec<String>,
+    pub events: Vec<ReplayEvent>,
+}
+
+/// Turns the `journal` of a decrypted replay into a typed, time-ordered list of player actions.
+pub fn decode_replay_timeline(replay: &Value) -> ReplayTimeline {
+    let journal = &replay["journal"];
+    let squad = journal["squad"]
+        .as_array()
+        .unwrap_or(&Vec::new())
+        .iter()
+        .filter_map(|slot| slot["charId"].as_str().or(slot["tmplId"].as_str()).map(str::to_string))
+        .collect();
+
+    let mut events: Vec<ReplayEvent> = journal["logs"]
+        .as_array()
+        .unwrap_or(&Vec::new())
+        .iter()
+        .map(|log| {
+            let timestamp = log["timestamp"].as_f64().unwrap_or(0.0);
+            let op = log["op"].as_i64().unwrap_or(-1);
+            ReplayEvent {
+                frame: (timestamp * REPLAY_FRAME_RATE).round() as u64,
+                timestamp,
+                action: ReplayAction::from(op),
+                op,
+                char_id: log["signiture"]["charId"].as_str().unwrap_or("").to_string(),
+                unique_id: log["signiture"]["uniqueId"].as_i64().unwrap_or(-1),
+                row: log["pos"]["row"].as_i64().unwrap_or(-1),
+                col: log["pos"]["col"].as_i64().unwrap_or(-1),
+                direction: ReplayDirection::from(log["direction"].as_i64().unwrap_or(-1)),
+            }
+        })
+        .collect();
+    events.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+    ReplayTimeline {
+        stage_id: journal["metadata"]["stageId"].as_str().unwrap_or("").to_string(),
+        save_time: journal["metadata"]["saveTime"].as_str().unwrap_or("").to_string(),
+        squad,
+        events,
+    }
+}
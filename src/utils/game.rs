@@ -1,8 +1,14 @@
 use std::fmt::Display;
 
-use super::{battle_data::BattleDataDecoder, json::read_json};
+use crate::constants::config::CONFIG_JSON_PATH;
+
+use super::{
+    battle_data::{BattleData, BattleDataDecoder},
+    json::{get_values, read_json, JSON},
+};
+use axum::Json;
 use reqwest::get;
-use serde_json::Value;
+use serde_json::{json, Value};
 
 pub async fn update_data(url: &str) -> Value {
     let local_path = url
@@ -25,10 +31,103 @@ pub async fn update_data(url: &str) -> Value {
     }
 }
 
-pub fn decrypt_battle_data<T: Display>(data: T, login_time: Option<u64>) -> Value {
+pub fn decrypt_battle_data<T: Display>(data: T, login_time: Option<u64>) -> BattleData {
     let decryptor = match login_time {
         Some(time) => BattleDataDecoder::new_with_login_time(time as u32),
         None => BattleDataDecoder::new(),
     };
     decryptor.decrypt_battle_data(data.to_string()).unwrap()
 }
+
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+/// The daily reset boundary as seconds added to a timestamp before splitting it into days, from `missionConfig`
+/// (04:00 UTC+8 by default). Shifting a reset by it lands on midnight UTC of the server's calendar date.
+pub fn reset_offset() -> u64 {
+    let config = &read_json(CONFIG_JSON_PATH)["missionConfig"];
+    let reset_hour = config["resetHour"].as_i64().unwrap_or(4);
+    let utc_offset = config["utcOffset"].as_i64().unwrap_or(8);
+    ((utc_offset - reset_hour) * 60 * 60).rem_euclid(DAY_SECONDS as i64) as u64
+}
+
+/// The most recent daily reset at or before `ts`.
+pub fn day_start(ts: u64) -> u64 {
+    let offset = reset_offset();
+    ((ts + offset) / DAY_SECONDS * DAY_SECONDS).saturating_sub(offset)
+}
+
+/// The server day `ts` falls in, from 0 on Monday.
+pub fn weekday(ts: u64) -> u64 {
+    // The epoch fell on a Thursday.
+    ((ts + reset_offset()) / DAY_SECONDS + 3) % 7
+}
+
+/// The most recent weekly reset, on Monday, at or before `ts`.
+pub fn week_start(ts: u64) -> u64 {
+    day_start(ts).saturating_sub(weekday(ts) * DAY_SECONDS)
+}
+
+/// Response for a request the server refused, leaving the player data untouched.
+pub fn error_result() -> JSON {
+    Json(json!({
+        "result": 1,
+        "playerDataDelta": {
+            "modified": {},
+            "deleted": {}
+        }
+    }))
+}
+
+/// Parses `PHASE_n` (or a plain number) into the elite phase index.
+pub fn phase_index(phase: &Value) -> i64 {
+    match phase {
+        Value::String(phase) => phase.trim_start_matches("PHASE_").parse().unwrap_or(0),
+        _ => phase.as_i64().unwrap_or(0),
+    }
+}
+
+/// The skin an operator (or template) wears by default: its elite 2 art once promoted, otherwise the first one it has.
+pub fn default_skin(skin_table: &Value, char_id: &str, evolve_phase: i64) -> String {
+    let elite_skin = format!("{char_id}#2");
+    let base_skin = format!("{char_id}#1");
+    if evolve_phase == 2 && !skin_table["charSkins"][&elite_skin].is_null() {
+        return elite_skin;
+    }
+    if !skin_table["charSkins"][&base_skin].is_null() {
+        return base_skin;
+    }
+    get_values(&skin_table["charSkins"])
+        .into_iter()
+        .find(|skin| skin["tmplId"] == char_id && skin["isBuySkin"] == false)
+        .and_then(|skin| skin["skinId"].as_str().map(str::to_string))
+        .unwrap_or(base_skin)
+}
+
+/// Parses `TIER_n` (or a zero-based number) into the zero-based rarity index.
+pub fn rarity_index(rarity: &Value) -> usize {
+    match rarity {
+        Value::String(rarity) => rarity.trim_start_matches("TIER_").parse::<usize>().unwrap_or(1) - 1,
+        _ => rarity.as_u64().unwrap_or(0) as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-10-19, a Monday, at `hour`:00 UTC+8.
+    fn monday_at(hour: u64) -> u64 {
+        1792368000 - 8 * 60 * 60 + hour * 60 * 60
+    }
+
+    #[test]
+    fn resets_follow_mission_config() {
+        let config = &read_json(CONFIG_JSON_PATH)["missionConfig"];
+        let reset_hour = config["resetHour"].as_u64().unwrap_or(4);
+        assert_eq!(day_start(monday_at(reset_hour + 8)), monday_at(reset_hour));
+        assert_eq!(day_start(monday_at(reset_hour) - 1), monday_at(reset_hour) - DAY_SECONDS);
+        assert_eq!(weekday(monday_at(reset_hour + 8)), 0);
+        assert_eq!(week_start(monday_at(reset_hour + 8)), monday_at(reset_hour));
+        assert_eq!(week_start(monday_at(reset_hour) - 1), monday_at(reset_hour) - 7 * DAY_SECONDS);
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+
+use crate::{
+    constants::url::{GAMEDATA_CONST_URL, ITEM_TABLE_URL},
+    core::time,
+};
+
+use super::{ap::add_ap, game::update_data, json::get_keys, level::add_player_exp};
+
+/// Item types whose balance is a plain counter in `status`.
+const STATUS_ITEMS: [(&str, &str); 13] = [
+    ("GOLD", "gold"),
+    ("DIAMOND_SHD", "diamondShard"),
+    ("HGG_SHD", "hggShard"),
+    ("LGG_SHD", "lggShard"),
+    ("CLASSIC_SHD", "classicShard"),
+    ("SOCIAL_PT", "socialPoint"),
+    ("TKT_TRY", "practiceTicket"),
+    ("TKT_RECRUIT", "recruitLicense"),
+    ("TKT_INST_FIN", "instantFinishTicket"),
+    ("TKT_GACHA", "gachaTicket"),
+    ("TKT_GACHA_10", "tenGachaTicket"),
+    ("CLASSIC_TKT_GACHA", "classicGachaTicket"),
+    ("CLASSIC_TKT_GACHA_10", "classicTenGachaTicket"),
+];
+
+/// Sections of the player data that only hold items and are carried across `syncData`.
+const ITEM_SECTIONS: [&str; 3] = ["inventory", "consumable", "furnitureGrants"];
+
+fn status_field(item_type: &str) -> Option<&'static str> {
+    STATUS_ITEMS
+        .iter()
+        .find(|(status_type, _)| *status_type == item_type)
+        .map(|(_, field)| *field)
+}
+
+fn item_type_of(item_table: &Value, item_id: &str, item: &Value) -> String {
+    match item["type"].as_str() {
+        Some(item_type) => item_type.to_string(),
+        None => item_table["items"][item_id]["itemType"].as_str().unwrap_or("MATERIAL").to_string(),
+    }
+}
+
+/// Returns how many of an item the player owns, looked up in the section its type lives in.
+pub fn item_count(user: &Value, item_id: &str, item_type: &str) -> u64 {
+    if let Some(field) = status_field(item_type) {
+        return user["status"][field].as_u64().unwrap_or(0);
+    }
+    match item_type {
+        "DIAMOND" => user["status"]["androidDiamond"].as_u64().unwrap_or(0),
+        "AP_SUPPLY" => get_keys(&user["consumable"][item_id])
+            .iter()
+            .map(|inst_id| user["consumable"][item_id][inst_id]["count"].as_u64().unwrap_or(0))
+            .sum(),
+        "CHAR_SKIN" => user["skin"]["characterSkins"][item_id].as_u64().unwrap_or(0),
+        _ => user["inventory"][item_id].as_u64().unwrap_or(0),
+    }
+}
+
+fn set_status(user: &mut Value, field: &str, count: u64, modified: &mut Value) {
+    user["status"][field] = json!(count);
+    modified["status"][field] = json!(count);
+}
+
+fn add_item(user: &mut Value, item_id: &str, item_type: &str, count: u64, const_table: &Value, modified: &mut Value) {
+    if let Some(field) = status_field(item_type) {
+        let owned = user["status"][field].as_u64().unwrap_or(0);
+        set_status(user, field, owned + count, modified);
+        return;
+    }
+    match item_type {
+        "DIAMOND" => {
+            for field in ["androidDiamond", "iosDiamond"] {
+                let owned = user["status"][field].as_u64().unwrap_or(0);
+                set_status(user, field, owned + count, modified);
+            }
+        }
+        "EXP_PLAYER" => {
+            add_player_exp(&mut user["status"], const_table, count);
+            for field in ["level", "exp", "maxAp", "ap"] {
+                modified["status"][field] = user["status"][field].clone();
+            }
+        }
+        "AP_GAMEPLAY" => {
+            add_ap(&mut user["status"], count);
+            modified["status"]["ap"] = user["status"]["ap"].clone();
+        }
+        "AP_SUPPLY" => {
+            let owned = user["consumable"][item_id]["0"]["count"].as_u64().unwrap_or(0);
+            user["consumable"][item_id]["0"] = json!({
+                "ts": -1,
+                "count": owned + count
+            });
+            modified["consumable"][item_id]["0"] = user["consumable"][item_id]["0"].clone();
+        }
+        "CHAR_SKIN" => {
+            user["skin"]["characterSkins"][item_id] = json!(1);
+            user["skin"]["skinTs"][item_id] = json!(time());
+            modified["skin"]["characterSkins"][item_id] = json!(1);
+            modified["skin"]["skinTs"][item_id] = json!(time());
+        }
+        "CHARM" => {
+            let owned = user["charm"]["charms"][item_id].as_u64().unwrap_or(0);
+            user["charm"]["charms"][item_id] = json!(owned + count);
+            modified["charm"]["charms"][item_id] = json!(owned + count);
+        }
+        "PLAYER_AVATAR" => {
+            user["avatar"]["avatar_icon"][item_id] = json!({
+                "ts": time(),
+                "src": "other"
+            });
+            modified["avatar"]["avatar_icon"][item_id] = user["avatar"]["avatar_icon"][item_id].clone();
+        }
+        // Furniture is owned by the base, so it waits in `furnitureGrants` until the base is next opened.
+        "FURN" => {
+            let granted = user["furnitureGrants"][item_id].as_u64().unwrap_or(0);
+            user["furnitureGrants"][item_id] = json!(granted + count);
+        }
+        _ => {
+            let owned = user["inventory"][item_id].as_u64().unwrap_or(0);
+            user["inventory"][item_id] = json!(owned + count);
+            modified["inventory"][item_id] = json!(owned + count);
+        }
+    }
+}
+
+fn remove_item(user: &mut Value, item_id: &str, item_type: &str, count: u64, modified: &mut Value) {
+    if let Some(field) = status_field(item_type) {
+        let owned = user["status"][field].as_u64().unwrap_or(0);
+        set_status(user, field, owned - count, modified);
+        return;
+    }
+    match item_type {
+        "DIAMOND" => {
+            for field in ["androidDiamond", "iosDiamond"] {
+                let owned = user["status"][field].as_u64().unwrap_or(0);
+                set_status(user, field, owned.saturating_sub(count), modified);
+            }
+        }
+        "AP_SUPPLY" => {
+            let mut remaining = count;
+            for inst_id in get_keys(&user["consumable"][item_id]) {
+                if remaining == 0 {
+                    break;
+                }
+                let owned = user["consumable"][item_id][&inst_id]["count"].as_u64().unwrap_or(0);
+                let used = owned.min(remaining);
+                user["consumable"][item_id][&inst_id]["count"] = json!(owned - used);
+                modified["consumable"][item_id][&inst_id] = user["consumable"][item_id][&inst_id].clone();
+                remaining -= used;
+            }
+        }
+        _ => {
+            let owned = user["inventory"][item_id].as_u64().unwrap_or(0);
+            user["inventory"][item_id] = json!(owned - count);
+            modified["inventory"][item_id] = json!(owned - count);
+        }
+    }
+}
+
+/// Merges `{ id, count, type }` entries of the same item so balances are checked once per item, skipping entries without an id.
+fn merge_items(item_table: &Value, items: &[Value]) -> Vec<(String, String, u64)> {
+    let mut totals: Vec<(String, String, u64)> = Vec::new();
+    for item in items {
+        let Some(item_id) = item["id"].as_str() else {
+            continue;
+        };
+        let count = item["count"].as_u64().unwrap_or(0);
+        if count == 0 {
+            continue;
+        }
+        match totals.iter_mut().find(|(id, _, _)| id == item_id) {
+            Some((_, _, total)) => *total += count,
+            None => totals.push((item_id.to_string(), item_type_of(item_table, item_id, item), count)),
+        }
+    }
+    totals
+}
+
+/// Grants a list of `{ id, count, type }` items, routing each type to its section of the player data.
+/// Returns the granted items as a standard `items` reward array.
+pub async fn grant_items(user: &mut Value, items: &[Value], modified: &mut Value) -> Vec<Value> {
+    let item_table = update_data(ITEM_TABLE_URL).await;
+    let const_table = update_data(GAMEDATA_CONST_URL).await;
+
+    let mut rewards = Vec::new();
+    for (item_id, item_type, count) in merge_items(&item_table, items) {
+        add_item(user, &item_id, &item_type, count, &const_table, modified);
+        rewards.push(json!({
+            "type": item_type,
+            "id": item_id,
+            "count": count
+        }));
+    }
+    rewards
+}
+
+/// Consumes a list of `{ id, count, type }` costs, failing without side effects if any balance is short.
+pub async fn consume_items(user: &mut Value, items: &[Value], modified: &mut Value) -> Result<()> {
+    let item_table = update_data(ITEM_TABLE_URL).await;
+    let totals = merge_items(&item_table, items);
+
+    for (item_id, item_type, count) in &totals {
+        if item_count(user, item_id, item_type) < *count {
+            bail!("Not enough {item_id}.");
+        }
+    }
+    for (item_id, item_type, count) in &totals {
+        remove_item(user, item_id, item_type, *count, modified);
+    }
+    Ok(())
+}
+
+/// Carries item balances from a previous save into a freshly generated one.
+pub fn carry_over_items(previous_user: &Value, user: &mut Value) {
+    for section in ITEM_SECTIONS {
+        if previous_user[section].is_object() {
+            user[section] = previous_user[section].clone();
+        }
+    }
+    for (_, field) in STATUS_ITEMS {
+        if previous_user["status"][field].is_u64() {
+            user["status"][field] = previous_user["status"][field].clone();
+        }
+    }
+    for field in ["androidDiamond", "iosDiamond"] {
+        if previous_user["status"][field].is_u64() {
+            user["status"][field] = previous_user["status"][field].clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_entries_without_an_id() {
+        let item_table = json!({ "items": { "30012": { "itemType": "MATERIAL" } } });
+        let items = [
+            json!({ "id": "30012", "count": 2 }),
+            json!({ "count": 5, "type": "GOLD" }),
+            json!({ "id": 4001, "count": 5 }),
+            json!({ "id": "30012", "count": 1 }),
+        ];
+        assert_eq!(
+            merge_items(&item_table, &items),
+            vec![("30012".to_string(), "MATERIAL".to_string(), 3)]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use serde_json::{json, Value};
+
+use super::{
+    ap::{add_ap, max_ap},
+    comp::min,
+};
+
+/// Adds player EXP, levelling up through `playerExpMap`.
+/// Every level-up raises `maxAp` and refills sanity by the new cap.
+pub fn add_player_exp(status: &mut Value, const_table: &Value, exp: u64) {
+    let max_level = const_table["maxPlayerLevel"].as_u64().unwrap();
+    let exp_map = const_table["playerExpMap"].as_array().unwrap();
+    let mut level = status["level"].as_u64().unwrap_or(1);
+    let mut player_exp = status["exp"].as_u64().unwrap_or(0) + exp;
+
+    while level < max_level {
+        let required = exp_map[level as usize - 1].as_u64().unwrap();
+        if player_exp < required {
+            break;
+        }
+        player_exp -= required;
+        level += 1;
+
+        let new_max_ap = max_ap(const_table, level);
+        status["maxAp"] = json!(new_max_ap);
+        add_ap(status, new_max_ap);
+    }
+    if level >= max_level {
+        player_exp = 0;
+    }
+
+    status["level"] = json!(level);
+    status["exp"] = json!(player_exp);
+}
+
+pub fn add_favor_point(favor_table: &Value, favor_point: u64, amount: u64) -> u64 {
+    let max_favor = favor_table["maxFavor"].as_u64().unwrap();
+    min(favor_point + amount, max_favor)
+}
+
+/// Adds favor to the squad operators that were deployed in a battle (`deployed`, by char id), keeping `charGroup` in sync.
+pub fn add_squad_favor(user: &mut Value, favor_table: &Value, squad: &Value, deployed: &[String], favor: u64, modified: &mut Value) {
+    for slot in squad.as_array().unwrap_or(&Vec::new()) {
+        let inst_id = match &slot["charInstId"] {
+            Value::Number(inst_id) => inst_id.to_string(),
+            Value::String(inst_id) => inst_id.to_string(),
+            _ => continue,
+        };
+        let char = &user["troop"]["chars"][&inst_id];
+        let char_id = match char["charId"].as_str() {
+            Some(char_id) => char_id.to_string(),
+            None => continue,
+        };
+        // Templates deploy under their own id.
+        let form_id = char["currentTmpl"].as_str().unwrap_or(&char_id);
+        if !deployed.iter().any(|id| *id == char_id || id == form_id) {
+            continue;
+        }
+        let char_data = &mut user["troop"]["chars"][&inst_id];
+        let favor_point = add_favor_point(favor_table, char_data["favorPoint"].as_u64().unwrap_or(0), favor);
+        char_data["favorPoint"] = json!(favor_point);
+        user["troop"]["charGroup"][&char_id]["favorPoint"] = json!(favor_point);
+        modified["troop"]["chars"][&inst_id] = json!({ "favorPoint": favor_point });
+        modified["troop"]["charGroup"][&char_id] = json!({ "favorPoint": favor_point });
+    }
+}
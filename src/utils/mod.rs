@@ -1,9 +1,12 @@
+pub mod ap;
 pub mod battle_data;
 pub mod battle_replay;
 pub mod comp;
 pub mod crypto;
 pub mod game;
+pub mod item;
 pub mod json;
+pub mod level;
 pub mod random;
 pub mod rlutils;
 pub mod server;